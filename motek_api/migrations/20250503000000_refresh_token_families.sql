@@ -0,0 +1,6 @@
+-- Refresh token rotation: every token belongs to a family started at login.
+-- Rotated tokens stay in the table (revoked) so a replay can be detected.
+ALTER TABLE refresh_tokens
+    ADD COLUMN IF NOT EXISTS family_id UUID NOT NULL DEFAULT gen_random_uuid();
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family_id ON refresh_tokens (family_id);
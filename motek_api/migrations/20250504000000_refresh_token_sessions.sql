@@ -0,0 +1,8 @@
+-- Device/session metadata recorded when a refresh token family is created at login.
+ALTER TABLE refresh_tokens
+    ADD COLUMN IF NOT EXISTS platform     TEXT,
+    ADD COLUMN IF NOT EXISTS ip_address   TEXT,
+    ADD COLUMN IF NOT EXISTS user_agent   TEXT,
+    ADD COLUMN IF NOT EXISTS last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user_id ON refresh_tokens (user_id);
@@ -0,0 +1,6 @@
+-- Accounts scheduled for deletion are purged once the grace period is over.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS scheduled_deletion_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_users_scheduled_deletion_at
+    ON users (scheduled_deletion_at)
+    WHERE scheduled_deletion_at IS NOT NULL;
@@ -0,0 +1,73 @@
+-- Full-text search over notes: title, string values inside the JSON content, and tags.
+-- Each note is indexed with the text search configuration of its owner's language
+-- (user_settings.lang) at the time it was last written; searches use the same
+-- configuration per note. Polish requires a 'polish' configuration installed on the
+-- server (e.g. from hunspell dictionaries); unknown or missing languages use 'simple'.
+
+CREATE OR REPLACE FUNCTION motek_search_config(p_lang TEXT) RETURNS regconfig
+LANGUAGE plpgsql STABLE AS $$
+DECLARE
+    cfg TEXT;
+BEGIN
+    cfg := CASE lower(split_part(COALESCE(p_lang, ''), '-', 1))
+        WHEN 'en' THEN 'english'
+        WHEN 'pl' THEN 'polish'
+        WHEN 'de' THEN 'german'
+        WHEN 'fr' THEN 'french'
+        WHEN 'es' THEN 'spanish'
+        ELSE 'simple'
+    END;
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = cfg) THEN
+        cfg := 'simple';
+    END IF;
+    RETURN cfg::regconfig;
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION motek_user_search_config(p_user_id UUID) RETURNS regconfig
+LANGUAGE sql STABLE AS $$
+    SELECT motek_search_config(
+        (SELECT lang FROM user_settings WHERE user_id = p_user_id ORDER BY created_at LIMIT 1)
+    );
+$$;
+
+-- All string values of a JSON document (object keys excluded), space separated.
+CREATE OR REPLACE FUNCTION motek_jsonb_text(doc JSONB) RETURNS TEXT
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT COALESCE(string_agg(v #>> '{}', ' '), '')
+    FROM jsonb_path_query(COALESCE(doc, 'null'::jsonb), 'strict $.** ? (@.type() == "string")') AS v;
+$$;
+
+CREATE OR REPLACE FUNCTION motek_note_search_vector(
+    cfg regconfig, title TEXT, content JSONB, tags JSONB
+) RETURNS tsvector
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT setweight(to_tsvector(cfg, COALESCE(title, '')), 'A')
+        || setweight(to_tsvector(cfg, motek_jsonb_text(tags)), 'B')
+        || setweight(to_tsvector(cfg, motek_jsonb_text(content)), 'C');
+$$;
+
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS search_config regconfig NOT NULL DEFAULT 'simple';
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS search_vector tsvector;
+
+-- Backfill without firing the updated_at trigger
+ALTER TABLE notes DISABLE TRIGGER USER;
+UPDATE notes SET search_config = motek_user_search_config(user_id);
+UPDATE notes SET search_vector = motek_note_search_vector(search_config, title, content, tags);
+ALTER TABLE notes ENABLE TRIGGER USER;
+
+CREATE INDEX IF NOT EXISTS idx_notes_search_vector ON notes USING GIN (search_vector);
+
+CREATE OR REPLACE FUNCTION notes_search_vector_update() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+    NEW.search_config := motek_user_search_config(NEW.user_id);
+    NEW.search_vector := motek_note_search_vector(NEW.search_config, NEW.title, NEW.content, NEW.tags);
+    RETURN NEW;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS notes_search_vector ON notes;
+CREATE TRIGGER notes_search_vector
+    BEFORE INSERT OR UPDATE OF title, content, tags ON notes
+    FOR EACH ROW EXECUTE FUNCTION notes_search_vector_update();
@@ -0,0 +1,62 @@
+-- Normalized per-user tags. notes.tags stays as a denormalized, sorted array of tag
+-- names (used by search and returned with notes); note_tags is the source of truth.
+CREATE TABLE IF NOT EXISTS tags (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id     UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name        TEXT NOT NULL CHECK (length(name) BETWEEN 1 AND 64),
+    color       TEXT CHECK (color ~ '^#[0-9a-fA-F]{6}$'),
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tags_user_name ON tags (user_id, lower(name));
+
+CREATE TABLE IF NOT EXISTS note_tags (
+    note_id  UUID NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    tag_id   UUID NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    PRIMARY KEY (note_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_note_tags_tag_id ON note_tags (tag_id);
+
+-- Existing tags: arrays keep their string elements, a string is read as a comma-separated
+-- list, anything else is dropped.
+CREATE TEMP TABLE legacy_note_tags ON COMMIT DROP AS
+SELECT note_id, user_id, name FROM (
+    SELECT n.id AS note_id, n.user_id, left(btrim(e.value #>> '{}'), 64) AS name
+    FROM notes n
+    CROSS JOIN LATERAL jsonb_array_elements(
+        CASE WHEN jsonb_typeof(n.tags) = 'array' THEN n.tags ELSE '[]'::jsonb END
+    ) AS e
+    WHERE jsonb_typeof(e.value) = 'string'
+    UNION ALL
+    SELECT n.id, n.user_id, left(btrim(s), 64)
+    FROM notes n
+    CROSS JOIN LATERAL regexp_split_to_table(n.tags #>> '{}', ',') AS s
+    WHERE jsonb_typeof(n.tags) = 'string'
+) raw
+WHERE name <> '';
+
+INSERT INTO tags (user_id, name)
+SELECT DISTINCT ON (user_id, lower(name)) user_id, name FROM legacy_note_tags
+ORDER BY user_id, lower(name), name
+ON CONFLICT DO NOTHING;
+
+INSERT INTO note_tags (note_id, tag_id)
+SELECT DISTINCT l.note_id, t.id
+FROM legacy_note_tags l
+JOIN tags t ON t.user_id = l.user_id AND lower(t.name) = lower(l.name)
+ON CONFLICT DO NOTHING;
+
+-- Rewrite notes.tags from note_tags without firing the updated_at trigger
+ALTER TABLE notes DISABLE TRIGGER USER;
+UPDATE notes n SET tags = COALESCE(
+    (SELECT jsonb_agg(t.name ORDER BY lower(t.name))
+     FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+     WHERE nt.note_id = n.id),
+    '[]'::jsonb
+);
+UPDATE notes SET search_vector = motek_note_search_vector(search_config, title, content, tags);
+ALTER TABLE notes ENABLE TRIGGER USER;
+
+ALTER TABLE notes ALTER COLUMN tags SET DEFAULT '[]'::jsonb;
@@ -0,0 +1,5 @@
+-- Note version history: title snapshots and one row per version number.
+ALTER TABLE note_versions ADD COLUMN IF NOT EXISTS title TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_note_versions_note_version_no
+    ON note_versions (note_id, version_no);
@@ -0,0 +1,15 @@
+-- Revision counter of notes, increased by every update; used as the ETag of a note.
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS revision BIGINT NOT NULL DEFAULT 1;
+
+CREATE OR REPLACE FUNCTION notes_bump_revision() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+    NEW.revision := OLD.revision + 1;
+    RETURN NEW;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS notes_bump_revision ON notes;
+CREATE TRIGGER notes_bump_revision
+    BEFORE UPDATE ON notes
+    FOR EACH ROW EXECUTE FUNCTION notes_bump_revision();
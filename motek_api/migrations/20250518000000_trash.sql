@@ -0,0 +1,6 @@
+-- Trash: deleted notes and notebooks keep their rows until purged.
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE notebooks ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_notes_deleted_at ON notes (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_notebooks_deleted_at ON notebooks (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,81 @@
+-- Note content becomes a typed block document: {"version": 1, "blocks": [...]}.
+-- content_text holds its plain text, written by the API (NoteContent::plain_text), and
+-- replaces the JSON string values as the content part of the search vector.
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS content_text TEXT NOT NULL DEFAULT '';
+
+-- Converts legacy content like NoteContent::from_legacy (see its tests): a string becomes
+-- one paragraph per line, any other JSON a single paragraph of its non-empty string values
+-- joined by spaces. Values of objects with several keys may come in another order, since
+-- jsonb orders keys by length first.
+CREATE OR REPLACE FUNCTION motek_legacy_note_text(doc JSONB) RETURNS TEXT
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT COALESCE(string_agg(v #>> '{}', ' '), '')
+    FROM jsonb_path_query(doc, 'strict $.** ? (@.type() == "string" && @ != "")') AS v;
+$$;
+
+CREATE OR REPLACE FUNCTION motek_legacy_note_content(doc JSONB) RETURNS JSONB
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT CASE
+        WHEN jsonb_typeof(doc) = 'object' AND doc -> 'version' = '1'
+             AND jsonb_typeof(doc -> 'blocks') = 'array' THEN doc
+        WHEN jsonb_typeof(doc) = 'string' THEN jsonb_build_object(
+            'version', 1,
+            'blocks', COALESCE(
+                (SELECT jsonb_agg(jsonb_build_object('type', 'paragraph', 'text', l.line) ORDER BY l.n)
+                 FROM regexp_split_to_table(doc #>> '{}', E'\r?\n') WITH ORDINALITY AS l(line, n)
+                 WHERE doc #>> '{}' <> ''),
+                '[]'::jsonb))
+        WHEN motek_legacy_note_text(doc) = '' THEN '{"version": 1, "blocks": []}'::jsonb
+        ELSE jsonb_build_object(
+            'version', 1,
+            'blocks', jsonb_build_array(
+                jsonb_build_object('type', 'paragraph', 'text', motek_legacy_note_text(doc))))
+    END;
+$$;
+
+ALTER TABLE notes DISABLE TRIGGER USER;
+
+-- Structured legacy content is only kept as text; save the original as a version first
+INSERT INTO note_versions (id, note_id, version_no, title, content)
+SELECT gen_random_uuid(), n.id,
+       COALESCE((SELECT MAX(v.version_no) FROM note_versions v WHERE v.note_id = n.id), 0) + 1,
+       n.title, n.content
+FROM notes n
+WHERE jsonb_typeof(n.content) IN ('object', 'array')
+  AND NOT (n.content -> 'version' = '1' AND jsonb_typeof(n.content -> 'blocks') = 'array');
+
+UPDATE notes SET content = motek_legacy_note_content(content);
+-- Converted documents only contain paragraphs
+UPDATE notes SET content_text = COALESCE(
+    (SELECT string_agg(b.block ->> 'text', E'\n' ORDER BY b.n)
+     FROM jsonb_array_elements(content -> 'blocks') WITH ORDINALITY AS b(block, n)),
+    '');
+
+DROP TRIGGER IF EXISTS notes_search_vector ON notes;
+DROP FUNCTION IF EXISTS motek_note_search_vector(regconfig, TEXT, JSONB, JSONB);
+
+CREATE OR REPLACE FUNCTION motek_note_search_vector(
+    cfg regconfig, title TEXT, content_text TEXT, tags JSONB
+) RETURNS tsvector
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT setweight(to_tsvector(cfg, COALESCE(title, '')), 'A')
+        || setweight(to_tsvector(cfg, motek_jsonb_text(tags)), 'B')
+        || setweight(to_tsvector(cfg, COALESCE(content_text, '')), 'C');
+$$;
+
+CREATE OR REPLACE FUNCTION notes_search_vector_update() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+    NEW.search_config := motek_user_search_config(NEW.user_id);
+    NEW.search_vector := motek_note_search_vector(NEW.search_config, NEW.title, NEW.content_text, NEW.tags);
+    RETURN NEW;
+END;
+$$;
+
+UPDATE notes SET search_vector = motek_note_search_vector(search_config, title, content_text, tags);
+
+ALTER TABLE notes ENABLE TRIGGER USER;
+
+CREATE TRIGGER notes_search_vector
+    BEFORE INSERT OR UPDATE OF title, content_text, tags ON notes
+    FOR EACH ROW EXECUTE FUNCTION notes_search_vector_update();
@@ -0,0 +1,27 @@
+-- Background imports of notes from other applications, with progress and per-item errors.
+CREATE TABLE IF NOT EXISTS import_jobs (
+    id               UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id          UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    format           TEXT NOT NULL,
+    -- Notebook receiving the imported notes and folders; NULL for the top level
+    notebook_id      UUID REFERENCES notebooks (id) ON DELETE SET NULL,
+    status           TEXT NOT NULL DEFAULT 'pending'
+                     CHECK (status IN ('pending', 'running', 'completed', 'failed')),
+    total_items      INTEGER NOT NULL DEFAULT 0,
+    processed_items  INTEGER NOT NULL DEFAULT 0,
+    imported_notes   INTEGER NOT NULL DEFAULT 0,
+    failed_items     INTEGER NOT NULL DEFAULT 0,
+    -- [{"item": "...", "error": "..."}]
+    errors           JSONB NOT NULL DEFAULT '[]',
+    -- Why the whole import failed
+    error            TEXT,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Last progress report; jobs that stop reporting were interrupted
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at      TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_jobs_user_id ON import_jobs (user_id, created_at DESC);
+-- One unfinished import per user: uploads are held in memory while they are imported
+CREATE UNIQUE INDEX IF NOT EXISTS idx_import_jobs_unfinished ON import_jobs (user_id)
+    WHERE status IN ('pending', 'running');
@@ -0,0 +1,3 @@
+-- Files the server stored itself, as a path relative to `attachment_dir`. Only these are
+-- read from disk; `url` is set by clients and never used as a local path.
+ALTER TABLE attachments ADD COLUMN IF NOT EXISTS storage_key TEXT;
@@ -0,0 +1,40 @@
+-- Searches build one query in the language of the user (motek_user_search_config) so the
+-- GIN index on search_vector can be used. All notes of a user are therefore kept indexed
+-- in that language: changing or removing the user's settings re-indexes them.
+
+-- Re-indexing is not an edit of the note, so it keeps the revision (ETag)
+DROP TRIGGER IF EXISTS notes_bump_revision ON notes;
+CREATE TRIGGER notes_bump_revision
+    BEFORE UPDATE ON notes
+    FOR EACH ROW
+    WHEN (OLD.search_config IS NOT DISTINCT FROM NEW.search_config)
+    EXECUTE FUNCTION notes_bump_revision();
+
+CREATE OR REPLACE FUNCTION motek_reindex_user_notes(p_user_id UUID) RETURNS void
+LANGUAGE sql AS $$
+    UPDATE notes
+    SET search_config = c.cfg,
+        search_vector = motek_note_search_vector(c.cfg, title, content_text, tags)
+    FROM (SELECT motek_user_search_config(p_user_id) AS cfg) c
+    WHERE user_id = p_user_id AND search_config <> c.cfg;
+$$;
+
+CREATE OR REPLACE FUNCTION user_settings_reindex_notes() RETURNS trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        PERFORM motek_reindex_user_notes(OLD.user_id);
+    ELSE
+        PERFORM motek_reindex_user_notes(NEW.user_id);
+    END IF;
+    RETURN NULL;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS user_settings_reindex_notes ON user_settings;
+CREATE TRIGGER user_settings_reindex_notes
+    AFTER INSERT OR DELETE OR UPDATE OF lang ON user_settings
+    FOR EACH ROW EXECUTE FUNCTION user_settings_reindex_notes();
+
+-- Notes indexed before a language change
+SELECT motek_reindex_user_notes(u.user_id) FROM (SELECT DISTINCT user_id FROM notes) u;
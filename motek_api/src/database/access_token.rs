@@ -0,0 +1,92 @@
+//! Personal access token storage.
+
+use crate::database::token::{generate_refresh_token, hash_token};
+use crate::models::personal_access_token::{PersonalAccessToken, Scope};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+/// Prefix of every personal access token; lets `auth_middleware` tell them apart from JWTs.
+pub const ACCESS_TOKEN_PREFIX: &str = "mtk_";
+
+/// Creates a personal access token and returns it together with the plain token.
+/// The plain token is only available here; the database keeps its hash.
+pub async fn create_access_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    scopes: &[Scope],
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(PersonalAccessToken, String), sqlx::Error> {
+    let token = format!("{}{}", ACCESS_TOKEN_PREFIX, generate_refresh_token());
+    let scopes: Vec<&str> = scopes.iter().map(Scope::as_str).collect();
+
+    let rec = sqlx::query_as::<_, PersonalAccessToken>(
+        "INSERT INTO personal_access_tokens (user_id, name, token_hash, token_prefix, scopes, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING *",
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(hash_token(&token))
+    .bind(&token[..ACCESS_TOKEN_PREFIX.len() + 6])
+    .bind(&scopes)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    info!(
+        "Created access token {} '{}' for user_id={}",
+        rec.id, rec.name, user_id
+    );
+    Ok((rec, token))
+}
+
+/// Lists the non-revoked access tokens of a user, newest first.
+pub async fn list_access_tokens(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<PersonalAccessToken>, sqlx::Error> {
+    sqlx::query_as::<_, PersonalAccessToken>(
+        "SELECT * FROM personal_access_tokens
+         WHERE user_id = $1 AND revoked_at IS NULL
+         ORDER BY created_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Revokes one access token of a user. Returns the number of revoked tokens (0 or 1).
+pub async fn revoke_access_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query(
+        "UPDATE personal_access_tokens SET revoked_at = NOW()
+         WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+/// Looks up a valid (not revoked, not expired) access token and records its use.
+pub async fn authenticate_access_token(
+    pool: &PgPool,
+    token: &str,
+) -> Result<Option<PersonalAccessToken>, sqlx::Error> {
+    sqlx::query_as::<_, PersonalAccessToken>(
+        "UPDATE personal_access_tokens SET last_used_at = NOW()
+         WHERE token_hash = $1 AND revoked_at IS NULL
+           AND (expires_at IS NULL OR expires_at > NOW())
+         RETURNING *",
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await
+}
@@ -0,0 +1,285 @@
+//! Account deletion and data export.
+
+use crate::models::{
+    attachment::Attachment, note::Note, note_settings::NoteSettings, note_version::NoteVersion,
+    notebook::Notebook, personal_access_token::PersonalAccessToken, reminder::Reminder,
+    shared_note::SharedNote, tag::Tag, user::User, user_identity::UserIdentity,
+    user_settings::UserSettings,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Tables holding per-note data, deleted before the notes themselves.
+pub const NOTE_CHILD_TABLES: &[&str] = &[
+    "attachments",
+    "reminders",
+    "note_settings",
+    "note_versions",
+    "note_tags",
+];
+
+/// Tables holding per-user data, deleted right before the user row.
+const USER_CHILD_TABLES: &[&str] = &[
+    "notebooks",
+    "tags",
+    "import_jobs",
+    "user_settings",
+    "refresh_tokens",
+    "password_reset_tokens",
+    "personal_access_tokens",
+    "user_identities",
+    "revoked_access_tokens",
+    "recovery_codes",
+    "user_totp",
+];
+
+/// Schedules the account for deletion after `grace_days` and logs out every device.
+/// Returns the time at which the account will be purged.
+pub async fn schedule_account_deletion(
+    pool: &PgPool,
+    user_id: Uuid,
+    grace_days: i64,
+) -> Result<DateTime<Utc>, sqlx::Error> {
+    let scheduled_at = Utc::now() + Duration::days(grace_days);
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE users SET scheduled_deletion_at = $1 WHERE id = $2")
+        .bind(scheduled_at)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE refresh_tokens SET revoked = TRUE WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!(
+        "Account {} scheduled for deletion at {}",
+        user_id, scheduled_at
+    );
+    Ok(scheduled_at)
+}
+
+/// Cancels a pending account deletion. Returns true if one was pending.
+pub async fn cancel_account_deletion(pool: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query(
+        "UPDATE users SET scheduled_deletion_at = NULL
+         WHERE id = $1 AND scheduled_deletion_at IS NOT NULL",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Permanently removes all accounts whose grace period is over.
+/// Each account is purged in its own transaction; returns the number of purged accounts.
+pub async fn purge_deleted_accounts(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let ids: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM users WHERE scheduled_deletion_at IS NOT NULL AND scheduled_deletion_at <= NOW()",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut purged = 0;
+    for user_id in ids {
+        let mut tx = pool.begin().await?;
+        match purge_user(&mut tx, user_id).await {
+            Ok(()) => {
+                tx.commit().await?;
+                info!("Purged account {}", user_id);
+                purged += 1;
+            }
+            Err(e) => {
+                error!("Failed to purge account {}: {}", user_id, e);
+                tx.rollback().await?;
+            }
+        }
+    }
+    Ok(purged)
+}
+
+/// Deletes the user row and everything owned by it.
+async fn purge_user(tx: &mut Transaction<'_, Postgres>, user_id: Uuid) -> Result<(), sqlx::Error> {
+    for table in NOTE_CHILD_TABLES {
+        sqlx::query(&format!(
+            "DELETE FROM {} WHERE note_id IN (SELECT id FROM notes WHERE user_id = $1)",
+            table
+        ))
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+    }
+    // Shares of the user's notes and notes shared with the user
+    sqlx::query(
+        "DELETE FROM shared_note
+         WHERE user_id = $1 OR note_id IN (SELECT id FROM notes WHERE user_id = $1)",
+    )
+    .bind(user_id)
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query("DELETE FROM notes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+    for table in USER_CHILD_TABLES {
+        sqlx::query(&format!("DELETE FROM {} WHERE user_id = $1", table))
+            .bind(user_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+    sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Account data without the password hash.
+#[derive(Debug, Serialize)]
+pub struct ExportedUser {
+    pub id: Uuid,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub scheduled_deletion_at: Option<DateTime<Utc>>,
+    pub two_factor_enabled: bool,
+}
+
+/// Session (refresh token) metadata; token hashes are never exported.
+#[derive(Debug, FromRow, Serialize)]
+pub struct ExportedSession {
+    pub family_id: Uuid,
+    pub platform: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked: bool,
+}
+
+/// Everything stored about one user, grouped by table.
+#[derive(Debug, Serialize)]
+pub struct AccountExport {
+    pub exported_at: DateTime<Utc>,
+    pub user: ExportedUser,
+    pub user_settings: Vec<UserSettings>,
+    pub notebooks: Vec<Notebook>,
+    pub tags: Vec<Tag>,
+    pub notes: Vec<Note>,
+    pub note_settings: Vec<NoteSettings>,
+    pub note_versions: Vec<NoteVersion>,
+    pub attachments: Vec<Attachment>,
+    pub reminders: Vec<Reminder>,
+    pub shared_notes: Vec<SharedNote>,
+    pub sessions: Vec<ExportedSession>,
+    pub access_tokens: Vec<PersonalAccessToken>,
+    pub identities: Vec<UserIdentity>,
+}
+
+/// Fetches all rows of a per-note table for the notes owned by the user.
+async fn fetch_note_children<T>(
+    pool: &PgPool,
+    table: &str,
+    user_id: Uuid,
+) -> Result<Vec<T>, sqlx::Error>
+where
+    T: for<'r> FromRow<'r, sqlx::postgres::PgRow> + Send + Unpin,
+{
+    sqlx::query_as::<_, T>(&format!(
+        "SELECT t.* FROM {} t JOIN notes n ON t.note_id = n.id WHERE n.user_id = $1",
+        table
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Collects the data export of a user.
+pub async fn collect_account_export(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<AccountExport, sqlx::Error> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    let two_factor_enabled: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM user_totp WHERE user_id = $1 AND enabled_at IS NOT NULL)",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    let user_settings =
+        sqlx::query_as::<_, UserSettings>("SELECT * FROM user_settings WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?;
+    let notebooks = sqlx::query_as::<_, Notebook>("SELECT * FROM notebooks WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    let tags = sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE user_id = $1 ORDER BY name")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    let notes = sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    let shared_notes = sqlx::query_as::<_, SharedNote>(
+        "SELECT * FROM shared_note
+         WHERE user_id = $1 OR note_id IN (SELECT id FROM notes WHERE user_id = $1)",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    let sessions = sqlx::query_as::<_, ExportedSession>(
+        "SELECT family_id, platform, ip_address, user_agent, created_at, last_used_at,
+                expires_at, revoked
+         FROM refresh_tokens WHERE user_id = $1 ORDER BY created_at",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(AccountExport {
+        exported_at: Utc::now(),
+        user: ExportedUser {
+            id: user.id,
+            email: user.email,
+            created_at: user.created_at.and_utc(),
+            email_verified_at: user.email_verified_at,
+            scheduled_deletion_at: user.scheduled_deletion_at,
+            two_factor_enabled,
+        },
+        user_settings,
+        notebooks,
+        tags,
+        notes,
+        note_settings: fetch_note_children(pool, "note_settings", user_id).await?,
+        note_versions: fetch_note_children(pool, "note_versions", user_id).await?,
+        attachments: fetch_note_children(pool, "attachments", user_id).await?,
+        reminders: fetch_note_children(pool, "reminders", user_id).await?,
+        shared_notes,
+        sessions,
+        access_tokens: sqlx::query_as::<_, PersonalAccessToken>(
+            "SELECT * FROM personal_access_tokens WHERE user_id = $1 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?,
+        identities: sqlx::query_as::<_, UserIdentity>(
+            "SELECT * FROM user_identities WHERE user_id = $1 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?,
+    })
+}
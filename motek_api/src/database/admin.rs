@@ -0,0 +1,105 @@
+//! Queries for the admin API.
+
+use crate::models::user::Role;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// User as seen by admins, with usage counts. The password hash is never included.
+#[derive(Debug, FromRow, Serialize)]
+pub struct AdminUserSummary {
+    pub id: Uuid,
+    pub email: String,
+    pub role: Role,
+    pub created_at: NaiveDateTime,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub scheduled_deletion_at: Option<DateTime<Utc>>,
+    pub notes: i64,
+    pub notebooks: i64,
+    pub attachments: i64,
+}
+
+const SUMMARY_SELECT: &str = "SELECT u.id, u.email, u.role, u.created_at, u.email_verified_at,
+        u.disabled_at, u.locked_until, u.scheduled_deletion_at,
+        (SELECT COUNT(*) FROM notes n WHERE n.user_id = u.id) AS notes,
+        (SELECT COUNT(*) FROM notebooks b WHERE b.user_id = u.id) AS notebooks,
+        (SELECT COUNT(*) FROM attachments a JOIN notes n ON a.note_id = n.id
+          WHERE n.user_id = u.id) AS attachments
+     FROM users u";
+
+/// Lists users, newest first. `search` matches a part of the email (case-insensitive).
+pub async fn list_users(
+    pool: &PgPool,
+    search: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<AdminUserSummary>, sqlx::Error> {
+    sqlx::query_as::<_, AdminUserSummary>(&format!(
+        "{} WHERE ($1::TEXT IS NULL OR strpos(lower(u.email), lower($1)) > 0)
+         ORDER BY u.created_at DESC LIMIT $2 OFFSET $3",
+        SUMMARY_SELECT
+    ))
+    .bind(search)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+/// Whether a user with the given id exists.
+pub async fn user_exists(pool: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Fetches one user summary.
+pub async fn get_user_summary(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<AdminUserSummary>, sqlx::Error> {
+    sqlx::query_as::<_, AdminUserSummary>(&format!("{} WHERE u.id = $1", SUMMARY_SELECT))
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Disables or re-enables an account. Disabling also revokes all its sessions.
+/// Returns false if the user does not exist.
+pub async fn set_user_disabled(
+    pool: &PgPool,
+    user_id: Uuid,
+    disabled: bool,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query(
+        "UPDATE users SET disabled_at = CASE WHEN $2 THEN COALESCE(disabled_at, NOW()) END
+         WHERE id = $1",
+    )
+    .bind(user_id)
+    .bind(disabled)
+    .execute(&mut *tx)
+    .await?;
+    if disabled {
+        sqlx::query("UPDATE refresh_tokens SET revoked = TRUE WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Changes the role of a user. Returns false if the user does not exist.
+pub async fn set_user_role(pool: &PgPool, user_id: Uuid, role: Role) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query("UPDATE users SET role = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(role)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
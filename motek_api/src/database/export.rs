@@ -0,0 +1,77 @@
+//! Notes, notebooks and attachments selected for export to files.
+
+use crate::models::{attachment::Attachment, note::Note, notebook::Notebook};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A notebook with its sub-notebooks, their notes and the notes' attachments.
+#[derive(Debug)]
+pub struct NotebookExport {
+    /// The exported notebook first, then its sub-notebooks
+    pub notebooks: Vec<Notebook>,
+    pub notes: Vec<Note>,
+    pub attachments: Vec<Attachment>,
+}
+
+/// Collects a notebook of a user for export, without trashed items.
+/// Returns `None` if the notebook does not exist.
+pub async fn collect_notebook_export(
+    pool: &PgPool,
+    user_id: Uuid,
+    notebook_id: Uuid,
+) -> Result<Option<NotebookExport>, sqlx::Error> {
+    let notebooks = sqlx::query_as::<_, Notebook>(
+        "WITH RECURSIVE tree AS (
+            SELECT * FROM notebooks WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            UNION
+            SELECT nb.* FROM notebooks nb JOIN tree ON nb.parent_id = tree.id
+            WHERE nb.deleted_at IS NULL
+         )
+         SELECT * FROM tree ORDER BY id = $1 DESC, name",
+    )
+    .bind(notebook_id)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    if notebooks.is_empty() {
+        return Ok(None);
+    }
+
+    let ids: Vec<Uuid> = notebooks.iter().map(|nb| nb.id).collect();
+    let notes = sqlx::query_as::<_, Note>(
+        "SELECT * FROM notes WHERE notebook_id = ANY($1) AND deleted_at IS NULL
+         ORDER BY title, created_at",
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?;
+    let note_ids: Vec<Uuid> = notes.iter().map(|n| n.id).collect();
+    let attachments = sqlx::query_as::<_, Attachment>(
+        "SELECT * FROM attachments WHERE note_id = ANY($1) ORDER BY created_at",
+    )
+    .bind(&note_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(NotebookExport {
+        notebooks,
+        notes,
+        attachments,
+    }))
+}
+
+/// Attachments among `ids` that belong to notes of the user.
+pub async fn user_attachments(
+    pool: &PgPool,
+    user_id: Uuid,
+    ids: &[Uuid],
+) -> Result<Vec<Attachment>, sqlx::Error> {
+    sqlx::query_as::<_, Attachment>(
+        "SELECT a.* FROM attachments a JOIN notes n ON n.id = a.note_id
+         WHERE a.id = ANY($1) AND n.user_id = $2",
+    )
+    .bind(ids)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
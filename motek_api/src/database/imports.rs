@@ -0,0 +1,258 @@
+//! Import jobs and the notes, notebooks and attachments they create.
+
+use crate::models::{
+    import_job::{ImportItemError, ImportJob},
+    note_content::NoteContent,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, types::Json};
+use uuid::Uuid;
+
+/// Minutes without a progress report after which a running import counts as interrupted.
+const STALE_IMPORT_MINUTES: i32 = 60;
+
+/// Creates a pending job. Returns `None` if the user already has an unfinished import.
+pub async fn create_import_job(
+    pool: &PgPool,
+    user_id: Uuid,
+    format: &str,
+    notebook_id: Option<Uuid>,
+) -> Result<Option<ImportJob>, sqlx::Error> {
+    sqlx::query_as::<_, ImportJob>(
+        "INSERT INTO import_jobs (user_id, format, notebook_id) VALUES ($1, $2, $3)
+         ON CONFLICT (user_id) WHERE status IN ('pending', 'running') DO NOTHING
+         RETURNING *",
+    )
+    .bind(user_id)
+    .bind(format)
+    .bind(notebook_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn get_import_job(
+    pool: &PgPool,
+    user_id: Uuid,
+    id: Uuid,
+) -> Result<Option<ImportJob>, sqlx::Error> {
+    sqlx::query_as::<_, ImportJob>("SELECT * FROM import_jobs WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Import jobs of a user, newest first.
+pub async fn list_import_jobs(pool: &PgPool, user_id: Uuid) -> Result<Vec<ImportJob>, sqlx::Error> {
+    sqlx::query_as::<_, ImportJob>(
+        "SELECT * FROM import_jobs WHERE user_id = $1 ORDER BY created_at DESC LIMIT 100",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Marks a job as running once its upload is being read.
+pub async fn start_import_job(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE import_jobs SET status = 'running', updated_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Progress of a running import.
+#[derive(Debug, Default)]
+pub struct ImportProgress {
+    /// Items found in the upload so far
+    pub total: i32,
+    pub processed: i32,
+    pub imported: i32,
+    pub failed: i32,
+    pub errors: Vec<ImportItemError>,
+}
+
+/// Records the progress of a job; also serves as its heartbeat.
+pub async fn update_import_job(
+    pool: &PgPool,
+    id: Uuid,
+    progress: &ImportProgress,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE import_jobs SET processed_items = $2, imported_notes = $3, failed_items = $4,
+             errors = $5, total_items = $6, updated_at = NOW()
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(progress.processed)
+    .bind(progress.imported)
+    .bind(progress.failed)
+    .bind(Json(&progress.errors))
+    .bind(progress.total)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Completes a job, or fails it with `error` if the upload could not be imported.
+pub async fn finish_import_job(
+    pool: &PgPool,
+    id: Uuid,
+    progress: &ImportProgress,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE import_jobs SET status = $2, error = $3, processed_items = $4,
+             imported_notes = $5, failed_items = $6, errors = $7, total_items = $8,
+             updated_at = NOW(), finished_at = NOW()
+         WHERE id = $1",
+    )
+    .bind(id)
+    .bind(if error.is_some() {
+        "failed"
+    } else {
+        "completed"
+    })
+    .bind(error)
+    .bind(progress.processed)
+    .bind(progress.imported)
+    .bind(progress.failed)
+    .bind(Json(&progress.errors))
+    .bind(progress.total)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Fails imports that stopped reporting progress, e.g. because the server restarted.
+pub async fn fail_stale_imports(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE import_jobs SET status = 'failed', error = 'The import was interrupted',
+             updated_at = NOW(), finished_at = NOW()
+         WHERE status IN ('pending', 'running')
+           AND updated_at < NOW() - make_interval(mins => $1::INT)",
+    )
+    .bind(STALE_IMPORT_MINUTES)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// The storage keys among `keys` that attachments still refer to.
+pub async fn used_storage_keys(pool: &PgPool, keys: &[String]) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT storage_key FROM attachments WHERE storage_key = ANY($1)")
+        .bind(keys)
+        .fetch_all(pool)
+        .await
+}
+
+/// Id of the notebook of a user named `name` under `parent_id`, created if missing.
+pub async fn find_or_create_notebook(
+    pool: &PgPool,
+    user_id: Uuid,
+    parent_id: Option<Uuid>,
+    name: &str,
+) -> Result<Uuid, sqlx::Error> {
+    let existing: Option<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM notebooks
+         WHERE user_id = $1 AND parent_id IS NOT DISTINCT FROM $2 AND name = $3
+           AND deleted_at IS NULL
+         ORDER BY created_at LIMIT 1",
+    )
+    .bind(user_id)
+    .bind(parent_id)
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+    sqlx::query_scalar(
+        "INSERT INTO notebooks (user_id, name, parent_id) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(parent_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// A note to store, with validated tags and its attachments as `(id, filename, storage_key)`.
+pub struct NewImportedNote<'a> {
+    pub notebook_id: Option<Uuid>,
+    pub title: &'a str,
+    pub content: &'a NoteContent,
+    pub tags: &'a [String],
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub is_pinned: bool,
+    pub is_archived: bool,
+    pub attachments: &'a [(Uuid, String, String)],
+}
+
+/// Stores an imported note with its tags and attachments in one transaction.
+/// The note is written by a single INSERT so its original timestamps are kept.
+pub async fn insert_imported_note(
+    pool: &PgPool,
+    user_id: Uuid,
+    note: &NewImportedNote<'_>,
+) -> Result<Uuid, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO tags (user_id, name) SELECT $1, unnest($2::TEXT[])
+         ON CONFLICT (user_id, lower(name)) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(note.tags)
+    .execute(&mut *tx)
+    .await?;
+    let lowered: Vec<String> = note.tags.iter().map(|t| t.to_lowercase()).collect();
+
+    let id: Uuid = sqlx::query_scalar(
+        "INSERT INTO notes (user_id, notebook_id, title, content, content_text, tags,
+             is_pinned, is_archived, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $5,
+             COALESCE(
+                 (SELECT jsonb_agg(name ORDER BY lower(name)) FROM tags
+                  WHERE user_id = $1 AND lower(name) = ANY($6)),
+                 '[]'::jsonb),
+             $7, $8, COALESCE($9, NOW()), COALESCE($10, $9, NOW()))
+         RETURNING id",
+    )
+    .bind(user_id)
+    .bind(note.notebook_id)
+    .bind(note.title)
+    .bind(note.content.to_value())
+    .bind(note.content.plain_text())
+    .bind(&lowered)
+    .bind(note.is_pinned)
+    .bind(note.is_archived)
+    .bind(note.created_at)
+    .bind(note.updated_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO note_tags (note_id, tag_id)
+         SELECT $1, id FROM tags WHERE user_id = $2 AND lower(name) = ANY($3)",
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(&lowered)
+    .execute(&mut *tx)
+    .await?;
+    for (attachment_id, filename, storage_key) in note.attachments {
+        sqlx::query(
+            "INSERT INTO attachments (id, note_id, filename, url, storage_key)
+             VALUES ($1, $2, $3, $4, $4)",
+        )
+        .bind(attachment_id)
+        .bind(id)
+        .bind(filename)
+        .bind(storage_key)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(id)
+}
@@ -0,0 +1,66 @@
+//! Per-account failed login tracking.
+
+use crate::utils::config_loader::LockoutConfig;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Records a failed login (wrong password or 2FA code).
+/// Once `free_attempts` is exceeded the account is locked, twice as long for every
+/// further failure up to `max_lock_seconds`. Returns the lock expiry, if locked.
+pub async fn record_failed_login(
+    pool: &PgPool,
+    user_id: Uuid,
+    config: &LockoutConfig,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    // Failures older than the window start a new count
+    let attempts: i32 = sqlx::query_scalar(
+        "UPDATE users SET
+            failed_login_attempts = CASE
+                WHEN last_failed_login_at IS NULL OR last_failed_login_at < $2 THEN 1
+                ELSE failed_login_attempts + 1
+            END,
+            last_failed_login_at = NOW()
+         WHERE id = $1
+         RETURNING failed_login_attempts",
+    )
+    .bind(user_id)
+    .bind(Utc::now() - Duration::minutes(config.failure_window_minutes))
+    .fetch_one(pool)
+    .await?;
+
+    let over = attempts - config.free_attempts;
+    if over <= 0 {
+        return Ok(None);
+    }
+    let seconds = config
+        .base_lock_seconds
+        .saturating_mul(1i64 << (over - 1).min(32))
+        .min(config.max_lock_seconds);
+    let locked_until = Utc::now() + Duration::seconds(seconds);
+    sqlx::query("UPDATE users SET locked_until = $1 WHERE id = $2")
+        .bind(locked_until)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    warn!(
+        target: "security",
+        "Account {} locked for {}s after {} failed logins",
+        user_id, seconds, attempts
+    );
+    Ok(Some(locked_until))
+}
+
+/// Resets the failed login counter and lifts a lock (successful login or password reset).
+pub async fn clear_failed_logins(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE users SET failed_login_attempts = 0, last_failed_login_at = NULL, locked_until = NULL
+         WHERE id = $1 AND (failed_login_attempts > 0 OR locked_until IS NOT NULL)",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,168 @@
+//! TOTP second factor and recovery code storage.
+
+use crate::database::token::hash_token;
+use crate::models::user_totp::UserTotp;
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+/// Number of recovery codes generated at once.
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Fetches the TOTP record of a user (pending or enabled).
+pub async fn get_totp(pool: &PgPool, user_id: Uuid) -> Result<Option<UserTotp>, sqlx::Error> {
+    sqlx::query_as::<_, UserTotp>("SELECT * FROM user_totp WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Returns true if the user has a confirmed TOTP second factor.
+pub async fn is_totp_enabled(pool: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let enabled = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS(SELECT 1 FROM user_totp WHERE user_id = $1 AND enabled_at IS NOT NULL)",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(enabled)
+}
+
+/// Stores a new, not yet confirmed TOTP secret, replacing a previous pending one.
+/// Returns `None` if the user already has an enabled second factor.
+pub async fn create_pending_totp(
+    pool: &PgPool,
+    user_id: Uuid,
+    secret: &str,
+) -> Result<Option<UserTotp>, sqlx::Error> {
+    sqlx::query_as::<_, UserTotp>(
+        "INSERT INTO user_totp (user_id, secret) VALUES ($1, $2)
+         ON CONFLICT (user_id) DO UPDATE
+             SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+             WHERE user_totp.enabled_at IS NULL
+         RETURNING *",
+    )
+    .bind(user_id)
+    .bind(secret)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Records a used time step. Returns false if the step (or a later one) was
+/// already used, which makes every code single-use.
+pub async fn mark_totp_step_used(
+    pool: &PgPool,
+    user_id: Uuid,
+    step: i64,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query(
+        "UPDATE user_totp SET last_used_step = $2
+         WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)",
+    )
+    .bind(user_id)
+    .bind(step)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() == 1)
+}
+
+/// Confirms enrollment and stores a fresh set of recovery codes.
+/// Returns the plain recovery codes (shown to the user once).
+pub async fn enable_totp(pool: &PgPool, user_id: Uuid) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query("UPDATE user_totp SET enabled_at = NOW() WHERE user_id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    info!("TOTP enabled for user_id={}", user_id);
+    replace_recovery_codes(pool, user_id).await
+}
+
+/// Removes the second factor and all recovery codes of a user.
+pub async fn disable_totp(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM user_totp WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    info!("TOTP disabled for user_id={}", user_id);
+    Ok(())
+}
+
+/// Replaces all recovery codes of a user with a new set.
+/// Returns the plain codes; only their hashes are stored.
+pub async fn replace_recovery_codes(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<String>, sqlx::Error> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    for code in &codes {
+        sqlx::query("INSERT INTO recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(hash_token(&normalize_recovery_code(code)))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    info!(
+        "Generated {} recovery codes for user_id={}",
+        codes.len(),
+        user_id
+    );
+    Ok(codes)
+}
+
+/// Uses up a recovery code. Returns false if it is unknown or already used.
+pub async fn consume_recovery_code(
+    pool: &PgPool,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query(
+        "UPDATE recovery_codes SET used_at = NOW()
+         WHERE id = (SELECT id FROM recovery_codes
+                     WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+                     LIMIT 1)",
+    )
+    .bind(user_id)
+    .bind(hash_token(&normalize_recovery_code(code)))
+    .execute(pool)
+    .await?;
+    if res.rows_affected() == 1 {
+        info!("Recovery code used for user_id={}", user_id);
+    }
+    Ok(res.rows_affected() == 1)
+}
+
+/// Generates a recovery code such as `k3x9q-7mzp2`.
+fn generate_recovery_code() -> String {
+    let raw: String = rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(|b| char::from(b).to_ascii_lowercase())
+        .collect();
+    format!("{}-{}", &raw[..5], &raw[5..])
+}
+
+/// Ignores case, dashes and spaces so codes can be typed loosely.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
@@ -1 +1,15 @@
+pub mod access_token;
+pub mod account;
+pub mod admin;
+pub mod export;
+pub mod imports;
+pub mod lockout;
+pub mod mfa;
+pub mod note_batch;
+pub mod note_versions;
+pub mod notes;
+pub mod oidc;
+pub mod password_reset;
+pub mod tags;
 pub mod token;
+pub mod trash;
@@ -0,0 +1,179 @@
+//! Operations applied to many notes at once, in a single transaction.
+
+use crate::database::tags::{add_tag_to_notes, remove_tag_from_notes};
+use crate::database::trash::{restore_notes, trash_notes};
+use crate::utils::validators::MAX_TAGS_PER_NOTE;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Operation of a batch request, e.g. `{"op": "move", "notebook_id": "…"}`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    Archive,
+    Unarchive,
+    Pin,
+    Unpin,
+    /// Moves the notes to a notebook, or out of any notebook when `notebook_id` is null
+    Move {
+        notebook_id: Option<Uuid>,
+    },
+    AddTag {
+        tag: String,
+    },
+    RemoveTag {
+        tag: String,
+    },
+    /// Moves the notes to the trash
+    Delete,
+    /// Takes the notes out of the trash
+    Restore,
+}
+
+impl BatchOperation {
+    /// Restore works on trashed notes, every other operation on live ones.
+    fn targets_trash(&self) -> bool {
+        matches!(self, BatchOperation::Restore)
+    }
+}
+
+/// Outcome of a batch operation for one note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Ok,
+    /// The note does not exist, belongs to someone else, or is (not) in the trash
+    NotFound,
+    /// The note already has the most tags a note can have
+    TooManyTags,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchItemResult {
+    pub id: Uuid,
+    pub status: BatchItemStatus,
+}
+
+/// Applies `op` to the user's notes among `ids` in one transaction; other ids are reported
+/// as not found, and notes a tag cannot be added to as having too many tags. Returns `None`
+/// if the target notebook of a move does not exist.
+pub async fn run_batch(
+    pool: &PgPool,
+    user_id: Uuid,
+    ids: &[Uuid],
+    op: &BatchOperation,
+) -> Result<Option<Vec<BatchItemResult>>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    if let BatchOperation::Move {
+        notebook_id: Some(notebook_id),
+    } = op
+    {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM notebooks
+                            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL)",
+        )
+        .bind(notebook_id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if !exists {
+            return Ok(None);
+        }
+    }
+
+    // Lock the notes the user may change; anything else is reported as not found
+    let owned: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM notes
+         WHERE user_id = $1 AND id = ANY($2) AND (deleted_at IS NOT NULL) = $3
+         ORDER BY id
+         FOR UPDATE",
+    )
+    .bind(user_id)
+    .bind(ids)
+    .bind(op.targets_trash())
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // Notes left unchanged because they are full; the tag is kept on those that have it
+    let mut too_many_tags: HashSet<Uuid> = HashSet::new();
+    if let BatchOperation::AddTag { tag } = op
+        && !owned.is_empty()
+    {
+        too_many_tags = sqlx::query_scalar::<_, Uuid>(
+            "SELECT nt.note_id FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+             WHERE nt.note_id = ANY($1)
+             GROUP BY nt.note_id
+             HAVING count(*) >= $2 AND NOT bool_or(lower(t.name) = lower($3))",
+        )
+        .bind(&owned)
+        .bind(MAX_TAGS_PER_NOTE as i64)
+        .bind(tag)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+    }
+    let changed: Vec<Uuid> = owned
+        .iter()
+        .copied()
+        .filter(|id| !too_many_tags.contains(id))
+        .collect();
+
+    if !changed.is_empty() {
+        match op {
+            BatchOperation::Archive | BatchOperation::Unarchive => {
+                sqlx::query("UPDATE notes SET is_archived = $2 WHERE id = ANY($1)")
+                    .bind(&changed)
+                    .bind(matches!(op, BatchOperation::Archive))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            BatchOperation::Pin | BatchOperation::Unpin => {
+                sqlx::query("UPDATE notes SET is_pinned = $2 WHERE id = ANY($1)")
+                    .bind(&changed)
+                    .bind(matches!(op, BatchOperation::Pin))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            BatchOperation::Move { notebook_id } => {
+                sqlx::query("UPDATE notes SET notebook_id = $2 WHERE id = ANY($1)")
+                    .bind(&changed)
+                    .bind(notebook_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            BatchOperation::AddTag { tag } => {
+                add_tag_to_notes(&mut tx, user_id, &changed, tag).await?;
+            }
+            BatchOperation::RemoveTag { tag } => {
+                remove_tag_from_notes(&mut tx, user_id, &changed, tag).await?;
+            }
+            BatchOperation::Delete => {
+                trash_notes(&mut *tx, user_id, &changed).await?;
+            }
+            BatchOperation::Restore => {
+                restore_notes(&mut *tx, user_id, &changed).await?;
+            }
+        }
+    }
+    tx.commit().await?;
+
+    let owned: HashSet<Uuid> = owned.into_iter().collect();
+    Ok(Some(
+        ids.iter()
+            .map(|id| BatchItemResult {
+                id: *id,
+                status: if too_many_tags.contains(id) {
+                    BatchItemStatus::TooManyTags
+                } else if owned.contains(id) {
+                    BatchItemStatus::Ok
+                } else {
+                    BatchItemStatus::NotFound
+                },
+            })
+            .collect(),
+    ))
+}
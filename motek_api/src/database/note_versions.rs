@@ -0,0 +1,164 @@
+//! Note version history: snapshots taken before a note's title or content changes.
+
+use crate::models::{note_content::NoteContent, note_version::NoteVersion};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Versions kept per note when `note_version_limit` is not configured.
+pub const DEFAULT_VERSION_LIMIT: i64 = 50;
+
+/// Version without its content, for listings.
+#[derive(Debug, FromRow, Serialize)]
+pub struct NoteVersionSummary {
+    pub id: Uuid,
+    pub version_no: i32,
+    pub title: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Title, content and revision of a note, as locked for an update.
+#[derive(Debug, FromRow)]
+pub struct NoteSnapshot {
+    pub title: String,
+    pub content: Value,
+    pub revision: i64,
+}
+
+/// Locks a user's note for the rest of the transaction and returns its current state.
+pub async fn lock_note(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    note_id: Uuid,
+) -> Result<Option<NoteSnapshot>, sqlx::Error> {
+    sqlx::query_as::<_, NoteSnapshot>(
+        "SELECT title, content, revision FROM notes
+         WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+         FOR UPDATE",
+    )
+    .bind(note_id)
+    .bind(user_id)
+    .fetch_optional(&mut **tx)
+    .await
+}
+
+/// Stores `snapshot` as the next version of a note and deletes the oldest versions
+/// beyond `limit`. The note must be locked with [`lock_note`]. Returns the version number.
+pub async fn record_version(
+    tx: &mut Transaction<'_, Postgres>,
+    note_id: Uuid,
+    snapshot: &NoteSnapshot,
+    limit: i64,
+) -> Result<i32, sqlx::Error> {
+    let version_no: i32 = sqlx::query_scalar(
+        "INSERT INTO note_versions (id, note_id, version_no, title, content)
+         VALUES (gen_random_uuid(), $1,
+                 COALESCE((SELECT MAX(version_no) FROM note_versions WHERE note_id = $1), 0) + 1,
+                 $2, $3)
+         RETURNING version_no",
+    )
+    .bind(note_id)
+    .bind(&snapshot.title)
+    .bind(&snapshot.content)
+    .fetch_one(&mut **tx)
+    .await?;
+    sqlx::query("DELETE FROM note_versions WHERE note_id = $1 AND version_no <= $2")
+        .bind(note_id)
+        .bind(version_no as i64 - limit.max(1))
+        .execute(&mut **tx)
+        .await?;
+    Ok(version_no)
+}
+
+/// Lists the versions of a user's note, newest first.
+/// Returns `None` if the note does not exist or is in the trash.
+pub async fn list_versions(
+    pool: &PgPool,
+    user_id: Uuid,
+    note_id: Uuid,
+) -> Result<Option<Vec<NoteVersionSummary>>, sqlx::Error> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM notes
+                            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL)",
+    )
+    .bind(note_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Ok(None);
+    }
+    let versions = sqlx::query_as::<_, NoteVersionSummary>(
+        "SELECT id, version_no, title, created_at FROM note_versions
+         WHERE note_id = $1 ORDER BY version_no DESC",
+    )
+    .bind(note_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(Some(versions))
+}
+
+/// Fetches one version of a user's note that is not in the trash.
+pub async fn get_version(
+    pool: &PgPool,
+    user_id: Uuid,
+    note_id: Uuid,
+    version_no: i32,
+) -> Result<Option<NoteVersion>, sqlx::Error> {
+    sqlx::query_as::<_, NoteVersion>(
+        "SELECT v.* FROM note_versions v
+         JOIN notes n ON n.id = v.note_id
+         WHERE v.note_id = $1 AND n.user_id = $2 AND n.deleted_at IS NULL
+           AND v.version_no = $3",
+    )
+    .bind(note_id)
+    .bind(user_id)
+    .bind(version_no)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Makes a version the current title and content of the note. The current state is
+/// recorded as a new version first, so a restore can itself be undone.
+/// Returns false if the note or version does not exist.
+pub async fn restore_version(
+    pool: &PgPool,
+    user_id: Uuid,
+    note_id: Uuid,
+    version_no: i32,
+    limit: i64,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let Some(current) = lock_note(&mut tx, user_id, note_id).await? else {
+        return Ok(false);
+    };
+    let Some(version) = sqlx::query_as::<_, NoteVersion>(
+        "SELECT * FROM note_versions WHERE note_id = $1 AND version_no = $2",
+    )
+    .bind(note_id)
+    .bind(version_no)
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    let title = version.title.unwrap_or_else(|| current.title.clone());
+    // Versions recorded before block documents hold legacy content
+    let content = NoteContent::from_legacy(&version.content);
+    let value = content.to_value();
+    if title != current.title || value != current.content {
+        record_version(&mut tx, note_id, &current, limit).await?;
+        sqlx::query("UPDATE notes SET title = $2, content = $3, content_text = $4 WHERE id = $1")
+            .bind(note_id)
+            .bind(&title)
+            .bind(&value)
+            .bind(content.plain_text())
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
@@ -0,0 +1,300 @@
+//! Note listing with filters, sorting and keyset (cursor) pagination, and full-text search.
+
+use crate::models::note::Note;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Column notes can be sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteSort {
+    UpdatedAt,
+    CreatedAt,
+    Title,
+}
+
+impl NoteSort {
+    fn column(self) -> &'static str {
+        match self {
+            NoteSort::UpdatedAt => "updated_at",
+            NoteSort::CreatedAt => "created_at",
+            NoteSort::Title => "title",
+        }
+    }
+
+    /// Natural direction: newest first for timestamps, alphabetical for titles.
+    pub fn default_descending(self) -> bool {
+        self != NoteSort::Title
+    }
+
+    /// Cursor value of a note for this sort column.
+    fn key(self, note: &Note) -> String {
+        match self {
+            NoteSort::UpdatedAt => note.updated_at.to_rfc3339(),
+            NoteSort::CreatedAt => note.created_at.to_rfc3339(),
+            NoteSort::Title => note.title.clone(),
+        }
+    }
+}
+
+impl FromStr for NoteSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "updated_at" => Ok(NoteSort::UpdatedAt),
+            "created_at" => Ok(NoteSort::CreatedAt),
+            "title" => Ok(NoteSort::Title),
+            other => Err(format!("Unknown sort field: {}", other)),
+        }
+    }
+}
+
+/// Position after the last note of a page. Encoded as base64url JSON, opaque to clients.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NoteCursor {
+    pub sort: NoteSort,
+    pub descending: bool,
+    /// Sort column value of the last note
+    pub key: String,
+    /// Id of the last note, breaks ties between equal keys
+    pub id: Uuid,
+}
+
+impl NoteCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(s: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(s).ok()?;
+        let cursor: Self = serde_json::from_slice(&bytes).ok()?;
+        if cursor.sort != NoteSort::Title && DateTime::parse_from_rfc3339(&cursor.key).is_err() {
+            return None;
+        }
+        Some(cursor)
+    }
+}
+
+/// Filters for listing notes; `None` means no restriction.
+#[derive(Debug, Default)]
+pub struct NoteFilter {
+    pub notebook_id: Option<Uuid>,
+    pub is_archived: Option<bool>,
+    pub is_pinned: Option<bool>,
+    /// Tag name, ignoring case
+    pub tag: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub updated_after: Option<DateTime<Utc>>,
+    pub updated_before: Option<DateTime<Utc>>,
+}
+
+/// Appends the `AND ...` conditions of a filter to a query that already has a `WHERE`.
+fn push_filter(qb: &mut QueryBuilder<'_, Postgres>, filter: &NoteFilter) {
+    if let Some(notebook_id) = filter.notebook_id {
+        qb.push(" AND notebook_id = ").push_bind(notebook_id);
+    }
+    if let Some(is_archived) = filter.is_archived {
+        qb.push(" AND is_archived = ").push_bind(is_archived);
+    }
+    if let Some(is_pinned) = filter.is_pinned {
+        qb.push(" AND is_pinned = ").push_bind(is_pinned);
+    }
+    if let Some(tag) = &filter.tag {
+        qb.push(
+            " AND EXISTS (SELECT 1 FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+              WHERE nt.note_id = notes.id AND lower(t.name) = lower(",
+        )
+        .push_bind(tag.clone())
+        .push("))");
+    }
+    if let Some(after) = filter.created_after {
+        qb.push(" AND created_at >= ").push_bind(after);
+    }
+    if let Some(before) = filter.created_before {
+        qb.push(" AND created_at < ").push_bind(before);
+    }
+    if let Some(after) = filter.updated_after {
+        qb.push(" AND updated_at >= ").push_bind(after);
+    }
+    if let Some(before) = filter.updated_before {
+        qb.push(" AND updated_at < ").push_bind(before);
+    }
+}
+
+/// One page of notes and the cursor of the next page, if there is one.
+pub struct NotePage {
+    pub notes: Vec<Note>,
+    pub next_cursor: Option<NoteCursor>,
+}
+
+/// Lists a page of a user's notes ordered by `sort` (then by id), starting after `cursor`.
+/// The caller must make sure the cursor was issued for the same sort and direction.
+pub async fn list_notes_page(
+    pool: &PgPool,
+    user_id: Uuid,
+    filter: &NoteFilter,
+    sort: NoteSort,
+    descending: bool,
+    cursor: Option<&NoteCursor>,
+    limit: i64,
+) -> Result<NotePage, sqlx::Error> {
+    let column = sort.column();
+    let (cmp, dir) = if descending {
+        ("<", "DESC")
+    } else {
+        (">", "ASC")
+    };
+
+    let mut qb = QueryBuilder::<Postgres>::new(
+        "SELECT * FROM notes WHERE deleted_at IS NULL AND user_id = ",
+    );
+    qb.push_bind(user_id);
+    push_filter(&mut qb, filter);
+    if let Some(cursor) = cursor {
+        qb.push(format!(" AND ({}, id) {} (", column, cmp));
+        match sort {
+            NoteSort::Title => qb.push_bind(cursor.key.clone()),
+            _ => {
+                let at = DateTime::parse_from_rfc3339(&cursor.key)
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?
+                    .with_timezone(&Utc);
+                qb.push_bind(at)
+            }
+        };
+        qb.push(", ").push_bind(cursor.id).push(")");
+    }
+    qb.push(format!(" ORDER BY {} {}, id {} LIMIT ", column, dir, dir));
+    // One extra row tells whether another page follows
+    qb.push_bind(limit + 1);
+
+    let mut notes = qb.build_query_as::<Note>().fetch_all(pool).await?;
+    let next_cursor = if notes.len() as i64 > limit {
+        notes.truncate(limit as usize);
+        notes.last().map(|last| NoteCursor {
+            sort,
+            descending,
+            key: sort.key(last),
+            id: last.id,
+        })
+    } else {
+        None
+    };
+    Ok(NotePage { notes, next_cursor })
+}
+
+/// Sort column from the user's `default_sort` setting, if set to a known column.
+pub async fn default_sort_for_user(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<NoteSort>, sqlx::Error> {
+    let setting: Option<String> = sqlx::query_scalar(
+        "SELECT default_sort FROM user_settings WHERE user_id = $1 ORDER BY created_at LIMIT 1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(setting.and_then(|s| s.parse().ok()))
+}
+
+/// Number of `ids` that are attachments of the user's notes.
+pub async fn count_user_attachments(
+    pool: &PgPool,
+    user_id: Uuid,
+    ids: &[Uuid],
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM attachments a JOIN notes n ON n.id = a.note_id
+         WHERE a.id = ANY($1) AND n.user_id = $2",
+    )
+    .bind(ids)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Marks a match in `ts_headline` output; replaced after HTML escaping.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+/// A note matching a search, with its rank and highlighted fragments.
+#[derive(Debug, FromRow, Serialize)]
+pub struct NoteSearchHit {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub note: Note,
+    pub rank: f32,
+    /// Title with matches wrapped in `<mark>`, HTML-escaped
+    pub title_highlight: String,
+    /// Fragments of the content with matches wrapped in `<mark>`, HTML-escaped
+    pub snippet: String,
+}
+
+/// HTML-escapes headline text and turns the match markers into `<mark>` tags.
+fn render_highlight(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            MATCH_START => out.push_str("<mark>"),
+            MATCH_END => out.push_str("</mark>"),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Full-text search over a user's notes (title, content text and tags), best match first.
+/// `q` uses web search syntax: `"exact phrase"`, `or`, `-excluded`. The query is built once
+/// in the user's search language, the one all their notes are indexed in, so the GIN
+/// index on `search_vector` applies.
+pub async fn search_notes(
+    pool: &PgPool,
+    user_id: Uuid,
+    q: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<NoteSearchHit>, sqlx::Error> {
+    let title_options = format!(
+        "StartSel={}, StopSel={}, HighlightAll=true",
+        MATCH_START, MATCH_END
+    );
+    let snippet_options = format!(
+        "StartSel={}, StopSel={}, MaxFragments=3, MinWords=5, MaxWords=20, FragmentDelimiter=\" … \"",
+        MATCH_START, MATCH_END
+    );
+    let mut hits = sqlx::query_as::<_, NoteSearchHit>(
+        "SELECT n.*,
+                ts_rank_cd(n.search_vector, q.query) AS rank,
+                ts_headline(n.search_config, n.title, q.query, $4) AS title_highlight,
+                ts_headline(n.search_config, n.content_text, q.query, $5) AS snippet
+         FROM notes n
+         CROSS JOIN websearch_to_tsquery(motek_user_search_config($1), $2) AS q(query)
+         WHERE n.user_id = $1 AND n.deleted_at IS NULL AND n.search_vector @@ q.query
+         ORDER BY rank DESC, n.updated_at DESC, n.id
+         LIMIT $3 OFFSET $6",
+    )
+    .bind(user_id)
+    .bind(q)
+    .bind(limit)
+    .bind(title_options)
+    .bind(snippet_options)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    for hit in &mut hits {
+        hit.title_highlight = render_highlight(&hit.title_highlight);
+        hit.snippet = render_highlight(&hit.snippet);
+    }
+    Ok(hits)
+}
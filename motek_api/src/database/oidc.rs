@@ -0,0 +1,141 @@
+//! OpenID Connect login state and linked identities.
+
+use crate::database::token::generate_refresh_token;
+use crate::models::{oidc_login_state::OidcLoginState, platform::Platform, user::User};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+/// Lifetime of a pending authorization request.
+const LOGIN_STATE_TTL_MINUTES: i64 = 10;
+
+/// Starts a login: stores random `state`, `nonce` and PKCE verifier for the callback.
+pub async fn create_login_state(
+    pool: &PgPool,
+    provider: &str,
+    platform: Platform,
+) -> Result<OidcLoginState, sqlx::Error> {
+    sqlx::query_as::<_, OidcLoginState>(
+        "INSERT INTO oidc_login_states (state, provider, code_verifier, nonce, platform, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING *",
+    )
+    .bind(generate_refresh_token())
+    .bind(provider)
+    .bind(generate_refresh_token())
+    .bind(generate_refresh_token())
+    .bind(platform.as_str())
+    .bind(Utc::now() + Duration::minutes(LOGIN_STATE_TTL_MINUTES))
+    .fetch_one(pool)
+    .await
+}
+
+/// Consumes a pending login. Returns `None` if unknown, expired or for another provider.
+pub async fn take_login_state(
+    pool: &PgPool,
+    state: &str,
+    provider: &str,
+) -> Result<Option<OidcLoginState>, sqlx::Error> {
+    sqlx::query_as::<_, OidcLoginState>(
+        "DELETE FROM oidc_login_states
+         WHERE state = $1 AND provider = $2 AND expires_at > NOW()
+         RETURNING *",
+    )
+    .bind(state)
+    .bind(provider)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Deletes abandoned logins.
+pub async fn cleanup_expired_login_states(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM oidc_login_states WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+/// Finds the user linked to a provider identity.
+pub async fn get_user_by_identity(
+    pool: &PgPool,
+    provider: &str,
+    subject: &str,
+) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        "SELECT u.* FROM users u
+         JOIN user_identities i ON i.user_id = u.id
+         WHERE i.provider = $1 AND i.subject = $2",
+    )
+    .bind(provider)
+    .bind(subject)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Links a provider identity to an existing user.
+/// The email is marked verified, since the provider vouched for it.
+pub async fn link_identity(
+    pool: &PgPool,
+    user_id: Uuid,
+    provider: &str,
+    subject: &str,
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO user_identities (user_id, provider, subject, email) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(user_id)
+    .bind(provider)
+    .bind(subject)
+    .bind(email)
+    .execute(pool)
+    .await?;
+    sqlx::query(
+        "UPDATE users SET email_verified_at = NOW() WHERE id = $1 AND email_verified_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    info!(
+        "Linked {} identity {} to user_id={}",
+        provider, subject, user_id
+    );
+    Ok(())
+}
+
+/// Creates a user for a provider identity, with the email already verified.
+/// `password_hash` should be a hash of an unguessable password; the user can set a real
+/// one through the password reset flow.
+pub async fn create_user_with_identity(
+    pool: &PgPool,
+    provider: &str,
+    subject: &str,
+    email: &str,
+    password_hash: &str,
+) -> Result<User, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (email, password, email_verified_at) VALUES ($1, $2, NOW()) RETURNING *",
+    )
+    .bind(email)
+    .bind(password_hash)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query(
+        "INSERT INTO user_identities (user_id, provider, subject, email) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(user.id)
+    .bind(provider)
+    .bind(subject)
+    .bind(email)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Created user_id={} for {} identity {}",
+        user.id, provider, subject
+    );
+    Ok(user)
+}
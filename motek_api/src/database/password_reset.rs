@@ -0,0 +1,78 @@
+//! Password reset token storage.
+
+use crate::database::token::{generate_refresh_token, hash_token};
+use crate::models::password_reset_token::PasswordResetToken;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+/// Creates a password reset token for a user and returns the plain token.
+/// Any earlier unused token of the user is invalidated.
+pub async fn create_password_reset_token(
+    pool: &PgPool,
+    user_id: Uuid,
+    ttl_minutes: i64,
+) -> Result<String, sqlx::Error> {
+    let token = generate_refresh_token();
+    let expires_at = Utc::now() + Duration::minutes(ttl_minutes);
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+    )
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(expires_at)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    info!("Created password reset token for user_id={}", user_id);
+    Ok(token)
+}
+
+/// Consumes a reset token and sets the new password hash in one transaction.
+/// Any lockout from failed logins is lifted.
+/// Returns the user id, or `None` if the token is unknown, expired or already used.
+pub async fn reset_password_with_token(
+    pool: &PgPool,
+    token: &str,
+    password_hash: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let rec = sqlx::query_as::<_, PasswordResetToken>(
+        "UPDATE password_reset_tokens SET used_at = NOW()
+         WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+         RETURNING *",
+    )
+    .bind(hash_token(token))
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(PasswordResetToken { user_id, .. }) = rec else {
+        tx.rollback().await?;
+        return Ok(None);
+    };
+
+    // A reset also lifts a brute-force lockout
+    sqlx::query(
+        "UPDATE users SET password = $1,
+            failed_login_attempts = 0, last_failed_login_at = NULL, locked_until = NULL
+         WHERE id = $2",
+    )
+    .bind(password_hash)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    info!("Password reset completed for user_id={}", user_id);
+    Ok(Some(user_id))
+}
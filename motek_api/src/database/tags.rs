@@ -0,0 +1,265 @@
+//! Per-user tags and their assignment to notes.
+//!
+//! `note_tags` is the source of truth; `notes.tags` holds the sorted tag names of each
+//! note and is rewritten whenever the note's tags, or a tag's name, change.
+
+use crate::models::tag::Tag;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Tag with the number of notes carrying it (not counting notes in the trash).
+#[derive(Debug, FromRow, Serialize)]
+pub struct TagWithCount {
+    pub id: Uuid,
+    pub name: String,
+    pub color: Option<String>,
+    pub note_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Lists a user's tags by name, with usage counts.
+pub async fn list_tags(pool: &PgPool, user_id: Uuid) -> Result<Vec<TagWithCount>, sqlx::Error> {
+    sqlx::query_as::<_, TagWithCount>(
+        "SELECT t.id, t.name, t.color, COUNT(n.id) AS note_count, t.created_at, t.updated_at
+         FROM tags t
+         LEFT JOIN note_tags nt ON nt.tag_id = t.id
+         LEFT JOIN notes n ON n.id = nt.note_id AND n.deleted_at IS NULL
+         WHERE t.user_id = $1
+         GROUP BY t.id
+         ORDER BY lower(t.name)",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Fetches one tag of a user.
+pub async fn get_tag(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<Option<Tag>, sqlx::Error> {
+    sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Creates a tag. Fails with a unique violation if the user has one with the same name.
+pub async fn create_tag(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    color: Option<&str>,
+) -> Result<Tag, sqlx::Error> {
+    sqlx::query_as::<_, Tag>(
+        "INSERT INTO tags (user_id, name, color) VALUES ($1, $2, $3) RETURNING *",
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(color)
+    .fetch_one(pool)
+    .await
+}
+
+/// Rewrites `notes.tags` of the given notes from `note_tags`.
+async fn sync_notes_tags(
+    tx: &mut Transaction<'_, Postgres>,
+    note_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE notes n SET tags = COALESCE(
+            (SELECT jsonb_agg(t.name ORDER BY lower(t.name))
+             FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+             WHERE nt.note_id = n.id),
+            '[]'::jsonb)
+         WHERE n.id = ANY($1)",
+    )
+    .bind(note_ids)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Notes carrying a tag.
+async fn tagged_note_ids(
+    tx: &mut Transaction<'_, Postgres>,
+    tag_id: Uuid,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar("SELECT note_id FROM note_tags WHERE tag_id = $1")
+        .bind(tag_id)
+        .fetch_all(&mut **tx)
+        .await
+}
+
+/// Replaces the tags of a note, creating missing tags. `names` must be validated and
+/// free of case-insensitive duplicates. The note must belong to `user_id`.
+pub async fn set_note_tags(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    note_id: Uuid,
+    names: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO tags (user_id, name) SELECT $1, unnest($2::TEXT[])
+         ON CONFLICT (user_id, lower(name)) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(names)
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query("DELETE FROM note_tags WHERE note_id = $1")
+        .bind(note_id)
+        .execute(&mut **tx)
+        .await?;
+    let lowered: Vec<String> = names.iter().map(|n| n.to_lowercase()).collect();
+    sqlx::query(
+        "INSERT INTO note_tags (note_id, tag_id)
+         SELECT $1, id FROM tags WHERE user_id = $2 AND lower(name) = ANY($3)",
+    )
+    .bind(note_id)
+    .bind(user_id)
+    .bind(&lowered)
+    .execute(&mut **tx)
+    .await?;
+    sync_notes_tags(tx, &[note_id]).await
+}
+
+/// Adds a tag, created if missing, to several notes of a user.
+/// `name` must be validated; the notes must belong to `user_id`.
+pub async fn add_tag_to_notes(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    note_ids: &[Uuid],
+    name: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO tags (user_id, name) VALUES ($1, $2)
+         ON CONFLICT (user_id, lower(name)) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(name)
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query(
+        "INSERT INTO note_tags (note_id, tag_id)
+         SELECT n, t.id FROM unnest($1::UUID[]) AS n, tags t
+         WHERE t.user_id = $2 AND lower(t.name) = lower($3)
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(note_ids)
+    .bind(user_id)
+    .bind(name)
+    .execute(&mut **tx)
+    .await?;
+    sync_notes_tags(tx, note_ids).await
+}
+
+/// Removes a tag (matched ignoring case) from several notes of a user.
+pub async fn remove_tag_from_notes(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    note_ids: &[Uuid],
+    name: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "DELETE FROM note_tags nt USING tags t
+         WHERE nt.tag_id = t.id AND nt.note_id = ANY($1)
+           AND t.user_id = $2 AND lower(t.name) = lower($3)",
+    )
+    .bind(note_ids)
+    .bind(user_id)
+    .bind(name)
+    .execute(&mut **tx)
+    .await?;
+    sync_notes_tags(tx, note_ids).await
+}
+
+/// Renames and/or recolours a tag; an empty colour clears it. Renaming updates every
+/// note carrying the tag. Returns `None` if the tag does not exist; fails with a unique
+/// violation if another tag already has the new name.
+pub async fn update_tag(
+    pool: &PgPool,
+    user_id: Uuid,
+    id: Uuid,
+    name: Option<&str>,
+    color: Option<&str>,
+) -> Result<Option<Tag>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let tag = sqlx::query_as::<_, Tag>(
+        "UPDATE tags SET
+            name       = COALESCE($3, name),
+            color      = CASE WHEN $4::TEXT IS NULL THEN color ELSE NULLIF($4, '') END,
+            updated_at = NOW()
+         WHERE id = $1 AND user_id = $2
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(name)
+    .bind(color)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if tag.is_some() && name.is_some() {
+        let notes = tagged_note_ids(&mut tx, id).await?;
+        sync_notes_tags(&mut tx, &notes).await?;
+    }
+    tx.commit().await?;
+    Ok(tag)
+}
+
+/// Merges `source` into `target`: every note carrying `source` gets `target`, then
+/// `source` is deleted. Returns the number of affected notes, or `None` if either tag
+/// does not belong to the user.
+pub async fn merge_tags(
+    pool: &PgPool,
+    user_id: Uuid,
+    source: Uuid,
+    target: Uuid,
+) -> Result<Option<u64>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let owned: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM tags WHERE user_id = $1 AND id = ANY($2)")
+            .bind(user_id)
+            .bind([source, target])
+            .fetch_one(&mut *tx)
+            .await?;
+    if owned != 2 {
+        return Ok(None);
+    }
+
+    let notes = tagged_note_ids(&mut tx, source).await?;
+    sqlx::query(
+        "INSERT INTO note_tags (note_id, tag_id)
+         SELECT note_id, $2 FROM note_tags WHERE tag_id = $1
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(source)
+    .bind(target)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM tags WHERE id = $1")
+        .bind(source)
+        .execute(&mut *tx)
+        .await?;
+    sync_notes_tags(&mut tx, &notes).await?;
+    tx.commit().await?;
+    Ok(Some(notes.len() as u64))
+}
+
+/// Deletes a tag and removes it from every note. Returns false if it does not exist.
+pub async fn delete_tag(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let notes = tagged_note_ids(&mut tx, id).await?;
+    let res = sqlx::query("DELETE FROM tags WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    if res.rows_affected() == 0 {
+        return Ok(false);
+    }
+    sync_notes_tags(&mut tx, &notes).await?;
+    tx.commit().await?;
+    Ok(true)
+}
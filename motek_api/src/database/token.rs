@@ -1,12 +1,13 @@
 //! JWT token creation and verification logic.
 
 use chrono::{Duration, Utc};
-use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use rand::Rng;
 use rand::distr::Alphanumeric;
-use crate::models::refresh_token::RefreshToken;
+use crate::models::{platform::Platform, refresh_token::RefreshToken};
+use crate::utils::jwt_keys::JwtKeys;
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -19,21 +20,41 @@ pub fn generate_refresh_token() -> String {
         .collect()
 }
 
+/// Hashes an opaque token (reset links, API tokens) for storage.
+/// Only the hash is kept in the database, so a leaked table cannot be replayed.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Client information recorded with a refresh token (one session per device).
+#[derive(Debug, Default)]
+pub struct SessionInfo {
+    pub platform: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
 /// Creates and stores a refresh token for a user in the database.
+/// Starts a new token family, i.e. a new session.
 pub async fn create_refresh_token(
     pool: &PgPool,
     user_id: Uuid,
     expires_in_days: i64,
+    session: &SessionInfo,
 ) -> Result<RefreshToken, sqlx::Error> {
     let token = generate_refresh_token();
     let expires_at = Utc::now() + Duration::days(expires_in_days);
 
     let rec = sqlx::query_as::<_, RefreshToken>(
-        "INSERT INTO refresh_tokens (user_id, token, expires_at) VALUES ($1, $2, $3) RETURNING *"
+        "INSERT INTO refresh_tokens (user_id, token, expires_at, platform, ip_address, user_agent)
+         VALUES ($1, $2, $3, $4, $5, $6) RETURNING *"
     )
     .bind(user_id)
     .bind(&token)
     .bind(expires_at)
+    .bind(&session.platform)
+    .bind(&session.ip_address)
+    .bind(&session.user_agent)
     .fetch_one(pool)
     .await?;
 
@@ -41,6 +62,86 @@ pub async fn create_refresh_token(
     Ok(rec)
 }
 
+/// Rotates a refresh token: revokes the presented one and issues its successor
+/// in the same family, carrying over the session metadata.
+/// `ip_address` is the address of the client performing the refresh.
+/// Returns `None` if the token was already revoked in the meantime (a concurrent
+/// refresh or a replay), in which case nothing is inserted.
+pub async fn rotate_refresh_token(
+    pool: &PgPool,
+    current: &RefreshToken,
+    expires_in_days: i64,
+    ip_address: Option<String>,
+) -> Result<Option<RefreshToken>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let revoked = sqlx::query("UPDATE refresh_tokens SET revoked = TRUE WHERE id = $1 AND revoked = FALSE")
+        .bind(current.id)
+        .execute(&mut *tx)
+        .await?;
+    if revoked.rows_affected() == 0 {
+        tx.rollback().await?;
+        return Ok(None);
+    }
+
+    let token = generate_refresh_token();
+    let expires_at = Utc::now() + Duration::days(expires_in_days);
+    let rec = sqlx::query_as::<_, RefreshToken>(
+        "INSERT INTO refresh_tokens (user_id, token, expires_at, family_id, platform, ip_address, user_agent)
+         VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *"
+    )
+    .bind(current.user_id)
+    .bind(&token)
+    .bind(expires_at)
+    .bind(current.family_id)
+    .bind(&current.platform)
+    .bind(ip_address.or_else(|| current.ip_address.clone()))
+    .bind(&current.user_agent)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    info!(
+        "Rotated refresh token for user_id={} family_id={}",
+        current.user_id, current.family_id
+    );
+    Ok(Some(rec))
+}
+
+/// Revokes every token of a refresh token family.
+/// Used when a token that was already rotated is presented again.
+pub async fn revoke_refresh_token_family(pool: &PgPool, family_id: Uuid) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query("UPDATE refresh_tokens SET revoked = TRUE WHERE family_id = $1 AND revoked = FALSE")
+        .bind(family_id)
+        .execute(pool)
+        .await?;
+    warn!(
+        "Revoked refresh token family {} ({} active tokens)",
+        family_id,
+        res.rows_affected()
+    );
+    Ok(res.rows_affected())
+}
+
+/// Revokes one session (token family) of a user.
+/// Returns the number of active tokens revoked, 0 if the session does not exist
+/// or belongs to another user.
+pub async fn revoke_session(
+    pool: &PgPool,
+    user_id: Uuid,
+    family_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query(
+        "UPDATE refresh_tokens SET revoked = TRUE WHERE family_id = $1 AND user_id = $2 AND revoked = FALSE"
+    )
+    .bind(family_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    info!("Revoked session {} for user_id={}", family_id, user_id);
+    Ok(res.rows_affected())
+}
+
 /// Fetches a refresh token by its string value.
 pub async fn get_refresh_token(pool: &PgPool, token: &str) -> Result<Option<RefreshToken>, sqlx::Error> {
     let rec = sqlx::query_as::<_, RefreshToken>(
@@ -69,36 +170,44 @@ pub async fn revoke_refresh_token(pool: &PgPool, token: &str) -> Result<(), sqlx
 /// JWT Claims structure.
 /// - `sub`: subject (usually user email or ID)
 /// - `exp`: expiration timestamp (seconds since epoch)
-/// - `platform`: platform string (e.g., "web", "mobile")
+/// - `platform`: client platform (e.g., "web", "android")
+/// - `iat`: issued-at timestamp with fractional seconds, compared with the user's
+///   `tokens_valid_after`
+/// - `jti`: unique token id, used by the revocation deny-list
+/// - `typ`: always `access`; other tokens signed with the same keys (2FA challenges,
+///   email verification) lack it and are rejected by [`verify_jwt`]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
-    pub platform: String,
+    pub platform: Platform,
+    pub typ: String,
+    pub iat: f64,
+    pub jti: Uuid,
 }
 
-/// Creates a JWT for a given user and platform.
+const ACCESS_TOKEN_TYPE: &str = "access";
+
+/// Creates a JWT for a given user and platform, valid for `ttl`.
 /// Returns the encoded JWT string.
 pub fn create_jwt(
     username: &str,
-    platform: &str,
-    secret: &str,
+    platform: Platform,
+    ttl: Duration,
+    keys: &JwtKeys,
 ) -> Result<String, jsonwebtoken::errors::Error> {
-    let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(24))
-        .unwrap()
-        .timestamp() as usize;
+    let now = Utc::now();
+    let expiration = now.checked_add_signed(ttl).unwrap().timestamp() as usize;
 
     let claims = Claims {
         sub: username.to_string(),
         exp: expiration,
-        platform: platform.to_string(),
+        platform,
+        typ: ACCESS_TOKEN_TYPE.to_string(),
+        iat: now.timestamp_micros() as f64 / 1_000_000.0,
+        jti: Uuid::new_v4(),
     };
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    );
+    let token = keys.encode(&claims);
     match &token {
         Ok(_) => info!("JWT created for user={} platform={}", username, platform),
         Err(e) => error!("JWT creation failed for user={}: {}", username, e),
@@ -107,33 +216,123 @@ pub fn create_jwt(
 }
 
 /// Verifies a JWT and returns the claims if valid.
-pub fn verify_jwt(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    let result = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::new(Algorithm::HS256),
-    );
+pub fn verify_jwt(token: &str, keys: &JwtKeys) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let result = keys.decode::<Claims>(token).and_then(|claims| {
+        if claims.typ != ACCESS_TOKEN_TYPE {
+            return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+        }
+        Ok(claims)
+    });
     match &result {
-        Ok(data) => info!("JWT verified for subject={}", data.claims.sub),
+        Ok(claims) => info!("JWT verified for subject={}", claims.sub),
         Err(e) => error!("JWT verification failed: {}", e),
     }
-    result.map(|data| data.claims)
+    result
+}
+
+/// Claims of an email verification token.
+/// `purpose` keeps these tokens from being accepted anywhere else and `email`
+/// invalidates them if the address changes before verification.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailVerificationClaims {
+    pub sub: String,
+    pub email: String,
+    pub exp: usize,
+    pub purpose: String,
+}
+
+const EMAIL_VERIFICATION_PURPOSE: &str = "verify_email";
+
+/// Creates a signed email verification token for a user.
+pub fn create_email_verification_token(
+    user_id: Uuid,
+    email: &str,
+    ttl_hours: i64,
+    keys: &JwtKeys,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = EmailVerificationClaims {
+        sub: user_id.to_string(),
+        email: email.to_string(),
+        exp: (Utc::now() + Duration::hours(ttl_hours)).timestamp() as usize,
+        purpose: EMAIL_VERIFICATION_PURPOSE.to_string(),
+    };
+    keys.encode(&claims)
+}
+
+/// Verifies an email verification token and returns its claims.
+pub fn verify_email_verification_token(
+    token: &str,
+    keys: &JwtKeys,
+) -> Result<EmailVerificationClaims, jsonwebtoken::errors::Error> {
+    let claims = keys.decode::<EmailVerificationClaims>(token)?;
+    if claims.purpose != EMAIL_VERIFICATION_PURPOSE {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+    Ok(claims)
+}
+
+/// Claims of a 2FA login challenge, issued after a successful password check.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MfaChallengeClaims {
+    pub sub: String,
+    pub platform: Platform,
+    pub exp: usize,
+    pub purpose: String,
+}
+
+const MFA_CHALLENGE_PURPOSE: &str = "mfa_challenge";
+
+/// Creates a 2FA challenge token valid for 5 minutes.
+pub fn create_mfa_challenge_token(
+    user_id: Uuid,
+    platform: Platform,
+    keys: &JwtKeys,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = MfaChallengeClaims {
+        sub: user_id.to_string(),
+        platform,
+        exp: (Utc::now() + Duration::minutes(5)).timestamp() as usize,
+        purpose: MFA_CHALLENGE_PURPOSE.to_string(),
+    };
+    keys.encode(&claims)
+}
+
+/// Verifies a 2FA challenge token and returns its claims.
+pub fn verify_mfa_challenge_token(
+    token: &str,
+    keys: &JwtKeys,
+) -> Result<MfaChallengeClaims, jsonwebtoken::errors::Error> {
+    let claims = keys.decode::<MfaChallengeClaims>(token)?;
+    if claims.purpose != MFA_CHALLENGE_PURPOSE {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+    Ok(claims)
 }
 
 /// Revokes all refresh tokens for a given user (logout from all devices).
+/// Access tokens issued before now stop being accepted as well.
 pub async fn revoke_all_refresh_tokens_for_user(
     pool: &PgPool,
     user_id: Uuid,
 ) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
     let res = sqlx::query("UPDATE refresh_tokens SET revoked = TRUE WHERE user_id = $1")
         .bind(user_id)
-        .execute(pool)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE users SET tokens_valid_after = NOW() WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
         .await?;
+    tx.commit().await?;
     Ok(res.rows_affected())
 }
 
+/// Deletes expired refresh tokens.
+/// Revoked tokens are kept until they expire so that a replayed token can still
+/// be traced back to its family.
 pub async fn cleanup_expired_refresh_tokens(pool: &PgPool) -> Result<u64, sqlx::Error> {
-    let res = sqlx::query("DELETE FROM refresh_tokens WHERE expires_at < NOW() OR revoked = TRUE")
+    let res = sqlx::query("DELETE FROM refresh_tokens WHERE expires_at < NOW()")
         .execute(pool)
         .await?;
     Ok(res.rows_affected())
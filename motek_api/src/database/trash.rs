@@ -0,0 +1,315 @@
+//! Trash: soft deletion, restore and purge of notes and notebooks.
+//!
+//! A trashed notebook takes its sub-notebooks and their notes with it, all with the same
+//! `deleted_at`, so that restoring the notebook brings back exactly what was deleted with it.
+
+use crate::database::account::NOTE_CHILD_TABLES;
+use crate::models::{note::Note, notebook::Notebook};
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Days items stay in the trash when `trash_retention_days` is not configured.
+pub const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+/// Contents of a user's trash, most recently deleted first.
+#[derive(Debug, Serialize)]
+pub struct Trash {
+    pub notes: Vec<Note>,
+    pub notebooks: Vec<Notebook>,
+}
+
+/// Lists the trashed notes and notebooks of a user.
+pub async fn list_trash(pool: &PgPool, user_id: Uuid) -> Result<Trash, sqlx::Error> {
+    let notes = sqlx::query_as::<_, Note>(
+        "SELECT * FROM notes WHERE user_id = $1 AND deleted_at IS NOT NULL
+         ORDER BY deleted_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    let notebooks = sqlx::query_as::<_, Notebook>(
+        "SELECT * FROM notebooks WHERE user_id = $1 AND deleted_at IS NOT NULL
+         ORDER BY deleted_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(Trash { notes, notebooks })
+}
+
+/// Moves notes of a user to the trash. Returns the number of notes moved; notes that
+/// do not exist or are already trashed are skipped.
+pub async fn trash_notes(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    ids: &[Uuid],
+) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query(
+        "UPDATE notes SET deleted_at = NOW()
+         WHERE id = ANY($1) AND user_id = $2 AND deleted_at IS NULL",
+    )
+    .bind(ids)
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+/// Moves a notebook with its sub-notebooks and their notes to the trash.
+/// Returns false if it does not exist or is already trashed.
+pub async fn trash_notebook(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let tree: Vec<Uuid> = sqlx::query_scalar(
+        "WITH RECURSIVE tree AS (
+            SELECT id FROM notebooks WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            UNION
+            SELECT nb.id FROM notebooks nb JOIN tree ON nb.parent_id = tree.id
+            WHERE nb.deleted_at IS NULL
+         )
+         SELECT id FROM tree",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    if tree.is_empty() {
+        return Ok(false);
+    }
+
+    sqlx::query("UPDATE notebooks SET deleted_at = NOW() WHERE id = ANY($1)")
+        .bind(&tree)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "UPDATE notes SET deleted_at = NOW() WHERE notebook_id = ANY($1) AND deleted_at IS NULL",
+    )
+    .bind(&tree)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Restores notes of a user from the trash. A note whose notebook is still in the trash
+/// is taken out of it. Returns the number of restored notes.
+pub async fn restore_notes(
+    executor: impl PgExecutor<'_>,
+    user_id: Uuid,
+    ids: &[Uuid],
+) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query(
+        "UPDATE notes SET
+            deleted_at  = NULL,
+            notebook_id = CASE WHEN EXISTS (
+                              SELECT 1 FROM notebooks nb
+                              WHERE nb.id = notes.notebook_id AND nb.deleted_at IS NOT NULL
+                          ) THEN NULL ELSE notebook_id END
+         WHERE id = ANY($1) AND user_id = $2 AND deleted_at IS NOT NULL",
+    )
+    .bind(ids)
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+/// Restores a notebook with the sub-notebooks and notes trashed together with it.
+/// If its parent is still in the trash, it is moved to the top level.
+/// Returns false if the notebook is not in the trash.
+pub async fn restore_notebook(pool: &PgPool, user_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let tree: Vec<Uuid> = sqlx::query_scalar(
+        "WITH RECURSIVE tree AS (
+            SELECT id, deleted_at FROM notebooks
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL
+            UNION
+            SELECT nb.id, nb.deleted_at FROM notebooks nb JOIN tree ON nb.parent_id = tree.id
+            WHERE nb.deleted_at = tree.deleted_at
+         )
+         SELECT id FROM tree",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    if tree.is_empty() {
+        return Ok(false);
+    }
+
+    sqlx::query(
+        "UPDATE notes n SET deleted_at = NULL
+         FROM notebooks nb
+         WHERE nb.id = n.notebook_id AND nb.id = ANY($1) AND n.deleted_at = nb.deleted_at",
+    )
+    .bind(&tree)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "UPDATE notebooks SET
+            deleted_at = NULL,
+            parent_id  = CASE WHEN id = $2 AND EXISTS (
+                             SELECT 1 FROM notebooks p
+                             WHERE p.id = notebooks.parent_id AND p.deleted_at IS NOT NULL
+                         ) THEN NULL ELSE parent_id END
+         WHERE id = ANY($1)",
+    )
+    .bind(&tree)
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Permanently deletes notes with everything attached to them.
+/// Attachment files are not touched here: files the server stored (imports) are removed
+/// by the periodic orphaned file cleanup, others belong to wherever their `url` points.
+async fn purge_notes(tx: &mut Transaction<'_, Postgres>, ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+    for table in NOTE_CHILD_TABLES {
+        sqlx::query(&format!("DELETE FROM {} WHERE note_id = ANY($1)", table))
+            .bind(ids)
+            .execute(&mut **tx)
+            .await?;
+    }
+    sqlx::query("DELETE FROM shared_note WHERE note_id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await?;
+    let res = sqlx::query("DELETE FROM notes WHERE id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+/// Permanently deletes notebooks. Notes and notebooks outside `ids` that still point to
+/// one of them are moved to the top level.
+async fn purge_notebooks(
+    tx: &mut Transaction<'_, Postgres>,
+    ids: &[Uuid],
+) -> Result<u64, sqlx::Error> {
+    sqlx::query("UPDATE notes SET notebook_id = NULL WHERE notebook_id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query(
+        "UPDATE notebooks SET parent_id = NULL WHERE parent_id = ANY($1) AND NOT (id = ANY($1))",
+    )
+    .bind(ids)
+    .execute(&mut **tx)
+    .await?;
+    let res = sqlx::query("DELETE FROM notebooks WHERE id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await?;
+    Ok(res.rows_affected())
+}
+
+/// Permanently deletes a trashed note. Returns false if it is not in the trash.
+pub async fn delete_trashed_note(
+    pool: &PgPool,
+    user_id: Uuid,
+    id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let ids: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM notes WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    if ids.is_empty() {
+        return Ok(false);
+    }
+    purge_notes(&mut tx, &ids).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Permanently deletes a trashed notebook with its trashed sub-notebooks and notes.
+/// Returns false if it is not in the trash.
+pub async fn delete_trashed_notebook(
+    pool: &PgPool,
+    user_id: Uuid,
+    id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let tree: Vec<Uuid> = sqlx::query_scalar(
+        "WITH RECURSIVE tree AS (
+            SELECT id FROM notebooks WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL
+            UNION
+            SELECT nb.id FROM notebooks nb JOIN tree ON nb.parent_id = tree.id
+            WHERE nb.deleted_at IS NOT NULL
+         )
+         SELECT id FROM tree",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    if tree.is_empty() {
+        return Ok(false);
+    }
+    let notes: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM notes WHERE notebook_id = ANY($1) AND deleted_at IS NOT NULL",
+    )
+    .bind(&tree)
+    .fetch_all(&mut *tx)
+    .await?;
+    purge_notes(&mut tx, &notes).await?;
+    purge_notebooks(&mut tx, &tree).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Permanently deletes everything in a user's trash.
+/// Returns the number of deleted notes and notebooks.
+pub async fn empty_trash(pool: &PgPool, user_id: Uuid) -> Result<(u64, u64), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let notes: Vec<Uuid> =
+        sqlx::query_scalar("SELECT id FROM notes WHERE user_id = $1 AND deleted_at IS NOT NULL")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+    let notebooks: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM notebooks WHERE user_id = $1 AND deleted_at IS NOT NULL",
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    let purged = (
+        purge_notes(&mut tx, &notes).await?,
+        purge_notebooks(&mut tx, &notebooks).await?,
+    );
+    tx.commit().await?;
+    Ok(purged)
+}
+
+/// Permanently deletes notes and notebooks that have been in the trash longer than
+/// `retention_days`. Returns the number of deleted notes and notebooks.
+pub async fn purge_expired_trash(
+    pool: &PgPool,
+    retention_days: i64,
+) -> Result<(u64, u64), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let notes: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM notes WHERE deleted_at < NOW() - make_interval(days => $1)",
+    )
+    .bind(retention_days as i32)
+    .fetch_all(&mut *tx)
+    .await?;
+    let notebooks: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM notebooks WHERE deleted_at < NOW() - make_interval(days => $1)",
+    )
+    .bind(retention_days as i32)
+    .fetch_all(&mut *tx)
+    .await?;
+    let purged = (
+        purge_notes(&mut tx, &notes).await?,
+        purge_notebooks(&mut tx, &notebooks).await?,
+    );
+    tx.commit().await?;
+    Ok(purged)
+}
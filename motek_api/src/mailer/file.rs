@@ -0,0 +1,41 @@
+//! File mailer – writes every email to a directory instead of sending it.
+//! Meant for development and tests without a mail server.
+
+use super::{Email, Mailer};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::path::PathBuf;
+use tracing::info;
+use uuid::Uuid;
+
+/// Stores each email as a text file in `dir`.
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    /// Creates the mailer, creating the output directory if needed.
+    pub fn new(dir: &str) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        Ok(Self { dir: dir.into() })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> anyhow::Result<()> {
+        let name = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            Uuid::new_v4()
+        );
+        let path = self.dir.join(name);
+        let contents = format!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            email.to, email.subject, email.body
+        );
+        tokio::fs::write(&path, contents).await?;
+        info!("Email to {} written to {}", email.to, path.display());
+        Ok(())
+    }
+}
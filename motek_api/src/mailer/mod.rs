@@ -0,0 +1,60 @@
+//! Outgoing email.
+//! Handlers talk to the `Mailer` trait; the implementation is chosen in config.
+
+pub mod file;
+pub mod smtp;
+
+use crate::utils::config_loader::MailerConfig;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::info;
+
+/// A single plain-text email message.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers emails (password resets, verification links, …).
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> anyhow::Result<()>;
+}
+
+/// Mailer that only writes emails to the log. Used when no mailer is configured.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> anyhow::Result<()> {
+        info!(
+            "Email to={} subject={:?}\n{}",
+            email.to, email.subject, email.body
+        );
+        Ok(())
+    }
+}
+
+/// Builds the mailer selected in config (defaults to `LogMailer`).
+pub fn from_config(config: Option<&MailerConfig>) -> anyhow::Result<Arc<dyn Mailer>> {
+    let mailer: Arc<dyn Mailer> = match config {
+        Some(MailerConfig::Smtp {
+            host,
+            port,
+            username,
+            password,
+            from,
+        }) => Arc::new(smtp::SmtpMailer::new(
+            host,
+            *port,
+            username.as_deref(),
+            password.as_deref(),
+            from,
+        )?),
+        Some(MailerConfig::File { dir }) => Arc::new(file::FileMailer::new(dir)?),
+        Some(MailerConfig::Log) | None => Arc::new(LogMailer),
+    };
+    Ok(mailer)
+}
@@ -0,0 +1,62 @@
+//! SMTP mailer (STARTTLS relay).
+
+use super::{Email, Mailer};
+use async_trait::async_trait;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+use tracing::{error, info};
+
+/// Sends emails through an SMTP relay.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// Creates a mailer for the given relay host; credentials are optional.
+    pub fn new(
+        host: &str,
+        port: Option<u16>,
+        username: Option<&str>,
+        password: Option<&str>,
+        from: &str,
+    ) -> anyhow::Result<Self> {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?;
+        if let Some(port) = port {
+            builder = builder.port(port);
+        }
+        if let (Some(user), Some(pass)) = (username, password) {
+            builder = builder.credentials(Credentials::new(user.to_string(), pass.to_string()));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from: from.parse()?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse()?)
+            .subject(&email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body)?;
+
+        match self.transport.send(message).await {
+            Ok(_) => {
+                info!("Email sent via SMTP to {}", email.to);
+                Ok(())
+            }
+            Err(e) => {
+                error!("SMTP delivery to {} failed: {}", email.to, e);
+                Err(e.into())
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@
 //! Initializes logging and starts the server.
 
 mod database;
+mod mailer;
 mod models;
 mod routes;
 mod server;
@@ -18,6 +18,9 @@ pub struct Attachment {
     pub filename: String,
     /// URL (or path) to the file
     pub url: String,
+    /// File stored by the server, relative to `attachment_dir`; never set by clients
+    #[serde(skip)]
+    pub storage_key: Option<String>,
     /// Timestamp when the attachment was added
     pub created_at: DateTime<Utc>,
 }
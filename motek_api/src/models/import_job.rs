@@ -0,0 +1,50 @@
+//! ImportJob model – a background import of notes from another application.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::Json};
+use uuid::Uuid;
+
+/// ImportJob – progress and outcome of one import.
+/// Relations:
+///   • user_id → users.id (owner of the imported notes)
+///   • notebook_id → notebooks.id (optional notebook receiving the notes)
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct ImportJob {
+    /// UUID of the job
+    pub id: Uuid,
+    /// UUID of the owner (users.id)
+    pub user_id: Uuid,
+    /// Source format: `markdown`, `enex` or `keep`
+    pub format: String,
+    /// UUID of the notebook receiving the notes, if any
+    pub notebook_id: Option<Uuid>,
+    /// `pending`, `running`, `completed` or `failed`
+    pub status: String,
+    /// Number of notes found in the upload so far (all of them once finished)
+    pub total_items: i32,
+    /// Number of notes handled so far
+    pub processed_items: i32,
+    /// Number of notes created
+    pub imported_notes: i32,
+    /// Number of notes that could not be imported
+    pub failed_items: i32,
+    /// Problems with single notes or attachments
+    pub errors: Json<Vec<ImportItemError>>,
+    /// Why the whole import failed, if it did
+    pub error: Option<String>,
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+    /// Last progress report
+    pub updated_at: DateTime<Utc>,
+    /// When the job completed or failed
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A problem with one item of an import.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportItemError {
+    /// File name or title of the item
+    pub item: String,
+    pub error: String,
+}
@@ -1,10 +1,19 @@
 pub mod attachment;
+pub mod import_job;
 pub mod note;
+pub mod note_content;
 pub mod note_settings;
 pub mod note_version;
 pub mod notebook;
+pub mod oidc_login_state;
+pub mod password_reset_token;
+pub mod personal_access_token;
+pub mod platform;
 pub mod reminder;
 pub mod shared_note;
+pub mod tag;
 pub mod user;
+pub mod user_identity;
 pub mod user_settings;
+pub mod user_totp;
 pub mod refresh_token;
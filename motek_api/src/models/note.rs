@@ -3,7 +3,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use sqlx::FromRow;
+use sqlx::{FromRow, types::Json};
 use uuid::Uuid;
 
 /// Note – the main note entity.
@@ -26,10 +26,14 @@ pub struct Note {
     pub is_archived: bool,
     /// Pin flag
     pub is_pinned: bool,
-    /// Array of tags in JSONB
-    pub tags: Value,
+    /// Names of the note's tags, sorted (denormalized from note_tags)
+    pub tags: Json<Vec<String>>,
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
     /// Last modification timestamp (triggered)
     pub updated_at: DateTime<Utc>,
+    /// Revision counter, increased by every update (triggered); the note's ETag
+    pub revision: i64,
+    /// When the note was moved to the trash, if it is there
+    pub deleted_at: Option<DateTime<Utc>>,
 }
@@ -0,0 +1,363 @@
+//! Note content model – the typed block document stored in `notes.content`.
+//!
+//! ```json
+//! {"version": 1, "blocks": [
+//!     {"type": "heading", "level": 1, "text": "Shopping"},
+//!     {"type": "checklist", "items": [{"text": "Milk", "checked": true}]}
+//! ]}
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+use uuid::Uuid;
+
+/// Version of the content schema written by this server.
+pub const CONTENT_VERSION: u32 = 1;
+/// Maximum number of blocks in one note.
+pub const MAX_BLOCKS: usize = 10_000;
+/// Maximum length of a code block's language name, in characters.
+pub const MAX_LANGUAGE_LENGTH: usize = 32;
+
+/// NoteContent – versioned list of blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NoteContent {
+    /// Schema version, currently always [`CONTENT_VERSION`]
+    pub version: u32,
+    /// Blocks in display order
+    pub blocks: Vec<Block>,
+}
+
+/// One block of a note.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Block {
+    Paragraph {
+        text: String,
+    },
+    /// Heading of level 1 to 6
+    Heading {
+        level: u8,
+        text: String,
+    },
+    /// Bulleted, or numbered when `ordered`, list
+    List {
+        #[serde(default)]
+        ordered: bool,
+        items: Vec<String>,
+    },
+    Checklist {
+        items: Vec<ChecklistItem>,
+    },
+    Code {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+        code: String,
+    },
+    /// Image stored as an attachment of one of the user's notes
+    Image {
+        attachment_id: Uuid,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+    },
+    /// Link to an http(s) or mailto URL, shown as `text` when present
+    Link {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+    },
+}
+
+/// Item of a checklist block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChecklistItem {
+    pub text: String,
+    #[serde(default)]
+    pub checked: bool,
+}
+
+impl NoteContent {
+    /// Parses and validates content sent by a client. A JSON string is accepted as
+    /// plain text, one paragraph per line, for clients that do not know blocks yet.
+    pub fn from_value(value: Value) -> Result<Self, String> {
+        let content = match value {
+            Value::String(text) => Self::from_plain_text(&text),
+            value => serde_json::from_value::<NoteContent>(value)
+                .map_err(|e| format!("Invalid note content: {}", e))?,
+        };
+        content.validate()?;
+        Ok(content)
+    }
+
+    /// Converts content stored before block documents, e.g. in old note versions.
+    /// Strings become paragraphs; other JSON keeps only its text, as one paragraph.
+    pub fn from_legacy(value: &Value) -> Self {
+        if let Ok(content) = Self::from_value(value.clone()) {
+            return content;
+        }
+        let mut parts = Vec::new();
+        collect_strings(value, &mut parts);
+        let text = parts.join(" ");
+        if text.is_empty() {
+            return Self::from_plain_text("");
+        }
+        NoteContent {
+            version: CONTENT_VERSION,
+            blocks: vec![Block::Paragraph { text }],
+        }
+    }
+
+    /// One paragraph per line of `text`; empty text has no blocks.
+    pub fn from_plain_text(text: &str) -> Self {
+        let blocks = if text.is_empty() {
+            Vec::new()
+        } else {
+            text.split('\n')
+                .map(|line| Block::Paragraph {
+                    text: line.strip_suffix('\r').unwrap_or(line).to_string(),
+                })
+                .collect()
+        };
+        NoteContent {
+            version: CONTENT_VERSION,
+            blocks,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version != CONTENT_VERSION {
+            return Err(format!(
+                "Unsupported content version {} (expected {})",
+                self.version, CONTENT_VERSION
+            ));
+        }
+        if self.blocks.len() > MAX_BLOCKS {
+            return Err(format!("A note can have at most {} blocks", MAX_BLOCKS));
+        }
+        for block in &self.blocks {
+            match block {
+                Block::Heading { level, .. } if !(1..=6).contains(level) => {
+                    return Err("Heading level must be between 1 and 6".into());
+                }
+                Block::Code {
+                    language: Some(language),
+                    ..
+                } if language.chars().count() > MAX_LANGUAGE_LENGTH
+                    || language.chars().any(char::is_control) =>
+                {
+                    return Err("Invalid code block language".into());
+                }
+                Block::Link { url, .. } => validate_link(url)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Attachments referenced by image blocks.
+    pub fn attachment_ids(&self) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = self
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Image { attachment_id, .. } => Some(*attachment_id),
+                _ => None,
+            })
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Text of the note without formatting, one line per paragraph, heading or item.
+    /// Stored in `notes.content_text` for search.
+    pub fn plain_text(&self) -> String {
+        let mut lines: Vec<&str> = Vec::new();
+        for block in &self.blocks {
+            match block {
+                Block::Paragraph { text } | Block::Heading { text, .. } => lines.push(text),
+                Block::List { items, .. } => lines.extend(items.iter().map(String::as_str)),
+                Block::Checklist { items } => {
+                    lines.extend(items.iter().map(|item| item.text.as_str()))
+                }
+                Block::Code { code, .. } => lines.push(code),
+                Block::Image { caption, .. } => lines.extend(caption.as_deref()),
+                Block::Link { url, text } => lines.push(text.as_deref().unwrap_or(url)),
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Start of the plain text on a single line, cut to `max_chars` characters.
+    pub fn preview(&self, max_chars: usize) -> String {
+        let text = self.plain_text();
+        let mut words = text.split_whitespace();
+        let mut preview = words.next().unwrap_or_default().to_string();
+        for word in words {
+            if preview.chars().count() > max_chars {
+                break;
+            }
+            preview.push(' ');
+            preview.push_str(word);
+        }
+        if preview.chars().count() > max_chars {
+            preview = preview.chars().take(max_chars).collect();
+            preview.push('…');
+        }
+        preview
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+/// Checks that a link block can point to `url`.
+pub fn validate_link(url: &str) -> Result<(), String> {
+    match Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https" | "mailto") => Ok(()),
+        _ => Err(format!("Invalid link URL: {}", url)),
+    }
+}
+
+/// Every string value inside a JSON document, object keys excluded.
+fn collect_strings<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::String(s) if !s.is_empty() => out.push(s),
+        Value::Array(values) => values.iter().for_each(|v| collect_strings(v, out)),
+        Value::Object(map) => map.values().for_each(|v| collect_strings(v, out)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paragraphs(content: &NoteContent) -> Vec<&str> {
+        content
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Paragraph { text } => text.as_str(),
+                other => panic!("expected a paragraph, got {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn string_content_is_one_paragraph_per_line() {
+        let content = NoteContent::from_value(json!("first\r\nsecond\n\nlast")).unwrap();
+        assert_eq!(content.version, CONTENT_VERSION);
+        assert_eq!(paragraphs(&content), ["first", "second", "", "last"]);
+        assert!(
+            NoteContent::from_value(json!(""))
+                .unwrap()
+                .blocks
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn block_document_is_parsed() {
+        let content = NoteContent::from_value(json!({
+            "version": 1,
+            "blocks": [
+                {"type": "heading", "level": 2, "text": "Plan"},
+                {"type": "checklist", "items": [{"text": "Milk"}]},
+                {"type": "link", "url": "https://example.com"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(content.blocks.len(), 3);
+        assert_eq!(
+            content.blocks[1],
+            Block::Checklist {
+                items: vec![ChecklistItem {
+                    text: "Milk".into(),
+                    checked: false
+                }]
+            }
+        );
+    }
+
+    #[test]
+    fn heading_level_must_be_1_to_6() {
+        for level in [0, 7] {
+            let value = json!({
+                "version": 1,
+                "blocks": [{"type": "heading", "level": level, "text": "Title"}]
+            });
+            assert!(NoteContent::from_value(value).is_err(), "level {}", level);
+        }
+    }
+
+    #[test]
+    fn links_are_limited_to_web_and_mail() {
+        let link = |url: &str| json!({"version": 1, "blocks": [{"type": "link", "url": url}]});
+        assert!(NoteContent::from_value(link("javascript:alert(1)")).is_err());
+        assert!(NoteContent::from_value(link("file:///etc/passwd")).is_err());
+        assert!(NoteContent::from_value(link("mailto:me@example.com")).is_ok());
+    }
+
+    #[test]
+    fn other_versions_and_unknown_fields_are_rejected() {
+        assert!(NoteContent::from_value(json!({"version": 2, "blocks": []})).is_err());
+        assert!(NoteContent::from_value(json!({"version": 1, "blocks": [], "x": 1})).is_err());
+        assert!(
+            NoteContent::from_value(json!({
+                "version": 1,
+                "blocks": [{"type": "paragraph", "text": "a", "bold": true}]
+            }))
+            .is_err()
+        );
+    }
+
+    // Expectations shared with motek_legacy_note_content in the note_content migration
+    #[test]
+    fn legacy_content_keeps_its_text() {
+        let strings = NoteContent::from_legacy(&json!("a\r\nb"));
+        assert_eq!(paragraphs(&strings), ["a", "b"]);
+
+        let object = NoteContent::from_legacy(&json!({"body": ["one", "", {"x": "two"}, 3]}));
+        assert_eq!(paragraphs(&object), ["one two"]);
+
+        assert!(NoteContent::from_legacy(&json!({"x": 1})).blocks.is_empty());
+        assert!(NoteContent::from_legacy(&json!(null)).blocks.is_empty());
+
+        // Invalid block documents are treated as legacy content too
+        let invalid = NoteContent::from_legacy(&json!({"version": 2, "blocks": "old"}));
+        assert_eq!(paragraphs(&invalid), ["old"]);
+    }
+
+    #[test]
+    fn plain_text_and_preview() {
+        let content = NoteContent {
+            version: CONTENT_VERSION,
+            blocks: vec![
+                Block::Heading {
+                    level: 1,
+                    text: "Trip".into(),
+                },
+                Block::List {
+                    ordered: false,
+                    items: vec!["tent".into(), "map".into()],
+                },
+                Block::Image {
+                    attachment_id: Uuid::nil(),
+                    caption: None,
+                },
+                Block::Link {
+                    url: "https://example.com".into(),
+                    text: None,
+                },
+            ],
+        };
+        assert_eq!(content.plain_text(), "Trip\ntent\nmap\nhttps://example.com");
+        assert_eq!(content.preview(100), "Trip tent map https://example.com");
+        assert_eq!(content.preview(8), "Trip ten…");
+    }
+}
@@ -17,6 +17,8 @@ pub struct NoteVersion {
     pub note_id: Uuid,
     /// Sequential version number (1, 2, 3…)
     pub version_no: i32,
+    /// Snapshot of the note title (None for versions recorded before titles were kept)
+    pub title: Option<String>,
     /// Snapshot of note content in JSON format
     pub content: Value,
     /// Version creation timestamp
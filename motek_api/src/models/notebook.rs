@@ -23,4 +23,6 @@ pub struct Notebook {
     pub created_at: DateTime<Utc>,
     /// Last modification date (triggered)
     pub updated_at: DateTime<Utc>,
+    /// When the folder was moved to the trash, if it is there
+    pub deleted_at: Option<DateTime<Utc>>,
 }
@@ -0,0 +1,25 @@
+//! OidcLoginState model – pending OpenID Connect authorization request.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// OidcLoginState – data kept between the redirect to the provider and the callback.
+/// Single-use; deleted when the callback consumes it.
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct OidcLoginState {
+    /// Random `state` parameter sent to the provider
+    pub state: String,
+    /// Provider name from config
+    pub provider: String,
+    /// PKCE code verifier
+    pub code_verifier: String,
+    /// Expected `nonce` claim of the ID token
+    pub nonce: String,
+    /// Platform the login was started from
+    pub platform: String,
+    /// Expiration timestamp
+    pub expires_at: DateTime<Utc>,
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,25 @@
+//! PasswordResetToken model – single-use token for the forgot-password flow.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// PasswordResetToken – a pending password reset.
+/// Relations:
+///   • user_id → users.id (account being recovered)
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct PasswordResetToken {
+    /// UUID of the reset token
+    pub id: Uuid,
+    /// UUID of the user
+    pub user_id: Uuid,
+    /// SHA-256 of the token sent by email (the token itself is never stored)
+    pub token_hash: String,
+    /// Expiration timestamp
+    pub expires_at: DateTime<Utc>,
+    /// Set when the token has been used (or superseded)
+    pub used_at: Option<DateTime<Utc>>,
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+}
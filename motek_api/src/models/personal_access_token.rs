@@ -0,0 +1,88 @@
+//! PersonalAccessToken model – scoped API token for scripts and integrations.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// PersonalAccessToken – a named, long-lived Bearer credential.
+/// The `token_hash` column (SHA-256 of the token) is deliberately not mapped,
+/// so it can never end up in a response.
+/// Relations:
+///   • user_id → users.id (token owner)
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct PersonalAccessToken {
+    /// UUID of the token
+    pub id: Uuid,
+    /// UUID of the owner (users.id)
+    pub user_id: Uuid,
+    /// Name given by the user (e.g., "CI import job")
+    pub name: String,
+    /// First characters of the token, to recognise it in listings
+    pub token_prefix: String,
+    /// Granted scopes (e.g., "notes:read")
+    pub scopes: Vec<String>,
+    /// Expiration timestamp (None = never expires)
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Last time the token was used
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Set when the token has been revoked
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+}
+
+impl PersonalAccessToken {
+    /// Granted scopes; unknown values (from older versions) are ignored.
+    pub fn parsed_scopes(&self) -> Vec<Scope> {
+        self.scopes.iter().filter_map(|s| s.parse().ok()).collect()
+    }
+}
+
+/// Scope – permission granted to a personal access token.
+/// Serialized as "<area>:<access>", e.g. "notes:read".
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "notes:read")]
+    NotesRead,
+    #[serde(rename = "notes:write")]
+    NotesWrite,
+    #[serde(rename = "notebooks:read")]
+    NotebooksRead,
+    #[serde(rename = "notebooks:write")]
+    NotebooksWrite,
+}
+
+impl Scope {
+    /// Name as stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::NotesRead => "notes:read",
+            Scope::NotesWrite => "notes:write",
+            Scope::NotebooksRead => "notebooks:read",
+            Scope::NotebooksWrite => "notebooks:write",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "notes:read" => Ok(Scope::NotesRead),
+            "notes:write" => Ok(Scope::NotesWrite),
+            "notebooks:read" => Ok(Scope::NotebooksRead),
+            "notebooks:write" => Ok(Scope::NotebooksWrite),
+            other => Err(format!("Unknown scope '{}'", other)),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Platform model – client platform a session was started from.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Platform – client platform given at login and carried in the JWT.
+/// Serialized in lowercase ("web", "android", "ios", "desktop", "cli").
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Web,
+    Android,
+    Ios,
+    Desktop,
+    Cli,
+}
+
+impl Platform {
+    /// Lowercase name as stored in the database and tokens.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Platform::Web => "web",
+            Platform::Android => "android",
+            Platform::Ios => "ios",
+            Platform::Desktop => "desktop",
+            Platform::Cli => "cli",
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "web" => Ok(Platform::Web),
+            "android" => Ok(Platform::Android),
+            "ios" => Ok(Platform::Ios),
+            "desktop" => Ok(Platform::Desktop),
+            "cli" => Ok(Platform::Cli),
+            other => Err(format!("Unknown platform '{}'", other)),
+        }
+    }
+}
@@ -6,6 +6,9 @@ use sqlx::FromRow;
 use uuid::Uuid;
 
 /// RefreshToken – persistent refresh token for a user session.
+/// Relations:
+///   • user_id → users.id (token owner)
+///   • family_id – shared by all tokens rotated from the same login
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct RefreshToken {
     pub id: Uuid,
@@ -14,4 +17,13 @@ pub struct RefreshToken {
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub revoked: bool,
+    pub family_id: Uuid,
+    /// Platform given at login (e.g., "web", "android")
+    pub platform: Option<String>,
+    /// Client IP address of the last login/refresh
+    pub ip_address: Option<String>,
+    /// User-Agent header of the client
+    pub user_agent: Option<String>,
+    /// Timestamp of the last login/refresh within this session
+    pub last_used_at: DateTime<Utc>,
 }
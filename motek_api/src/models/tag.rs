@@ -0,0 +1,26 @@
+//! Tag model – user-defined labels attached to notes.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Tag – a label owned by one user; names are unique per user, ignoring case.
+/// Relations:
+///   • user_id → users.id (tag owner)
+///   • note_tags.tag_id → tags.id (notes carrying the tag)
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct Tag {
+    /// UUID of the tag
+    pub id: Uuid,
+    /// UUID of the owner (users.id)
+    pub user_id: Uuid,
+    /// Display name
+    pub name: String,
+    /// Optional colour as `#RRGGBB`
+    pub color: Option<String>,
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+    /// Last rename or colour change
+    pub updated_at: DateTime<Utc>,
+}
@@ -1,6 +1,6 @@
 //! User model – represents a registered user.
 
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -18,4 +18,25 @@ pub struct User {
     pub password: String,
     /// Timestamp when the user account was created
     pub created_at: NaiveDateTime,
+    /// Timestamp when the email address was confirmed (None = unverified)
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// When the account will be purged (None = not scheduled for deletion)
+    pub scheduled_deletion_at: Option<DateTime<Utc>>,
+    /// Logins are refused until this time after repeated failures (None = not locked)
+    pub locked_until: Option<DateTime<Utc>>,
+    /// Role: "user" or "admin"
+    pub role: Role,
+    /// Set when an admin has disabled the account (None = active)
+    pub disabled_at: Option<DateTime<Utc>>,
+    /// Access tokens issued before this time are rejected (set by "log out everywhere")
+    pub tokens_valid_after: Option<DateTime<Utc>>,
+}
+
+/// Role – what a user is allowed to do. Admins can use `/api/admin`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Admin,
 }
@@ -0,0 +1,25 @@
+//! UserIdentity model – external (OpenID Connect) identity linked to a user.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// UserIdentity – account at an identity provider that can sign in as the user.
+/// Relations:
+///   • user_id → users.id (linked user)
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct UserIdentity {
+    /// UUID of the identity
+    pub id: Uuid,
+    /// UUID of the linked user
+    pub user_id: Uuid,
+    /// Provider name from config (e.g., "company")
+    pub provider: String,
+    /// Subject (`sub` claim) at the provider
+    pub subject: String,
+    /// Email reported by the provider when the identity was linked
+    pub email: Option<String>,
+    /// Timestamp when the identity was linked
+    pub created_at: DateTime<Utc>,
+}
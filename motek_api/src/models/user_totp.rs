@@ -0,0 +1,23 @@
+//! UserTotp model – TOTP second factor of a user.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// UserTotp – TOTP secret of a user (at most one per user).
+/// Relations:
+///   • user_id → users.id (account owner)
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct UserTotp {
+    /// UUID of the user
+    pub user_id: Uuid,
+    /// Base32-encoded shared secret
+    pub secret: String,
+    /// Set once enrollment is confirmed with a valid code (None = pending)
+    pub enabled_at: Option<DateTime<Utc>>,
+    /// Last accepted time step, codes at or before it are rejected
+    pub last_used_step: Option<i64>,
+    /// Enrollment timestamp
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,149 @@
+use crate::{
+    database::access_token::{create_access_token, list_access_tokens, revoke_access_token},
+    models::personal_access_token::{PersonalAccessToken, Scope},
+    state::AppState,
+    utils::extractors::AuthUser,
+};
+use axum::{
+    Router,
+    extract::{Json, Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Longest lifetime of a token that expires, in days.
+const MAX_EXPIRES_IN_DAYS: i64 = 3650;
+
+/// Returns a router for personal access token management.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_tokens).post(create_token))
+        .route("/{id}", delete(revoke_token))
+}
+
+/// Payload for creating a personal access token.
+#[derive(Deserialize)]
+pub struct CreateTokenPayload {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Lifetime in days, at most 3650 (None = never expires)
+    pub expires_in_days: Option<i64>,
+}
+
+/// Response with a newly created token. `token` is shown only once.
+#[derive(Serialize)]
+pub struct CreateTokenResponse {
+    pub token: String,
+    #[serde(flatten)]
+    pub details: PersonalAccessToken,
+}
+
+/// List the active personal access tokens of the authenticated user.
+pub async fn list_tokens(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> Result<(StatusCode, Json<Vec<PersonalAccessToken>>), (StatusCode, String)> {
+    info!("User {} requested access tokens list", user_id);
+    let rows = list_access_tokens(&state.pool, user_id)
+        .await
+        .map_err(|e| {
+            error!(
+                "DB error fetching access tokens for user {}: {}",
+                user_id, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+    Ok((StatusCode::OK, Json(rows)))
+}
+
+/// Create a personal access token for the authenticated user.
+pub async fn create_token(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(payload): Json<CreateTokenPayload>,
+) -> Result<(StatusCode, Json<CreateTokenResponse>), (StatusCode, String)> {
+    info!(
+        "User {} is creating access token '{}'",
+        user_id, payload.name
+    );
+
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Name is required".to_string()));
+    }
+    if payload.scopes.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "At least one scope is required".to_string(),
+        ));
+    }
+    let expires_at = match payload.expires_in_days {
+        Some(days) if days <= 0 => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "expires_in_days must be positive".to_string(),
+            ));
+        }
+        Some(days) if days > MAX_EXPIRES_IN_DAYS => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("expires_in_days must be at most {}", MAX_EXPIRES_IN_DAYS),
+            ));
+        }
+        Some(days) => Some(Utc::now() + Duration::days(days)),
+        None => None,
+    };
+
+    let (details, token) =
+        create_access_token(&state.pool, user_id, name, &payload.scopes, expires_at)
+            .await
+            .map_err(|e| {
+                error!("Failed to create access token for {}: {}", user_id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Database error".to_string(),
+                )
+            })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateTokenResponse { token, details }),
+    ))
+}
+
+/// Revoke one personal access token.
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    info!("User {} is revoking access token {}", user_id, id);
+    let revoked = revoke_access_token(&state.pool, user_id, id)
+        .await
+        .map_err(|e| {
+            error!(
+                "DB error revoking access token {} for user {}: {}",
+                id, user_id, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+    if revoked == 0 {
+        info!("Access token {} not found for user {}", id, user_id);
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Access token does not exist".to_string(),
+        ));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
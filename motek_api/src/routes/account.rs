@@ -0,0 +1,177 @@
+use crate::{
+    database::account::{AccountExport, collect_account_export, schedule_account_deletion},
+    models::user::User,
+    state::AppState,
+    utils::extractors::AuthUser,
+    utils::password::verify_password,
+};
+use axum::{
+    Router,
+    extract::{Json, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{delete, post},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Write};
+use tracing::{error, info};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+/// Returns a router for account deletion and data export.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", delete(delete_account))
+        .route("/export", post(export_account))
+}
+
+/// Payload for deleting the account.
+#[derive(Deserialize)]
+pub struct DeleteAccountPayload {
+    pub password: String,
+}
+
+/// Response after scheduling the account deletion.
+#[derive(Serialize)]
+pub struct DeleteAccountResponse {
+    /// When the account and all its data will be purged
+    pub scheduled_deletion_at: DateTime<Utc>,
+}
+
+/// Export archive format: `zip` (one JSON file per table, default) or `json`.
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Zip,
+    Json,
+}
+
+/// Query parameters of the export endpoint.
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Delete the account of the authenticated user.
+/// The account is locked and all sessions are revoked immediately; the data is purged
+/// after the grace period. Logging in again before that cancels the deletion.
+pub async fn delete_account(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(payload): Json<DeleteAccountPayload>,
+) -> Result<(StatusCode, Json<DeleteAccountResponse>), (StatusCode, String)> {
+    info!("User {} requested account deletion", user_id);
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.pool)
+        .await
+        .map_err(|e| {
+            error!("DB error fetching user {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+    if !verify_password(&payload.password, &user.password) {
+        info!("Account deletion refused: wrong password for {}", user_id);
+        return Err((StatusCode::FORBIDDEN, "Invalid password".to_string()));
+    }
+
+    let grace_days = state.config.account_deletion_grace_days.unwrap_or(14);
+    let scheduled_deletion_at = schedule_account_deletion(&state.pool, user_id, grace_days)
+        .await
+        .map_err(|e| {
+            error!("Failed to schedule deletion of {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(DeleteAccountResponse {
+            scheduled_deletion_at,
+        }),
+    ))
+}
+
+/// Export all data of the authenticated user as a downloadable archive.
+pub async fn export_account(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    info!("User {} requested a data export", user_id);
+
+    let export = collect_account_export(&state.pool, user_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to collect data export for {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+    let stem = format!("motek-export-{}", export.exported_at.format("%Y%m%d"));
+    let (body, content_type, filename) = match query.format {
+        ExportFormat::Json => {
+            let body = serde_json::to_vec_pretty(&export).map_err(|e| {
+                error!("Failed to serialize data export for {}: {}", user_id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Export error".to_string(),
+                )
+            })?;
+            (body, "application/json", format!("{}.json", stem))
+        }
+        ExportFormat::Zip => {
+            let body = build_export_zip(&export).map_err(|e| {
+                error!("Failed to build export archive for {}: {}", user_id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Export error".to_string(),
+                )
+            })?;
+            (body, "application/zip", format!("{}.zip", stem))
+        }
+    };
+
+    info!(
+        "Data export of {} bytes ready for user {}",
+        body.len(),
+        user_id
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Builds a ZIP archive with one `<table>.json` file per section of the export.
+fn build_export_zip(export: &AccountExport) -> anyhow::Result<Vec<u8>> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+    let serde_json::Value::Object(sections) = serde_json::to_value(export)? else {
+        anyhow::bail!("export is not a JSON object");
+    };
+    for (name, value) in sections {
+        zip.start_file(format!("{}.json", name), options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&value)?)?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
@@ -0,0 +1,178 @@
+use crate::{
+    database::admin::{
+        AdminUserSummary, get_user_summary, list_users, set_user_disabled, set_user_role,
+        user_exists,
+    },
+    database::token::revoke_all_refresh_tokens_for_user,
+    models::user::Role,
+    state::AppState,
+    utils::auth::require_admin,
+    utils::extractors::AuthUser,
+};
+use axum::{
+    Router,
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, post, put},
+};
+use serde::Deserialize;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Returns the admin-only router, nested under `/api/admin`.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/users", get(list))
+        .route("/users/{id}", get(fetch))
+        .route("/users/{id}/disable", post(disable))
+        .route("/users/{id}/enable", post(enable))
+        .route("/users/{id}/logout", post(force_logout))
+        .route("/users/{id}/role", put(change_role))
+        .route_layer(middleware::from_fn(require_admin))
+}
+
+/// Query parameters for listing users.
+#[derive(Deserialize)]
+pub struct ListUsersQuery {
+    /// Part of the email to search for
+    pub q: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Payload for changing a user's role.
+#[derive(Deserialize)]
+pub struct ChangeRolePayload {
+    pub role: Role,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!("Admin API database error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Database error".to_string(),
+    )
+}
+
+fn not_found(id: Uuid) -> (StatusCode, String) {
+    info!("Admin API: user {} not found", id);
+    (StatusCode::NOT_FOUND, "User does not exist".to_string())
+}
+
+/// List or search users with their note, notebook and attachment counts.
+pub async fn list(
+    State(state): State<AppState>,
+    AuthUser(admin_id): AuthUser,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<(StatusCode, Json<Vec<AdminUserSummary>>), (StatusCode, String)> {
+    let search = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query.offset.unwrap_or(0).max(0);
+    info!(
+        "Admin {} listing users (q={:?}, limit={}, offset={})",
+        admin_id, search, limit, offset
+    );
+
+    let users = list_users(&state.pool, search, limit, offset)
+        .await
+        .map_err(db_error)?;
+    Ok((StatusCode::OK, Json(users)))
+}
+
+/// Fetch one user with usage counts.
+pub async fn fetch(
+    State(state): State<AppState>,
+    AuthUser(admin_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<AdminUserSummary>), (StatusCode, String)> {
+    info!("Admin {} fetching user {}", admin_id, id);
+    let user = get_user_summary(&state.pool, id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| not_found(id))?;
+    Ok((StatusCode::OK, Json(user)))
+}
+
+/// Disable an account: it can no longer log in or use existing tokens.
+pub async fn disable(
+    State(state): State<AppState>,
+    AuthUser(admin_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if id == admin_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Cannot disable your own account".to_string(),
+        ));
+    }
+    if !set_user_disabled(&state.pool, id, true)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(not_found(id));
+    }
+    info!("Admin {} disabled user {}", admin_id, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Re-enable a disabled account.
+pub async fn enable(
+    State(state): State<AppState>,
+    AuthUser(admin_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !set_user_disabled(&state.pool, id, false)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(not_found(id));
+    }
+    info!("Admin {} enabled user {}", admin_id, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Log a user out of every device by revoking all refresh tokens.
+pub async fn force_logout(
+    State(state): State<AppState>,
+    AuthUser(admin_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !user_exists(&state.pool, id).await.map_err(db_error)? {
+        return Err(not_found(id));
+    }
+    let revoked = revoke_all_refresh_tokens_for_user(&state.pool, id)
+        .await
+        .map_err(db_error)?;
+    info!(
+        "Admin {} logged out user {} ({} refresh tokens revoked)",
+        admin_id, id, revoked
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Change the role of a user.
+pub async fn change_role(
+    State(state): State<AppState>,
+    AuthUser(admin_id): AuthUser,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ChangeRolePayload>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if id == admin_id && payload.role != Role::Admin {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Cannot remove your own admin role".to_string(),
+        ));
+    }
+    if !set_user_role(&state.pool, id, payload.role)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(not_found(id));
+    }
+    info!(
+        "Admin {} set role of user {} to {:?}",
+        admin_id, id, payload.role
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
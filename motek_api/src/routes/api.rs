@@ -1,34 +1,40 @@
-use axum::Router;
 use axum::http::StatusCode;
+use axum::{Router, middleware};
 use axum::{extract::State, response::IntoResponse};
 use tracing::info; // for logging
 
 use crate::{
-    routes::attachments, routes::note_settings, routes::notebooks, routes::notes,
-    routes::reminders, routes::shared_notes, routes::user_settings, state::AppState,
+    models::{personal_access_token::Scope, platform::Platform},
+    routes::access_tokens,
+    routes::account,
+    routes::admin,
+    routes::attachments,
+    routes::auth,
+    routes::imports,
+    routes::mfa,
+    routes::note_settings,
+    routes::note_versions,
+    routes::notebooks,
+    routes::notes,
+    routes::password,
+    routes::reminders,
+    routes::sessions,
+    routes::shared_notes,
+    routes::tags,
+    routes::trash,
+    routes::user_settings,
+    state::AppState,
     utils::jwt::AuthClaims,
+    utils::platform::{AllowedPlatforms, require_platform},
+    utils::scope::{ScopeGuard, require_scope, require_session},
 };
 
 /// Protected endpoint available only for users coming from the "web" platform.
+/// The platform is enforced by the `require_platform` layer in `router()`.
 pub async fn protected_endpoint(
     State(_state): State<AppState>,
     AuthClaims(claims): AuthClaims,
 ) -> impl IntoResponse {
-    // Log access attempt with user id and platform
-    info!(
-        "User {} is trying to access /protected from platform '{}'",
-        claims.sub, claims.platform
-    );
-
-    // Only allow users from the "web" platform
-    if claims.platform != "web" {
-        info!(
-            "Access denied for user {} from platform '{}'",
-            claims.sub, claims.platform
-        );
-        return (StatusCode::FORBIDDEN, "Web only!").into_response();
-    }
-
     // Log successful access
     info!(
         "User {} successfully accessed /protected from platform '{}'",
@@ -45,24 +51,98 @@ pub async fn protected_endpoint(
         .into_response()
 }
 
+/// Layer restricting personal access tokens to the given read/write scopes.
+fn scoped(router: Router<AppState>, read: Scope, write: Scope) -> Router<AppState> {
+    router.route_layer(middleware::from_fn_with_state(
+        ScopeGuard { read, write },
+        require_scope,
+    ))
+}
+
 /// Configure all application routes.
 /// This includes notes, notebooks, attachments, reminders, shared notes, and user settings.
+/// Notes and notebooks also accept personal access tokens with the matching scopes;
+/// everything else requires a login session.
 pub fn router() -> Router<AppState> {
-    Router::new()
+    let session_only = Router::new()
         // Protected endpoint requiring authentication and correct platform
-        .route("/protected", axum::routing::get(protected_endpoint))
+        .route(
+            "/protected",
+            axum::routing::get(protected_endpoint).route_layer(middleware::from_fn_with_state(
+                AllowedPlatforms(&[Platform::Web]),
+                require_platform,
+            )),
+        )
+        // Shared notes (global)
+        .nest("/shared-notes", shared_notes::router())
+        // User settings (global)
+        .nest("/user-settings", user_settings::router())
+        // Logout needs the access token, so these live behind auth_middleware
+        .route("/auth/logout", axum::routing::post(auth::logout))
+        .route("/auth/logout_all", axum::routing::post(auth::logout_all))
+        // Logged-in devices of the user
+        .nest("/auth/sessions", sessions::router())
+        // Password change for the logged-in user
+        .nest("/auth/password", password::router())
+        // Two-factor authentication management
+        .nest("/auth/mfa", mfa::router())
+        // Personal access tokens
+        .nest("/auth/tokens", access_tokens::router())
+        // Account deletion and data export
+        .nest("/account", account::router())
+        // Administration (admin role only)
+        .nest("/admin", admin::router())
+        .route_layer(middleware::from_fn(require_session));
+
+    Router::new()
         // Notes routes
-        .nest("/notes", notes::router())
+        .nest(
+            "/notes",
+            scoped(notes::router(), Scope::NotesRead, Scope::NotesWrite),
+        )
         // Attachments for a specific note
-        .nest("/notes/{note_id}/attachments", attachments::router())
+        .nest(
+            "/notes/{note_id}/attachments",
+            scoped(attachments::router(), Scope::NotesRead, Scope::NotesWrite),
+        )
         // Reminders for a specific note
-        .nest("/notes/{note_id}/reminders", reminders::router())
+        .nest(
+            "/notes/{note_id}/reminders",
+            scoped(reminders::router(), Scope::NotesRead, Scope::NotesWrite),
+        )
         // Settings for a specific note
-        .nest("/notes/{note_id}/settings", note_settings::router())
+        .nest(
+            "/notes/{note_id}/settings",
+            scoped(note_settings::router(), Scope::NotesRead, Scope::NotesWrite),
+        )
+        // Version history of a specific note
+        .nest(
+            "/notes/{note_id}/versions",
+            scoped(note_versions::router(), Scope::NotesRead, Scope::NotesWrite),
+        )
+        // Tags of the user's notes
+        .nest(
+            "/tags",
+            scoped(tags::router(), Scope::NotesRead, Scope::NotesWrite),
+        )
+        // Deleted notes and notebooks
+        .nest(
+            "/trash",
+            scoped(trash::router(), Scope::NotesRead, Scope::NotesWrite),
+        )
+        // Imports of notes from other applications
+        .nest(
+            "/imports",
+            scoped(imports::router(), Scope::NotesRead, Scope::NotesWrite),
+        )
         // Notebooks (global)
-        .nest("/notebooks", notebooks::router())
-        // Shared notes (global)
-        .nest("/shared-notes", shared_notes::router())
-        // User settings (global)
-        .nest("/user-settings", user_settings::router())
+        .nest(
+            "/notebooks",
+            scoped(
+                notebooks::router(),
+                Scope::NotebooksRead,
+                Scope::NotebooksWrite,
+            ),
+        )
+        .merge(session_only)
 }
@@ -21,7 +21,7 @@ pub fn router() -> Router<AppState> {
 // --- HANDLERS ---
 
 /// List all attachments that belong to the authenticated user.
-/// Only attachments for notes owned by the user will be returned.
+/// Only attachments for notes owned by the user and not in the trash will be returned.
 pub async fn list_attachments(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -32,7 +32,7 @@ pub async fn list_attachments(
         // Select only attachments belonging to notes owned by the user.
         "SELECT a.* FROM attachments a
          JOIN notes n ON a.note_id = n.id
-         WHERE n.user_id = $1",
+         WHERE n.user_id = $1 AND n.deleted_at IS NULL",
     )
     .bind(user_id)
     .fetch_all(&state.pool)
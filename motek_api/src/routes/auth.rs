@@ -1,24 +1,33 @@
 use crate::{
+    database::account::cancel_account_deletion,
+    database::lockout::{clear_failed_logins, record_failed_login},
+    database::mfa::is_totp_enabled,
     database::token::revoke_all_refresh_tokens_for_user,
-    database::token::{create_jwt, create_refresh_token, get_refresh_token, revoke_refresh_token},
-    models::user::User,
+    database::token::{
+        SessionInfo, create_jwt, create_mfa_challenge_token, create_refresh_token,
+        get_refresh_token, revoke_refresh_token, revoke_refresh_token_family, rotate_refresh_token,
+    },
+    models::{platform::Platform, refresh_token::RefreshToken, user::User},
+    routes::{email_verification, mfa, oidc, password},
     state::AppState,
     utils::extractors::AuthUser,
+    utils::jwt::AuthClaims,
+    utils::password::{hash_password, needs_rehash, verify_password},
     utils::validators::{validate_email, validate_password},
 };
 
 use axum::{
     Router,
     extract::{ConnectInfo, Json, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode, header::USER_AGENT},
     response::IntoResponse,
     routing::post,
 };
-use bcrypt::{hash, verify};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::net::SocketAddr;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 /// Payload for user registration.
@@ -33,7 +42,7 @@ pub struct RegisterPayload {
 pub struct LoginRequest {
     pub email: String,
     pub password: String,
-    pub platform: String, // "web", "android", "ios"
+    pub platform: Platform,
 }
 
 /// Response for login – returns both JWT and refresh token.
@@ -43,17 +52,27 @@ pub struct LoginResponse {
     pub refresh_token: String,
 }
 
+/// Response for login when the account has 2FA enabled.
+/// The challenge token is exchanged for real tokens at `/login/mfa`.
+#[derive(Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub challenge_token: String,
+}
+
 /// Payload for refreshing JWT.
+/// `platform` defaults to the platform the session was started from and must match it.
 #[derive(Deserialize)]
 pub struct RefreshRequest {
     pub refresh_token: String,
-    pub platform: String,
+    pub platform: Option<Platform>,
 }
 
-/// Response for refreshing JWT.
+/// Response for refreshing JWT – returns a new JWT and the rotated refresh token.
 #[derive(Serialize)]
 pub struct RefreshResponse {
     pub token: String,
+    pub refresh_token: String,
 }
 
 /// Payload for logout.
@@ -67,13 +86,15 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
+        .route("/login/mfa", post(mfa::login_mfa))
         .route("/refresh", post(refresh_jwt))
-        .route("/logout", post(logout))
-        .route("/logout_all", post(logout_all))
+        .nest("/password", password::public_router())
+        .nest("/verify-email", email_verification::router())
+        .nest("/oidc", oidc::router())
 }
 
 /// Helper to fetch user by email.
-async fn get_user_by_email(pool: &PgPool, email: &str) -> Result<User, sqlx::Error> {
+pub(crate) async fn get_user_by_email(pool: &PgPool, email: &str) -> Result<User, sqlx::Error> {
     sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
         .bind(email)
         .fetch_optional(pool)
@@ -82,7 +103,7 @@ async fn get_user_by_email(pool: &PgPool, email: &str) -> Result<User, sqlx::Err
 }
 
 /// Register a new user.
-/// Registration is rate-limited by IP address.
+/// Registration is rate-limited by IP address (see `utils::rate_limit`).
 pub async fn register(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<AppState>,
@@ -90,14 +111,6 @@ pub async fn register(
 ) -> Result<(StatusCode, Json<String>), (StatusCode, String)> {
     info!("Registration attempt from IP: {}", addr.ip());
     let ip = addr.ip();
-    let allowed = state.register_limiter.check_and_update(ip).await;
-    if !allowed {
-        info!("Registration rate limit exceeded for IP: {}", ip);
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            "Registration from this IP is allowed once per hour".to_string(),
-        ));
-    }
 
     // Validate password and email
     validate_password(&payload.password).map_err(|msg| (StatusCode::BAD_REQUEST, msg))?;
@@ -125,7 +138,7 @@ pub async fn register(
     }
 
     // 2) Hash password
-    let password_hash = hash(&payload.password, 12).map_err(|e| {
+    let password_hash = hash_password(&payload.password, &state.config.argon2).map_err(|e| {
         error!("Hashing error: {}", e);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -134,32 +147,39 @@ pub async fn register(
     })?;
 
     // 3) Insert user
-    sqlx::query_as::<_, User>("INSERT INTO users (email, password) VALUES ($1, $2) RETURNING *")
-        .bind(&payload.email)
-        .bind(&password_hash)
-        .fetch_one(&state.pool)
-        .await
-        .map_err(|e| {
-            error!("DB error during user insert: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error".to_string(),
-            )
-        })?;
+    let user = sqlx::query_as::<_, User>(
+        "INSERT INTO users (email, password) VALUES ($1, $2) RETURNING *",
+    )
+    .bind(&payload.email)
+    .bind(&password_hash)
+    .fetch_one(&state.pool)
+    .await
+    .map_err(|e| {
+        error!("DB error during user insert: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
 
     info!(
         "User {} registered successfully from IP {}",
         &payload.email, ip
     );
 
+    // 4) Ask the user to confirm the email address
+    email_verification::send_verification_email(&state, &user).await;
+
     Ok((StatusCode::CREATED, Json("User registered".to_string())))
 }
 
 /// Login and obtain a JWT token and refresh token.
-/// Checks password and platform, returns both tokens if successful.
+/// Checks password and platform, returns both tokens if successful,
+/// or a 2FA challenge if the account has TOTP enabled.
 pub async fn login(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     info!(
@@ -167,18 +187,6 @@ pub async fn login(
         &payload.email,
         addr.ip()
     );
-    let ip = addr.ip();
-    let allowed = state.login_limiter.check_and_update(ip).await;
-    if !allowed {
-        info!("Login rate limit exceeded for IP: {}", ip);
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            format!(
-                "Login from this IP is allowed {} per hour",
-                state.login_limiter.per_hour
-            ),
-        ));
-    }
     let user = match get_user_by_email(&state.pool, &payload.email).await {
         Ok(user) => user,
         Err(sqlx::Error::RowNotFound) => {
@@ -197,48 +205,224 @@ pub async fn login(
         }
     };
 
-    if !verify(&payload.password, &user.password).unwrap_or(false) {
+    ensure_enabled(&user)?;
+    ensure_not_locked(&user)?;
+
+    if !verify_password(&payload.password, &user.password) {
         info!("Login failed: invalid password for {}", &payload.email);
+        register_failed_login(&state, user.id).await?;
         return Err((
             StatusCode::UNAUTHORIZED,
             "Invalid email or password".to_string(),
         ));
     }
 
-    let jwt_secret = state.config.jwt_secret.as_deref().unwrap_or("sekret_dev");
-    let token = match create_jwt(&user.id.to_string(), &payload.platform, jwt_secret) {
-        Ok(t) => t,
+    // Migrate legacy bcrypt hashes (or outdated Argon2 parameters) now that we know the password
+    if needs_rehash(&user.password, &state.config.argon2) {
+        rehash_password(&state, &user, &payload.password).await;
+    }
+
+    ensure_email_verified(&state, &user)?;
+
+    // Accounts with 2FA get a short-lived challenge instead of tokens
+    if let Some(challenge) = mfa_challenge(&state, &user, payload.platform).await? {
+        info!(
+            "User {} passed password check, 2FA required",
+            &payload.email
+        );
+        return Ok(Json(challenge).into_response());
+    }
+
+    let session = session_info(payload.platform, &addr, &headers);
+    let tokens = issue_tokens(&state, user.id, payload.platform, &session).await?;
+
+    info!(
+        "User {} logged in successfully (platform: {})",
+        &payload.email, &payload.platform
+    );
+    Ok(Json(tokens).into_response())
+}
+
+/// Re-hashes a user's password with the current Argon2id parameters.
+/// Failures are only logged; the old hash keeps working.
+async fn rehash_password(state: &AppState, user: &User, password: &str) {
+    let password_hash = match hash_password(password, &state.config.argon2) {
+        Ok(h) => h,
         Err(e) => {
-            error!("JWT creation error for {}: {}", &payload.email, e);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, "JWT error".to_string()));
+            error!("Rehashing error for {}: {}", user.id, e);
+            return;
         }
     };
+    match sqlx::query("UPDATE users SET password = $1 WHERE id = $2 AND password = $3")
+        .bind(&password_hash)
+        .bind(user.id)
+        .bind(&user.password)
+        .execute(&state.pool)
+        .await
+    {
+        Ok(_) => info!("Password hash of user {} upgraded to Argon2id", user.id),
+        Err(e) => error!("DB error storing rehashed password for {}: {}", user.id, e),
+    }
+}
+
+/// Refuses the login of an account disabled by an admin.
+pub(crate) fn ensure_enabled(user: &User) -> Result<(), (StatusCode, String)> {
+    if user.disabled_at.is_some() {
+        info!("Login refused: account {} is disabled", user.id);
+        return Err((StatusCode::FORBIDDEN, "Account disabled".to_string()));
+    }
+    Ok(())
+}
+
+/// Refuses the login of an unverified email when `require_email_verification` is set.
+pub(crate) fn ensure_email_verified(
+    state: &AppState,
+    user: &User,
+) -> Result<(), (StatusCode, String)> {
+    if state.config.require_email_verification.unwrap_or(false) && user.email_verified_at.is_none()
+    {
+        info!("Login refused: email of {} not verified", user.id);
+        return Err((StatusCode::FORBIDDEN, "Email not verified".to_string()));
+    }
+    Ok(())
+}
+
+/// 2FA challenge to answer at `/login/mfa` if the account has TOTP enabled.
+pub(crate) async fn mfa_challenge(
+    state: &AppState,
+    user: &User,
+    platform: Platform,
+) -> Result<Option<MfaChallengeResponse>, (StatusCode, String)> {
+    let mfa_enabled = is_totp_enabled(&state.pool, user.id).await.map_err(|e| {
+        error!("DB error checking 2FA for {}: {}", user.id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
+    if !mfa_enabled {
+        return Ok(None);
+    }
+    let challenge_token =
+        create_mfa_challenge_token(user.id, platform, &state.jwt_keys).map_err(|e| {
+            error!("MFA challenge creation error for {}: {}", user.id, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "JWT error".to_string())
+        })?;
+    Ok(Some(MfaChallengeResponse {
+        mfa_required: true,
+        challenge_token,
+    }))
+}
+
+/// Refuses the login while the account is locked after repeated failures.
+pub(crate) fn ensure_not_locked(user: &User) -> Result<(), (StatusCode, String)> {
+    match user.locked_until {
+        Some(until) if until > Utc::now() => {
+            let seconds = (until - Utc::now()).num_seconds().max(1);
+            info!("Login refused: account {} locked for {}s", user.id, seconds);
+            Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "Account temporarily locked, try again in {} seconds",
+                    seconds
+                ),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Counts a failed password or 2FA code against the account.
+pub(crate) async fn register_failed_login(
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    record_failed_login(&state.pool, user_id, &state.config.lockout)
+        .await
+        .map_err(|e| {
+            error!("DB error recording failed login for {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+    Ok(())
+}
 
-    // Generate refresh token valid for 30 days
-    let refresh_token = match create_refresh_token(&state.pool, user.id, 30).await {
-        Ok(rt) => rt.token,
+/// Builds the session metadata (device info) recorded with a new refresh token.
+pub(crate) fn session_info(
+    platform: Platform,
+    addr: &SocketAddr,
+    headers: &HeaderMap,
+) -> SessionInfo {
+    SessionInfo {
+        platform: Some(platform.as_str().to_string()),
+        ip_address: Some(addr.ip().to_string()),
+        user_agent: headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+    }
+}
+
+/// Issues a JWT and a refresh token, starting a new session.
+/// Token lifetimes follow the per-platform policy from config.
+/// A successful sign-in resets the failed login counter and, during the grace period,
+/// cancels a pending account deletion.
+pub(crate) async fn issue_tokens(
+    state: &AppState,
+    user_id: Uuid,
+    platform: Platform,
+    session: &SessionInfo,
+) -> Result<LoginResponse, (StatusCode, String)> {
+    if let Err(e) = clear_failed_logins(&state.pool, user_id).await {
+        error!("Failed to clear failed logins for {}: {}", user_id, e);
+    }
+    match cancel_account_deletion(&state.pool, user_id).await {
+        Ok(true) => info!("Account deletion of {} cancelled by login", user_id),
+        Ok(false) => {}
         Err(e) => {
-            error!("Refresh token creation error for {}: {}", &payload.email, e);
+            error!("Failed to cancel account deletion for {}: {}", user_id, e);
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
-                "Refresh token error".to_string(),
+                "Database error".to_string(),
             ));
         }
+    }
+
+    let ttl = state.config.access_token_ttl(platform);
+    let token = match create_jwt(&user_id.to_string(), platform, ttl, &state.jwt_keys) {
+        Ok(t) => t,
+        Err(e) => {
+            error!("JWT creation error for {}: {}", user_id, e);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "JWT error".to_string()));
+        }
     };
 
-    info!(
-        "User {} logged in successfully (platform: {})",
-        &payload.email, &payload.platform
-    );
-    Ok(Json(LoginResponse {
+    let refresh_days = state.config.refresh_token_days(platform);
+    let refresh_token =
+        match create_refresh_token(&state.pool, user_id, refresh_days, session).await {
+            Ok(rt) => rt.token,
+            Err(e) => {
+                error!("Refresh token creation error for {}: {}", user_id, e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Refresh token error".to_string(),
+                ));
+            }
+        };
+
+    Ok(LoginResponse {
         token,
         refresh_token,
-    }))
+    })
 }
 
 /// Refresh JWT using a valid refresh token.
-/// Returns a new JWT if the refresh token is valid and not expired/revoked.
+/// The presented refresh token is revoked and replaced by a new one from the same family.
+/// Presenting an already rotated token revokes the whole family (reuse detection).
 pub async fn refresh_jwt(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     State(state): State<AppState>,
     Json(payload): Json<RefreshRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
@@ -257,16 +441,58 @@ pub async fn refresh_jwt(
         }
     };
 
-    if rec.revoked || rec.expires_at < chrono::Utc::now() {
-        error!("Refresh token is expired or revoked");
+    if rec.revoked {
+        return Err(refresh_token_reused(&state.pool, &rec).await);
+    }
+
+    if rec.expires_at < chrono::Utc::now() {
+        error!("Refresh token is expired");
         return Err((
             StatusCode::UNAUTHORIZED,
             "Refresh token expired or revoked".to_string(),
         ));
     }
 
-    let jwt_secret = state.config.jwt_secret.as_deref().unwrap_or("sekret_dev");
-    let token = match create_jwt(&rec.user_id.to_string(), &payload.platform, jwt_secret) {
+    // The session keeps the platform it was started from
+    let session_platform = rec
+        .platform
+        .as_deref()
+        .and_then(|p| p.parse::<Platform>().ok());
+    let platform = match (payload.platform, session_platform) {
+        (Some(requested), Some(session)) if requested != session => {
+            warn!(
+                "Refresh for user_id={} requested platform '{}' but session is '{}'",
+                rec.user_id, requested, session
+            );
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "Platform does not match session".to_string(),
+            ));
+        }
+        (requested, session) => requested
+            .or(session)
+            .ok_or((StatusCode::BAD_REQUEST, "Missing platform".to_string()))?,
+    };
+
+    // Rotate before issuing the JWT so a concurrent replay loses the race.
+    let refresh_days = state.config.refresh_token_days(platform);
+    let refresh_token =
+        match rotate_refresh_token(&state.pool, &rec, refresh_days, Some(addr.ip().to_string()))
+            .await
+        {
+            Ok(Some(rt)) => rt.token,
+            Ok(None) => return Err(refresh_token_reused(&state.pool, &rec).await),
+            Err(e) => {
+                error!("Refresh token rotation error: {}", e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Refresh token error".to_string(),
+                ));
+            }
+        };
+
+    let ttl = state.config.access_token_ttl(platform);
+    let token = match create_jwt(&rec.user_id.to_string(), platform, ttl, &state.jwt_keys) {
         Ok(t) => t,
         Err(e) => {
             error!("JWT creation error: {}", e);
@@ -275,14 +501,38 @@ pub async fn refresh_jwt(
     };
 
     info!("Issued new JWT for user_id={}", rec.user_id);
-    Ok(Json(RefreshResponse { token }))
+    Ok(Json(RefreshResponse {
+        token,
+        refresh_token,
+    }))
 }
 
-/// Logout: revoke a refresh token.
-/// After this, the refresh token cannot be used again.
+/// Handles a replayed (already revoked) refresh token.
+/// Revokes the whole token family, logs a security event and returns the rejection.
+async fn refresh_token_reused(pool: &PgPool, rec: &RefreshToken) -> (StatusCode, String) {
+    warn!(
+        target: "security",
+        "Refresh token reuse detected for user_id={} family_id={} token_id={}",
+        rec.user_id, rec.family_id, rec.id
+    );
+    if let Err(e) = revoke_refresh_token_family(pool, rec.family_id).await {
+        error!(
+            "Failed to revoke refresh token family {}: {}",
+            rec.family_id, e
+        );
+    }
+    (
+        StatusCode::UNAUTHORIZED,
+        "Refresh token expired or revoked".to_string(),
+    )
+}
+
+/// Logout: revoke a refresh token and the access token of the request.
+/// After this, neither token can be used again.
 pub async fn logout(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
+    AuthClaims(claims): AuthClaims,
     Json(payload): Json<LogoutRequest>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     info!(
@@ -299,6 +549,18 @@ pub async fn logout(
             "Token does not belong to user".to_string(),
         ));
     }
+    // The access token used for logging out stops working right away
+    if let Err(e) = state
+        .revoked_tokens
+        .revoke(&state.pool, claims.jti, user_id, claims.exp)
+        .await
+    {
+        error!("Failed to revoke access token {}: {}", claims.jti, e);
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Logout error".to_string(),
+        ));
+    }
     match revoke_refresh_token(&state.pool, &payload.refresh_token).await {
         Ok(_) => {
             info!("Refresh token revoked successfully");
@@ -329,7 +591,7 @@ pub async fn token_belongs_to_user(
     Ok(rec.unwrap_or(false))
 }
 
-/// Logout from all devices (revoke all refresh tokens for this user).
+/// Logout from all devices (revoke all refresh tokens and issued access tokens for this user).
 pub async fn logout_all(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -0,0 +1,140 @@
+use crate::{
+    database::token::{create_email_verification_token, verify_email_verification_token},
+    mailer::Email,
+    models::user::User,
+    routes::auth::get_user_by_email,
+    state::AppState,
+};
+use axum::{
+    Router,
+    extract::{Json, State},
+    http::StatusCode,
+    routing::post,
+};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Returns a router for the public email verification endpoints.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", post(verify_email))
+        .route("/resend", post(resend_verification))
+}
+
+/// Payload for confirming an email address.
+#[derive(Deserialize)]
+pub struct VerifyEmailPayload {
+    pub token: String,
+}
+
+/// Payload for requesting a new verification email.
+#[derive(Deserialize)]
+pub struct ResendVerificationPayload {
+    pub email: String,
+}
+
+/// Sends a verification email to a user.
+/// Failures are logged only; the user can always ask for a resend.
+pub async fn send_verification_email(state: &AppState, user: &User) {
+    let ttl_hours = state.config.email_verification_ttl_hours.unwrap_or(48);
+    let token =
+        match create_email_verification_token(user.id, &user.email, ttl_hours, &state.jwt_keys) {
+            Ok(t) => t,
+            Err(e) => {
+                error!("Verification token creation error for {}: {}", user.id, e);
+                return;
+            }
+        };
+
+    let link = match state.config.app_url.as_deref() {
+        Some(url) => format!("{}/verify-email?token={}", url.trim_end_matches('/'), token),
+        None => token,
+    };
+    let email = Email {
+        to: user.email.clone(),
+        subject: "Confirm your Motek email address".to_string(),
+        body: format!(
+            "Welcome to Motek!\n\n\
+             Confirm your email address (valid for {} hours):\n{}",
+            ttl_hours, link
+        ),
+    };
+    if let Err(e) = state.mailer.send(email).await {
+        error!("Failed to send verification email to {}: {}", user.email, e);
+    }
+}
+
+/// Confirm an email address with a verification token.
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyEmailPayload>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let claims = verify_email_verification_token(&payload.token, &state.jwt_keys).map_err(|e| {
+        warn!("Invalid email verification token: {}", e);
+        (
+            StatusCode::BAD_REQUEST,
+            "Invalid or expired verification token".to_string(),
+        )
+    })?;
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Invalid or expired verification token".to_string(),
+        )
+    })?;
+
+    let result = sqlx::query(
+        "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW())
+         WHERE id = $1 AND email = $2",
+    )
+    .bind(user_id)
+    .bind(&claims.email)
+    .execute(&state.pool)
+    .await
+    .map_err(|e| {
+        error!("DB error verifying email for {}: {}", user_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        info!("Email verification for {} did not match any user", user_id);
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Invalid or expired verification token".to_string(),
+        ));
+    }
+
+    info!("Email {} verified for user {}", &claims.email, user_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Send the verification email again.
+/// Always answers 202 so the endpoint cannot be used to probe for accounts.
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    Json(payload): Json<ResendVerificationPayload>,
+) -> StatusCode {
+    match get_user_by_email(&state.pool, &payload.email).await {
+        Ok(user) if user.email_verified_at.is_none() => {
+            info!("Resending verification email to {}", &user.email);
+            // Sent in the background so the response time does not reveal whether the account exists
+            tokio::spawn(async move { send_verification_email(&state, &user).await });
+        }
+        Ok(_) => info!(
+            "Verification resend for already verified {}",
+            &payload.email
+        ),
+        Err(sqlx::Error::RowNotFound) => {
+            info!("Verification resend for unknown email {}", &payload.email)
+        }
+        Err(e) => error!(
+            "DB error during verification resend for {}: {}",
+            &payload.email, e
+        ),
+    }
+    StatusCode::ACCEPTED
+}
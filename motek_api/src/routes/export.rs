@@ -0,0 +1,143 @@
+use crate::{
+    database::export::{collect_notebook_export, user_attachments},
+    models::note_content::NoteContent,
+    routes::notes::fetch_note,
+    state::AppState,
+    utils::export::{NoteFormat, file_name, render_note, temp_file, write_notebook_zip},
+    utils::extractors::AuthUser,
+};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Seek;
+use std::path::PathBuf;
+use tokio_util::io::ReaderStream;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Query parameters of the export endpoints.
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// `md` (default), `html` or `txt`
+    #[serde(default)]
+    pub format: NoteFormat,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!("Export database error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Database error".to_string(),
+    )
+}
+
+/// `Content-Disposition` of a download, with an ASCII fallback of the file name.
+fn content_disposition(name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| if c.is_ascii() { c } else { '_' })
+        .collect();
+    let mut encoded = String::new();
+    for byte in name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback, encoded
+    )
+}
+
+/// Download one note as Markdown, HTML or plain text.
+pub async fn export_note(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    info!("User {} is exporting note {}", user_id, id);
+    let note = fetch_note(&state.pool, user_id, id).await?;
+
+    // Images link to where their attachments are stored
+    let ids = NoteContent::from_legacy(&note.content).attachment_ids();
+    let hrefs: HashMap<Uuid, String> = user_attachments(&state.pool, user_id, &ids)
+        .await
+        .map_err(db_error)?
+        .into_iter()
+        .map(|a| (a.id, a.url))
+        .collect();
+
+    let body = render_note(&note, query.format, &hrefs);
+    let name = format!("{}.{}", file_name(&note.title), query.format.extension());
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                query.format.content_type().to_string(),
+            ),
+            (header::CONTENT_DISPOSITION, content_disposition(&name)),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Download a notebook with its sub-notebooks as a ZIP archive of notes in folders.
+pub async fn export_notebook(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    info!("User {} is exporting notebook {}", user_id, id);
+    let Some(export) = collect_notebook_export(&state.pool, user_id, id)
+        .await
+        .map_err(db_error)?
+    else {
+        info!("Notebook {} not found for user {}", id, user_id);
+        return Err((StatusCode::NOT_FOUND, "Notebook does not exist".to_string()));
+    };
+    let name = format!("{}.zip", file_name(&export.notebooks[0].name));
+    let attachment_dir = state.config.attachment_dir.as_ref().map(PathBuf::from);
+
+    // The archive is built in a temporary file and streamed from there
+    let file = tokio::task::spawn_blocking(move || -> anyhow::Result<std::fs::File> {
+        let mut file = write_notebook_zip(
+            temp_file()?,
+            &export,
+            query.format,
+            attachment_dir.as_deref(),
+        )?;
+        file.rewind()?;
+        Ok(file)
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|res| res)
+    .map_err(|e| {
+        error!("Failed to build export of notebook {}: {}", id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Export error".to_string(),
+        )
+    })?;
+
+    let stream = ReaderStream::new(tokio::fs::File::from_std(file));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, content_disposition(&name)),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
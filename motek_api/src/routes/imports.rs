@@ -0,0 +1,497 @@
+use crate::{
+    database::imports::{
+        ImportProgress, NewImportedNote, create_import_job, find_or_create_notebook,
+        finish_import_job, get_import_job, insert_imported_note, list_import_jobs,
+        start_import_job, update_import_job, used_storage_keys,
+    },
+    models::{
+        import_job::{ImportItemError, ImportJob},
+        note_content::Block,
+    },
+    state::AppState,
+    utils::export::file_name,
+    utils::extractors::AuthUser,
+    utils::import::{ImportEvent, ImportFormat, ImportedNote, parse_upload},
+    utils::validators::{MAX_TAGS_PER_NOTE, validate_tag_name},
+};
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Json, Path, Query, State},
+    http::StatusCode,
+    routing::get,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{self, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{OwnedSemaphorePermit, mpsc};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Largest accepted upload.
+const MAX_IMPORT_SIZE: usize = 256 * 1024 * 1024;
+/// Imports running at once on an instance; each holds its upload in memory.
+pub const MAX_RUNNING_IMPORTS: usize = 2;
+/// Parsed notes waiting to be stored.
+const QUEUED_NOTES: usize = 4;
+/// Progress is stored after this many notes.
+const PROGRESS_INTERVAL: i32 = 25;
+/// Most per-item errors kept in the report of a job.
+const MAX_REPORTED_ERRORS: usize = 1000;
+/// Folder under `attachment_dir` receiving imported attachments.
+const IMPORTS_DIR: &str = "imports";
+/// Age after which an imported file no attachment refers to is removed.
+const ORPHAN_FILE_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Returns a router for imports, nested under `/api/imports`.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/",
+            get(list)
+                .post(start)
+                .layer(DefaultBodyLimit::max(MAX_IMPORT_SIZE)),
+        )
+        .route("/{id}", get(get_one))
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!("Import database error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Database error".to_string(),
+    )
+}
+
+/// Query parameters of an import upload.
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    /// `markdown`, `enex` or `keep`
+    pub format: ImportFormat,
+    /// Notebook receiving the imported notes and folders; top level if missing
+    pub notebook_id: Option<Uuid>,
+}
+
+/// Start an import of the uploaded file (sent as the raw request body).
+/// The notes are imported in the background; the returned job reports the progress.
+/// Fails with 409 while the user has another unfinished import, and with 503 while
+/// the server is busy with other imports.
+pub async fn start(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<ImportJob>), (StatusCode, String)> {
+    info!(
+        "User {} is importing {} bytes of {} notes",
+        user_id,
+        body.len(),
+        query.format.as_str()
+    );
+    if body.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "The upload is empty".to_string()));
+    }
+    if let Some(notebook_id) = query.notebook_id {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM notebooks
+             WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL)",
+        )
+        .bind(notebook_id)
+        .bind(user_id)
+        .fetch_one(&state.pool)
+        .await
+        .map_err(db_error)?;
+        if !exists {
+            info!("Notebook {} not found for user {}", notebook_id, user_id);
+            return Err((StatusCode::NOT_FOUND, "Notebook does not exist".to_string()));
+        }
+    }
+
+    // Uploads are not queued, so a busy server does not pile them up in memory
+    let Ok(slot) = state.import_slots.clone().try_acquire_owned() else {
+        warn!("No import slot left for user {}", user_id);
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Too many imports are running, try again later".to_string(),
+        ));
+    };
+    let job = create_import_job(
+        &state.pool,
+        user_id,
+        query.format.as_str(),
+        query.notebook_id,
+    )
+    .await
+    .map_err(db_error)?
+    .ok_or((
+        StatusCode::CONFLICT,
+        "Another import is still in progress".to_string(),
+    ))?;
+    tokio::spawn(run_import(
+        state,
+        slot,
+        user_id,
+        job.id,
+        query.format,
+        query.notebook_id,
+        body,
+    ));
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// List the import jobs of the user, newest first.
+pub async fn list(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> Result<Json<Vec<ImportJob>>, (StatusCode, String)> {
+    info!("User {} is listing import jobs", user_id);
+    let jobs = list_import_jobs(&state.pool, user_id)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(jobs))
+}
+
+/// Get the progress and error report of one import job.
+pub async fn get_one(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ImportJob>, (StatusCode, String)> {
+    info!("User {} is fetching import job {}", user_id, id);
+    match get_import_job(&state.pool, user_id, id)
+        .await
+        .map_err(db_error)?
+    {
+        Some(job) => Ok(Json(job)),
+        None => Err((StatusCode::NOT_FOUND, "Import not found".to_string())),
+    }
+}
+
+impl ImportProgress {
+    fn report(&mut self, item: &str, error: impl Into<String>) {
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(ImportItemError {
+                item: item.to_string(),
+                error: error.into(),
+            });
+        }
+    }
+}
+
+/// Imports the notes of an upload and records the outcome in the job.
+async fn run_import(
+    state: AppState,
+    _slot: OwnedSemaphorePermit,
+    user_id: Uuid,
+    job_id: Uuid,
+    format: ImportFormat,
+    notebook_id: Option<Uuid>,
+    body: Bytes,
+) {
+    let mut progress = ImportProgress::default();
+    let result = import_notes(
+        &state,
+        user_id,
+        job_id,
+        format,
+        notebook_id,
+        body,
+        &mut progress,
+    )
+    .await;
+    let error = match &result {
+        Ok(()) => {
+            info!(
+                "Import {} of user {} completed: {} notes imported, {} failed",
+                job_id, user_id, progress.imported, progress.failed
+            );
+            None
+        }
+        Err(e) => {
+            warn!("Import {} of user {} failed: {}", job_id, user_id, e);
+            Some(e.as_str())
+        }
+    };
+    if let Err(e) = finish_import_job(&state.pool, job_id, &progress, error).await {
+        error!("Failed to finish import {}: {}", job_id, e);
+    }
+}
+
+/// Stores the notes of an upload while it is parsed. Fails only if the upload as a
+/// whole cannot be read; notes stored before that are kept.
+async fn import_notes(
+    state: &AppState,
+    user_id: Uuid,
+    job_id: Uuid,
+    format: ImportFormat,
+    notebook_id: Option<Uuid>,
+    body: Bytes,
+    progress: &mut ImportProgress,
+) -> Result<(), String> {
+    start_import_job(&state.pool, job_id).await.map_err(|e| {
+        error!("Failed to start import {}: {}", job_id, e);
+        "Database error".to_string()
+    })?;
+    // The parser waits while the queue is full and stops once it is closed
+    let (sender, mut events) = mpsc::channel(QUEUED_NOTES);
+    let parser = tokio::task::spawn_blocking(move || {
+        parse_upload(format, &body, &mut |event| {
+            sender.blocking_send(event).is_ok()
+        })
+    });
+
+    let attachment_dir = state.config.attachment_dir.as_ref().map(PathBuf::from);
+    // Notebooks created for folders, by path
+    let mut notebooks: HashMap<Vec<String>, Option<Uuid>> = HashMap::new();
+    while let Some(event) = events.recv().await {
+        let item = match event {
+            ImportEvent::Found(count) => {
+                progress.total += count as i32;
+                continue;
+            }
+            ImportEvent::Item(item) => item,
+        };
+        progress.processed += 1;
+        match item {
+            Ok(note) => {
+                let item = note.item.clone();
+                match import_note(
+                    state,
+                    user_id,
+                    notebook_id,
+                    attachment_dir.as_deref(),
+                    &mut notebooks,
+                    note,
+                    progress,
+                )
+                .await
+                {
+                    Ok(()) => progress.imported += 1,
+                    Err(e) => {
+                        progress.failed += 1;
+                        progress.report(&item, e);
+                    }
+                }
+            }
+            Err(e) => {
+                progress.failed += 1;
+                progress.report(&e.item, e.error);
+            }
+        }
+        if progress.processed % PROGRESS_INTERVAL == 0
+            && let Err(e) = update_import_job(&state.pool, job_id, progress).await
+        {
+            error!("Failed to update import {}: {}", job_id, e);
+        }
+    }
+    parser.await.map_err(|e| {
+        error!("Import {} parser panicked: {}", job_id, e);
+        "The upload could not be read".to_string()
+    })?
+}
+
+/// Tags of an imported note that are valid, without duplicates.
+fn clean_tags(note: &ImportedNote, progress: &mut ImportProgress) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in &note.tags {
+        match validate_tag_name(tag) {
+            Ok(tag) if !tags.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) => {
+                tags.push(tag)
+            }
+            Ok(_) => {}
+            Err(e) => progress.report(&note.item, format!("Tag {:?} skipped: {}", tag, e)),
+        }
+    }
+    if tags.len() > MAX_TAGS_PER_NOTE {
+        progress.report(
+            &note.item,
+            format!("Only the first {} tags were imported", MAX_TAGS_PER_NOTE),
+        );
+        tags.truncate(MAX_TAGS_PER_NOTE);
+    }
+    tags
+}
+
+/// Notebook for the folders of a note, created under `root` if missing.
+async fn folder_notebook(
+    state: &AppState,
+    user_id: Uuid,
+    root: Option<Uuid>,
+    notebooks: &mut HashMap<Vec<String>, Option<Uuid>>,
+    folders: &[String],
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let mut parent = root;
+    for depth in 1..=folders.len() {
+        let path = folders[..depth].to_vec();
+        parent = match notebooks.get(&path) {
+            Some(id) => *id,
+            None => {
+                let name = folders[depth - 1].trim();
+                let id = if name.is_empty() {
+                    parent
+                } else {
+                    Some(find_or_create_notebook(&state.pool, user_id, parent, name).await?)
+                };
+                notebooks.insert(path, id);
+                id
+            }
+        };
+    }
+    Ok(parent)
+}
+
+async fn import_note(
+    state: &AppState,
+    user_id: Uuid,
+    root: Option<Uuid>,
+    attachment_dir: Option<&path::Path>,
+    notebooks: &mut HashMap<Vec<String>, Option<Uuid>>,
+    mut note: ImportedNote,
+    progress: &mut ImportProgress,
+) -> Result<(), String> {
+    for warning in std::mem::take(&mut note.warnings) {
+        progress.report(&note.item, warning);
+    }
+    let tags = clean_tags(&note, progress);
+
+    // Attachments are stored as files; without storage their images are left out
+    let mut attachments = Vec::new();
+    match attachment_dir {
+        Some(_) => {
+            for file in &note.attachments {
+                let key = format!("{}/{}-{}", IMPORTS_DIR, file.id, file_name(&file.name));
+                attachments.push((file.id, file.name.clone(), key));
+            }
+        }
+        None if note.attachments.is_empty() => {}
+        None => {
+            progress.report(
+                &note.item,
+                "Attachments were skipped: the server does not store attachment files",
+            );
+            note.content
+                .blocks
+                .retain(|block| !matches!(block, Block::Image { .. }));
+        }
+    }
+    note.content.validate()?;
+
+    let notebook_id = folder_notebook(state, user_id, root, notebooks, &note.folders)
+        .await
+        .map_err(|e| {
+            error!(
+                "DB error creating notebooks for import of user {}: {}",
+                user_id, e
+            );
+            "Database error".to_string()
+        })?;
+    if let Some(dir) = attachment_dir
+        && !attachments.is_empty()
+    {
+        store_files(dir, &note, &attachments).await?;
+    }
+    let inserted = insert_imported_note(
+        &state.pool,
+        user_id,
+        &NewImportedNote {
+            notebook_id,
+            title: &note.title,
+            content: &note.content,
+            tags: &tags,
+            created_at: note.created_at,
+            updated_at: note.updated_at,
+            is_pinned: note.is_pinned,
+            is_archived: note.is_archived,
+            attachments: &attachments,
+        },
+    )
+    .await;
+    if let Err(e) = inserted {
+        error!("DB error importing note for user {}: {}", user_id, e);
+        if let Some(dir) = attachment_dir {
+            remove_files(dir, &attachments).await;
+        }
+        return Err("Database error".to_string());
+    }
+    Ok(())
+}
+
+/// Writes the attachment files of a note under their storage keys.
+/// Nothing is left behind if one of them cannot be written.
+async fn store_files(
+    dir: &path::Path,
+    note: &ImportedNote,
+    attachments: &[(Uuid, String, String)],
+) -> Result<(), String> {
+    let imports_dir = dir.join(IMPORTS_DIR);
+    let failed = |e: std::io::Error| {
+        error!(
+            "Failed to store imported attachments in {}: {}",
+            imports_dir.display(),
+            e
+        );
+        "Attachments could not be stored".to_string()
+    };
+    tokio::fs::create_dir_all(&imports_dir)
+        .await
+        .map_err(failed)?;
+    for (i, (file, (_, _, key))) in note.attachments.iter().zip(attachments).enumerate() {
+        if let Err(e) = tokio::fs::write(dir.join(key), &file.data).await {
+            remove_files(dir, &attachments[..i]).await;
+            return Err(failed(e));
+        }
+    }
+    Ok(())
+}
+
+/// Removes stored attachment files, e.g. of a note that could not be saved.
+async fn remove_files(dir: &path::Path, attachments: &[(Uuid, String, String)]) {
+    for (_, _, key) in attachments {
+        if let Err(e) = tokio::fs::remove_file(dir.join(key)).await {
+            warn!("Failed to remove imported attachment {}: {}", key, e);
+        }
+    }
+}
+
+/// Removes imported files no attachment refers to, e.g. after their notes were purged
+/// or the server stopped while importing them. Returns the number of removed files.
+pub async fn remove_orphaned_files(pool: &sqlx::PgPool, dir: &path::Path) -> Result<usize, String> {
+    let imports_dir = dir.join(IMPORTS_DIR);
+    let mut entries = match tokio::fs::read_dir(&imports_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut candidates = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        let old = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > ORPHAN_FILE_AGE);
+        if metadata.is_file()
+            && old
+            && let Some(name) = entry.file_name().to_str()
+        {
+            candidates.push(format!("{}/{}", IMPORTS_DIR, name));
+        }
+    }
+    if candidates.is_empty() {
+        return Ok(0);
+    }
+    let used = used_storage_keys(pool, &candidates)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut removed = 0;
+    for key in candidates.iter().filter(|key| !used.contains(key)) {
+        match tokio::fs::remove_file(dir.join(key)).await {
+            Ok(()) => removed += 1,
+            Err(e) => warn!("Failed to remove orphaned import file {}: {}", key, e),
+        }
+    }
+    Ok(removed)
+}
@@ -0,0 +1,277 @@
+use crate::{
+    database::mfa::{
+        consume_recovery_code, create_pending_totp, disable_totp, enable_totp, get_totp,
+        mark_totp_step_used, replace_recovery_codes,
+    },
+    database::token::verify_mfa_challenge_token,
+    models::user::User,
+    routes::auth::{
+        ensure_enabled, ensure_not_locked, issue_tokens, register_failed_login, session_info,
+    },
+    state::AppState,
+    utils::extractors::AuthUser,
+    utils::password::verify_password,
+    utils::totp,
+};
+use axum::{
+    Router,
+    extract::{ConnectInfo, Json, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::net::SocketAddr;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Returns a router for managing the second factor of the authenticated user.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/enroll", post(enroll))
+        .route("/confirm", post(confirm))
+        .route("/disable", post(disable))
+        .route("/recovery-codes", post(regenerate_recovery_codes))
+}
+
+/// Response for enrollment – secret to type in, or URI to render as a QR code.
+#[derive(Serialize)]
+pub struct EnrollResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Payload carrying a TOTP code.
+#[derive(Deserialize)]
+pub struct CodePayload {
+    pub code: String,
+}
+
+/// Payload for disabling 2FA – requires password and a current code.
+#[derive(Deserialize)]
+pub struct DisablePayload {
+    pub password: String,
+    pub code: String,
+}
+
+/// Response with freshly generated recovery codes (shown once).
+#[derive(Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Payload for completing a 2FA login.
+/// `code` is either a TOTP code or a recovery code.
+#[derive(Deserialize)]
+pub struct LoginMfaRequest {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+/// Maps a database error to the common 500 response.
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!("DB error in 2FA handler: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Database error".to_string(),
+    )
+}
+
+/// Fetches a user by id.
+async fn get_user(pool: &PgPool, user_id: Uuid) -> Result<User, (StatusCode, String)> {
+    sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)
+}
+
+/// Checks a TOTP code of a user and marks its time step as used.
+/// Returns false for wrong or replayed codes.
+async fn check_totp_code(
+    pool: &PgPool,
+    user: &User,
+    secret: &str,
+    code: &str,
+) -> Result<bool, (StatusCode, String)> {
+    let now = Utc::now().timestamp() as u64;
+    let step = totp::verify_code(secret, &user.email, code, now).map_err(|e| {
+        error!("Invalid TOTP secret for user {}: {}", user.id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "2FA error".to_string())
+    })?;
+    match step {
+        Some(step) => mark_totp_step_used(pool, user.id, step)
+            .await
+            .map_err(db_error),
+        None => Ok(false),
+    }
+}
+
+/// Checks a TOTP code of a user with enabled 2FA.
+/// Recovery codes are accepted as well when `allow_recovery` is set.
+async fn check_second_factor(
+    pool: &PgPool,
+    user: &User,
+    code: &str,
+    allow_recovery: bool,
+) -> Result<bool, (StatusCode, String)> {
+    let totp = match get_totp(pool, user.id).await.map_err(db_error)? {
+        Some(t) if t.enabled_at.is_some() => t,
+        _ => return Err((StatusCode::BAD_REQUEST, "2FA is not enabled".to_string())),
+    };
+    if check_totp_code(pool, user, &totp.secret, code).await? {
+        return Ok(true);
+    }
+    if allow_recovery {
+        return consume_recovery_code(pool, user.id, code)
+            .await
+            .map_err(db_error);
+    }
+    Ok(false)
+}
+
+/// Start 2FA enrollment: generate a secret and return it with the otpauth URI.
+/// 2FA is not active until the first code is confirmed.
+pub async fn enroll(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> Result<Json<EnrollResponse>, (StatusCode, String)> {
+    info!("User {} is enrolling TOTP", user_id);
+    let user = get_user(&state.pool, user_id).await?;
+
+    let secret = totp::generate_secret();
+    let otpauth_uri = totp::otpauth_uri(&secret, &user.email).map_err(|e| {
+        error!("Failed to build otpauth URI for {}: {}", user_id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "2FA error".to_string())
+    })?;
+
+    if create_pending_totp(&state.pool, user_id, &secret)
+        .await
+        .map_err(db_error)?
+        .is_none()
+    {
+        info!("User {} already has 2FA enabled", user_id);
+        return Err((StatusCode::CONFLICT, "2FA is already enabled".to_string()));
+    }
+
+    Ok(Json(EnrollResponse {
+        secret,
+        otpauth_uri,
+    }))
+}
+
+/// Confirm enrollment with a code from the authenticator app.
+/// Enables 2FA and returns the recovery codes.
+pub async fn confirm(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(payload): Json<CodePayload>,
+) -> Result<Json<RecoveryCodesResponse>, (StatusCode, String)> {
+    let user = get_user(&state.pool, user_id).await?;
+    let pending = match get_totp(&state.pool, user_id).await.map_err(db_error)? {
+        Some(t) if t.enabled_at.is_none() => t,
+        Some(_) => return Err((StatusCode::CONFLICT, "2FA is already enabled".to_string())),
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "No 2FA enrollment in progress".to_string(),
+            ));
+        }
+    };
+
+    if !check_totp_code(&state.pool, &user, &pending.secret, &payload.code).await? {
+        info!("User {} entered a wrong code while confirming 2FA", user_id);
+        return Err((StatusCode::BAD_REQUEST, "Invalid code".to_string()));
+    }
+
+    let recovery_codes = enable_totp(&state.pool, user_id).await.map_err(db_error)?;
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// Disable 2FA. Requires the account password and a current TOTP or recovery code.
+pub async fn disable(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(payload): Json<DisablePayload>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = get_user(&state.pool, user_id).await?;
+    if !verify_password(&payload.password, &user.password) {
+        info!(
+            "User {} entered a wrong password while disabling 2FA",
+            user_id
+        );
+        return Err((StatusCode::FORBIDDEN, "Invalid password".to_string()));
+    }
+    if !check_second_factor(&state.pool, &user, &payload.code, true).await? {
+        return Err((StatusCode::FORBIDDEN, "Invalid code".to_string()));
+    }
+
+    disable_totp(&state.pool, user_id).await.map_err(db_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Replace the recovery codes. Requires a current TOTP code.
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(payload): Json<CodePayload>,
+) -> Result<Json<RecoveryCodesResponse>, (StatusCode, String)> {
+    let user = get_user(&state.pool, user_id).await?;
+    if !check_second_factor(&state.pool, &user, &payload.code, false).await? {
+        return Err((StatusCode::FORBIDDEN, "Invalid code".to_string()));
+    }
+
+    let recovery_codes = replace_recovery_codes(&state.pool, user_id)
+        .await
+        .map_err(db_error)?;
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+/// Complete a login for an account with 2FA.
+/// Exchanges the challenge token from `/login` and a TOTP or recovery code for the real tokens.
+pub async fn login_mfa(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<LoginMfaRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let ip = addr.ip();
+
+    let claims =
+        verify_mfa_challenge_token(&payload.challenge_token, &state.jwt_keys).map_err(|e| {
+            warn!("Invalid 2FA challenge token: {}", e);
+            (
+                StatusCode::UNAUTHORIZED,
+                "Invalid or expired challenge".to_string(),
+            )
+        })?;
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| {
+        (
+            StatusCode::UNAUTHORIZED,
+            "Invalid or expired challenge".to_string(),
+        )
+    })?;
+
+    let user = get_user(&state.pool, user_id).await?;
+    ensure_enabled(&user)?;
+    ensure_not_locked(&user)?;
+    if !check_second_factor(&state.pool, &user, &payload.code, true).await? {
+        warn!(
+            "2FA login failed: invalid code for user {} from {}",
+            user_id, ip
+        );
+        register_failed_login(&state, user_id).await?;
+        return Err((StatusCode::UNAUTHORIZED, "Invalid code".to_string()));
+    }
+
+    let session = session_info(claims.platform, &addr, &headers);
+    let tokens = issue_tokens(&state, user_id, claims.platform, &session).await?;
+
+    info!(
+        "User {} logged in with 2FA (platform: {})",
+        &user.email, &claims.platform
+    );
+    Ok(Json(tokens))
+}
@@ -1,10 +1,23 @@
+pub mod access_tokens;
+pub mod account;
+pub mod admin;
 pub mod api;
 pub mod attachments;
 pub mod auth;
+pub mod email_verification;
+pub mod export;
+pub mod imports;
+pub mod mfa;
 pub mod note_settings;
+pub mod note_versions;
 pub mod notebooks;
 pub mod notes;
+pub mod oidc;
+pub mod password;
 pub mod public;
 pub mod reminders;
+pub mod sessions;
 pub mod shared_notes;
+pub mod tags;
+pub mod trash;
 pub mod user_settings;
@@ -17,7 +17,7 @@ pub fn router() -> Router<AppState> {
         .route("/{id}", get(get_one).put(update).delete(delete_one))
 }
 
-/// List all note settings for notes owned by the user.
+/// List all note settings for notes owned by the user, except notes in the trash.
 pub async fn list(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -26,7 +26,7 @@ pub async fn list(
     let rows = sqlx::query_as::<_, NoteSettings>(
         "SELECT ns.* FROM note_settings ns
          JOIN notes n ON ns.note_id = n.id
-         WHERE n.user_id = $1",
+         WHERE n.user_id = $1 AND n.deleted_at IS NULL",
     )
     .bind(user_id)
     .fetch_all(&state.pool)
@@ -0,0 +1,159 @@
+use crate::{
+    database::note_versions::{
+        DEFAULT_VERSION_LIMIT, NoteVersionSummary, get_version, list_versions, restore_version,
+    },
+    models::note_version::NoteVersion,
+    routes::notes::{fetch_note, get_note},
+    state::AppState,
+    utils::extractors::AuthUser,
+};
+use axum::{
+    Router,
+    extract::{Json, Path, Query, State},
+    http::StatusCode,
+    response::Response,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Returns a router for the version history of a note,
+/// nested under `/api/notes/{note_id}/versions`.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list))
+        .route("/diff", get(diff))
+        .route("/{version_no}", get(get_one))
+        .route("/{version_no}/restore", post(restore))
+}
+
+/// Query parameters for comparing two versions.
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    pub from: i32,
+    /// Version to compare with (default: the current note)
+    pub to: Option<i32>,
+}
+
+/// Differences between two versions of a note.
+#[derive(Serialize)]
+pub struct VersionDiff {
+    pub from: i32,
+    /// None when compared with the current note
+    pub to: Option<i32>,
+    pub title_from: Option<String>,
+    pub title_to: Option<String>,
+    /// Unified diff of the content, as pretty-printed JSON
+    pub content_patch: String,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!("Note versions database error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Database error".to_string(),
+    )
+}
+
+fn version_not_found(note_id: Uuid, version_no: i32) -> (StatusCode, String) {
+    info!("Version {} of note {} not found", version_no, note_id);
+    (StatusCode::NOT_FOUND, "Version does not exist".to_string())
+}
+
+/// List the versions of a note, newest first.
+pub async fn list(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(note_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<Vec<NoteVersionSummary>>), (StatusCode, String)> {
+    info!("User {} is listing versions of note {}", user_id, note_id);
+    let versions = list_versions(&state.pool, user_id, note_id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Note does not exist".to_string()))?;
+    Ok((StatusCode::OK, Json(versions)))
+}
+
+/// Get one version with its content.
+pub async fn get_one(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((note_id, version_no)): Path<(Uuid, i32)>,
+) -> Result<(StatusCode, Json<NoteVersion>), (StatusCode, String)> {
+    let version = get_version(&state.pool, user_id, note_id, version_no)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| version_not_found(note_id, version_no))?;
+    Ok((StatusCode::OK, Json(version)))
+}
+
+/// Compare two versions, or a version with the current note.
+pub async fn diff(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(note_id): Path<Uuid>,
+    Query(query): Query<DiffQuery>,
+) -> Result<(StatusCode, Json<VersionDiff>), (StatusCode, String)> {
+    let from = get_version(&state.pool, user_id, note_id, query.from)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| version_not_found(note_id, query.from))?;
+    let (title_to, content_to) = match query.to {
+        Some(to) => {
+            let version = get_version(&state.pool, user_id, note_id, to)
+                .await
+                .map_err(db_error)?
+                .ok_or_else(|| version_not_found(note_id, to))?;
+            (version.title, version.content)
+        }
+        None => {
+            let note = fetch_note(&state.pool, user_id, note_id).await?;
+            (Some(note.title), note.content)
+        }
+    };
+
+    let (old, new) = (pretty(&from.content), pretty(&content_to));
+    let content_patch = diffy::create_patch(&old, &new).to_string();
+    Ok((
+        StatusCode::OK,
+        Json(VersionDiff {
+            from: query.from,
+            to: query.to,
+            title_from: from.title,
+            title_to,
+            content_patch,
+        }),
+    ))
+}
+
+/// Content as pretty-printed JSON, one value per line, so diffs are readable.
+fn pretty(content: &Value) -> String {
+    let mut text = serde_json::to_string_pretty(content).unwrap_or_default();
+    text.push('\n');
+    text
+}
+
+/// Restore a version as the current title and content of the note.
+pub async fn restore(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path((note_id, version_no)): Path<(Uuid, i32)>,
+) -> Result<Response, (StatusCode, String)> {
+    let limit = state
+        .config
+        .note_version_limit
+        .unwrap_or(DEFAULT_VERSION_LIMIT);
+    if !restore_version(&state.pool, user_id, note_id, version_no, limit)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(version_not_found(note_id, version_no));
+    }
+    info!(
+        "User {} restored version {} of note {}",
+        user_id, version_no, note_id
+    );
+    get_note(State(state), AuthUser(user_id), Path(note_id)).await
+}
@@ -1,4 +1,7 @@
-use crate::{state::AppState, utils::extractors::AuthUser};
+use crate::{
+    database::trash::trash_notebook, routes::export::export_notebook, state::AppState,
+    utils::extractors::AuthUser,
+};
 use axum::{
     Router,
     extract::{Json, Path, State},
@@ -16,6 +19,7 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(list).post(create))
         .route("/{id}", get(get_one).put(update).delete(delete_one))
+        .route("/{id}/export", get(export_notebook))
         .nest(
             "/{id}/notes",
             Router::new().route("/", get(list_notes_in_notebook)),
@@ -28,18 +32,19 @@ pub async fn list(
     AuthUser(user_id): AuthUser,
 ) -> Result<(StatusCode, Json<Vec<Notebook>>), (StatusCode, String)> {
     info!("User {} requested notebook list", user_id);
-    let rows =
-        sqlx::query_as::<_, Notebook>("SELECT * FROM notebooks WHERE user_id = $1 ORDER BY name")
-            .bind(user_id)
-            .fetch_all(&state.pool)
-            .await
-            .map_err(|e| {
-                error!("DB error fetching notebooks for user {}: {}", user_id, e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Database error".to_string(),
-                )
-            })?;
+    let rows = sqlx::query_as::<_, Notebook>(
+        "SELECT * FROM notebooks WHERE user_id = $1 AND deleted_at IS NULL ORDER BY name",
+    )
+    .bind(user_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| {
+        error!("DB error fetching notebooks for user {}: {}", user_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
     Ok((StatusCode::OK, Json(rows)))
 }
 
@@ -79,22 +84,23 @@ pub async fn get_one(
     Path(id): Path<Uuid>,
 ) -> Result<(StatusCode, Json<Notebook>), (StatusCode, String)> {
     info!("User {} is fetching notebook id {}", user_id, id);
-    let opt =
-        sqlx::query_as::<_, Notebook>("SELECT * FROM notebooks WHERE id = $1 AND user_id = $2")
-            .bind(id)
-            .bind(user_id)
-            .fetch_optional(&state.pool)
-            .await
-            .map_err(|e| {
-                error!(
-                    "DB error fetching notebook {} for user {}: {}",
-                    id, user_id, e
-                );
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Database error".to_string(),
-                )
-            })?;
+    let opt = sqlx::query_as::<_, Notebook>(
+        "SELECT * FROM notebooks WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| {
+        error!(
+            "DB error fetching notebook {} for user {}: {}",
+            id, user_id, e
+        );
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
 
     if let Some(nb) = opt {
         Ok((StatusCode::OK, Json(nb)))
@@ -122,7 +128,7 @@ pub async fn update(
         r#"UPDATE notebooks SET
               name      = COALESCE($2, name),
               parent_id = COALESCE($3, parent_id)
-          WHERE id = $1 AND user_id = $4"#,
+          WHERE id = $1 AND user_id = $4 AND deleted_at IS NULL"#,
     )
     .bind(id)
     .bind(p.name)
@@ -147,17 +153,14 @@ pub async fn update(
     get_one(State(state), AuthUser(user_id), Path(id)).await
 }
 
-/// Delete a notebook for a user.
+/// Move a notebook of a user, with its sub-notebooks and notes, to the trash.
 pub async fn delete_one(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     info!("User {} is deleting notebook id {}", user_id, id);
-    let result = sqlx::query("DELETE FROM notebooks WHERE id = $1 AND user_id = $2")
-        .bind(id)
-        .bind(user_id)
-        .execute(&state.pool)
+    let trashed = trash_notebook(&state.pool, user_id, id)
         .await
         .map_err(|e| {
             error!(
@@ -170,7 +173,7 @@ pub async fn delete_one(
             )
         })?;
 
-    if result.rows_affected() == 0 {
+    if !trashed {
         info!("Notebook {} not found for user {}", id, user_id);
         return Err((StatusCode::NOT_FOUND, "Not found".to_string()));
     }
@@ -185,7 +188,8 @@ pub async fn list_notes_in_notebook(
 ) -> Result<(StatusCode, Json<Vec<Note>>), (StatusCode, String)> {
     info!("User {} is listing notes in notebook {}", user_id, nb_id);
     let notes = sqlx::query_as::<_, Note>(
-        "SELECT * FROM notes WHERE notebook_id = $1 AND user_id = $2 ORDER BY updated_at DESC",
+        "SELECT * FROM notes WHERE notebook_id = $1 AND user_id = $2 AND deleted_at IS NULL
+         ORDER BY updated_at DESC",
     )
     .bind(nb_id)
     .bind(user_id)
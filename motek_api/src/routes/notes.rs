@@ -1,55 +1,323 @@
-use crate::{state::AppState, utils::extractors::AuthUser};
+use crate::{
+    database::note_batch::{BatchItemResult, BatchItemStatus, BatchOperation, run_batch},
+    database::note_versions::{DEFAULT_VERSION_LIMIT, lock_note, record_version},
+    database::notes::{
+        NoteCursor, NoteFilter, NoteSearchHit, NoteSort, count_user_attachments,
+        default_sort_for_user, list_notes_page, search_notes,
+    },
+    database::tags::set_note_tags,
+    database::trash::trash_notes,
+    routes::export::export_note,
+    state::AppState,
+    utils::extractors::AuthUser,
+    utils::validators::{validate_tag_name, validate_tags},
+};
 use axum::{
     Router,
-    extract::{Json, Path, State},
-    http::StatusCode,
-    routing::get,
+    extract::{Json, Path, Query, State},
+    http::{
+        HeaderMap, StatusCode,
+        header::{ETAG, IF_MATCH},
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
 use tracing::{error, info};
 use uuid::Uuid;
 
-use crate::models::note::Note;
+use crate::models::{note::Note, note_content::NoteContent};
 
 /// Returns a router for note endpoints.
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(list_notes).post(create_note))
+        .route("/search", get(search))
+        .route("/batch", post(batch))
         .route("/{id}", get(get_note).put(update_note).delete(delete_note))
+        .route("/{id}/export", get(export_note))
+}
+
+/// Query parameters for listing notes. Every filter is optional.
+#[derive(Deserialize)]
+pub struct ListNotesQuery {
+    /// Page size, 1..=200 (default 50)
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub notebook_id: Option<Uuid>,
+    pub is_archived: Option<bool>,
+    pub is_pinned: Option<bool>,
+    pub tag: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub updated_after: Option<DateTime<Utc>>,
+    pub updated_before: Option<DateTime<Utc>>,
+    /// `updated_at`, `created_at` or `title`; defaults to the user's `default_sort`
+    pub sort: Option<NoteSort>,
+    /// `asc` or `desc`; defaults to newest first, or A-Z for titles
+    pub order: Option<SortOrder>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Length of note previews in lists, in characters.
+const PREVIEW_LENGTH: usize = 200;
+
+/// Note in a list, with the start of its text.
+#[derive(Serialize)]
+pub struct NoteListItem {
+    #[serde(flatten)]
+    pub note: Note,
+    pub preview: String,
+}
+
+impl From<Note> for NoteListItem {
+    fn from(note: Note) -> Self {
+        let preview = NoteContent::from_legacy(&note.content).preview(PREVIEW_LENGTH);
+        NoteListItem { note, preview }
+    }
 }
 
+/// One page of notes. `next_cursor` is absent on the last page.
 #[derive(Serialize)]
-pub struct NotesListResponse(Vec<Note>);
+pub struct NotesListResponse {
+    pub notes: Vec<NoteListItem>,
+    pub next_cursor: Option<String>,
+}
 
-/// List all notes for a user.
+/// List notes for a user, one page at a time.
 pub async fn list_notes(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
+    Query(query): Query<ListNotesQuery>,
 ) -> Result<(StatusCode, Json<NotesListResponse>), (StatusCode, String)> {
     info!("User {} requested notes list", user_id);
-    let notes = sqlx::query_as::<_, Note>(
-        "SELECT * FROM notes WHERE user_id = $1 ORDER BY updated_at DESC",
-    )
-    .bind(user_id)
-    .fetch_all(&state.pool)
-    .await
-    .map_err(|e| {
+    let db_error = |e: sqlx::Error| {
         error!("DB error fetching notes for user {}: {}", user_id, e);
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             "Database error".to_string(),
         )
-    })?;
+    };
+
+    let sort = match query.sort {
+        Some(sort) => sort,
+        None => default_sort_for_user(&state.pool, user_id)
+            .await
+            .map_err(db_error)?
+            .unwrap_or(NoteSort::UpdatedAt),
+    };
+    let descending = match query.order {
+        Some(order) => matches!(order, SortOrder::Desc),
+        None => sort.default_descending(),
+    };
+    let cursor = match query.cursor.as_deref() {
+        Some(raw) => {
+            let cursor = NoteCursor::decode(raw)
+                .filter(|c| c.sort == sort && c.descending == descending)
+                .ok_or_else(|| {
+                    info!("User {} sent an invalid notes cursor", user_id);
+                    (StatusCode::BAD_REQUEST, "Invalid cursor".to_string())
+                })?;
+            Some(cursor)
+        }
+        None => None,
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let filter = NoteFilter {
+        notebook_id: query.notebook_id,
+        is_archived: query.is_archived,
+        is_pinned: query.is_pinned,
+        tag: query.tag,
+        created_after: query.created_after,
+        created_before: query.created_before,
+        updated_after: query.updated_after,
+        updated_before: query.updated_before,
+    };
+
+    let page = list_notes_page(
+        &state.pool,
+        user_id,
+        &filter,
+        sort,
+        descending,
+        cursor.as_ref(),
+        limit,
+    )
+    .await
+    .map_err(db_error)?;
 
-    Ok((StatusCode::OK, Json(NotesListResponse(notes))))
+    Ok((
+        StatusCode::OK,
+        Json(NotesListResponse {
+            notes: page.notes.into_iter().map(NoteListItem::from).collect(),
+            next_cursor: page.next_cursor.map(|c| c.encode()),
+        }),
+    ))
+}
+
+/// Query parameters for searching notes.
+#[derive(Deserialize)]
+pub struct SearchNotesQuery {
+    /// Search terms: words, `"exact phrase"`, `or`, `-excluded`
+    pub q: String,
+    /// Number of results, 1..=100 (default 20)
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Full-text search over the user's notes, best match first.
+/// Words are stemmed using the language from the user's settings.
+pub async fn search(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Query(query): Query<SearchNotesQuery>,
+) -> Result<(StatusCode, Json<Vec<NoteSearchHit>>), (StatusCode, String)> {
+    let q = query.q.trim();
+    if q.is_empty() || q.chars().count() > 256 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Search query must be 1 to 256 characters".to_string(),
+        ));
+    }
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+    info!("User {} is searching notes", user_id);
+
+    let hits = search_notes(&state.pool, user_id, q, limit, offset)
+        .await
+        .map_err(|e| {
+            error!("DB error searching notes for user {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+    Ok((StatusCode::OK, Json(hits)))
+}
+
+/// Maximum number of notes in one batch request.
+const MAX_BATCH_SIZE: usize = 500;
+
+/// Payload for applying one operation to many notes.
+#[derive(Deserialize)]
+pub struct BatchPayload {
+    pub ids: Vec<Uuid>,
+    pub operation: BatchOperation,
+}
+
+/// Per-note results of a batch request, in the order of the request.
+#[derive(Serialize)]
+pub struct BatchResponse {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Apply one operation (archive, pin, move, tag, delete, …) to many notes of a user
+/// in a single transaction. Notes the user does not own are reported as `not_found`, notes
+/// that already have the most tags allowed as `too_many_tags` when adding a tag.
+pub async fn batch(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(payload): Json<BatchPayload>,
+) -> Result<(StatusCode, Json<BatchResponse>), (StatusCode, String)> {
+    let mut ids = payload.ids;
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+    if ids.is_empty() || ids.len() > MAX_BATCH_SIZE {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("A batch must contain 1 to {} notes", MAX_BATCH_SIZE),
+        ));
+    }
+    let mut operation = payload.operation;
+    if let BatchOperation::AddTag { tag } | BatchOperation::RemoveTag { tag } = &mut operation {
+        *tag = validate_tag_name(tag).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+    info!(
+        "User {} is running {:?} on {} notes",
+        user_id,
+        operation,
+        ids.len()
+    );
+
+    let results = run_batch(&state.pool, user_id, &ids, &operation)
+        .await
+        .map_err(|e| {
+            error!("DB error in note batch for user {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Target notebook does not exist".to_string(),
+            )
+        })?;
+    let succeeded = results
+        .iter()
+        .filter(|r| r.status == BatchItemStatus::Ok)
+        .count();
+    Ok((
+        StatusCode::OK,
+        Json(BatchResponse {
+            succeeded,
+            failed: results.len() - succeeded,
+            results,
+        }),
+    ))
+}
+
+/// Parses note content from a request; images must reference attachments of the
+/// user's notes.
+async fn parse_content(
+    pool: &PgPool,
+    user_id: Uuid,
+    value: serde_json::Value,
+) -> Result<NoteContent, (StatusCode, String)> {
+    let content = NoteContent::from_value(value).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let attachments = content.attachment_ids();
+    if !attachments.is_empty() {
+        let owned = count_user_attachments(pool, user_id, &attachments)
+            .await
+            .map_err(|e| {
+                error!("DB error checking attachments of user {}: {}", user_id, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Database error".to_string(),
+                )
+            })?;
+        if owned != attachments.len() as i64 {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Image references an unknown attachment".to_string(),
+            ));
+        }
+    }
+    Ok(content)
 }
 
 #[derive(Deserialize)]
 pub struct CreateNotePayload {
     pub title: String,
+    /// Block document, or a string taken as plain text
     pub content: serde_json::Value,
     pub notebook_id: Option<Uuid>,
-    pub tags: serde_json::Value,
+    /// Tag names; missing tags are created
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 /// Create a new note for a user.
@@ -57,117 +325,224 @@ pub async fn create_note(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Json(payload): Json<CreateNotePayload>,
-) -> Result<(StatusCode, Json<Note>), (StatusCode, String)> {
+) -> Result<Response, (StatusCode, String)> {
     info!(
         "User {} is creating a note with title '{}'",
         user_id, payload.title
     );
-    let note = sqlx::query_as::<_, Note>(
-        "INSERT INTO notes (user_id, notebook_id, title, content, tags)
-         VALUES ($1,$2,$3,$4,$5) RETURNING *",
+    let tags = validate_tags(&payload.tags).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let content = parse_content(&state.pool, user_id, payload.content).await?;
+    let db_error = |e: sqlx::Error| {
+        error!("DB error creating note for user {}: {}", user_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    };
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    let id: Uuid = sqlx::query_scalar(
+        "INSERT INTO notes (user_id, notebook_id, title, content, content_text)
+         VALUES ($1,$2,$3,$4,$5) RETURNING id",
     )
     .bind(user_id)
     .bind(payload.notebook_id)
     .bind(&payload.title)
-    .bind(&payload.content)
-    .bind(&payload.tags)
-    .fetch_one(&state.pool)
+    .bind(content.to_value())
+    .bind(content.plain_text())
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| {
         error!("DB error creating note for user {}: {}", user_id, e);
         (StatusCode::BAD_REQUEST, "Invalid request".to_string())
     })?;
+    set_note_tags(&mut tx, user_id, id, &tags)
+        .await
+        .map_err(db_error)?;
+    let note = sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE id = $1")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(note_response(StatusCode::CREATED, note))
+}
+
+/// ETag of a note: its revision, which increases with every write.
+pub fn note_etag(note: &Note) -> String {
+    format!("\"{}\"", note.revision)
+}
+
+/// Whether an `If-Match` header allows writing a note at `revision`.
+/// A missing header always matches; weak tags never do.
+fn if_match(headers: &HeaderMap, revision: i64) -> bool {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return true;
+    };
+    let Ok(value) = value.to_str() else {
+        return false;
+    };
+    let current = format!("\"{}\"", revision);
+    value
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag == current)
+}
+
+/// Note as JSON with its `ETag` header.
+pub fn note_response(status: StatusCode, note: Note) -> Response {
+    let etag = note_etag(&note);
+    (status, [(ETAG, etag)], Json(note)).into_response()
+}
+
+/// Loads one note of a user, mapping a missing note to 404.
+pub async fn fetch_note(
+    pool: &PgPool,
+    user_id: Uuid,
+    id: Uuid,
+) -> Result<Note, (StatusCode, String)> {
+    let opt = sqlx::query_as::<_, Note>(
+        "SELECT * FROM notes WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        error!("DB error fetching note {} for user {}: {}", id, user_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
 
-    Ok((StatusCode::CREATED, Json(note)))
+    opt.ok_or_else(|| {
+        info!("Note {} not found for user {}", id, user_id);
+        (StatusCode::NOT_FOUND, "Note does not exist".to_string())
+    })
 }
 
-/// Get one note by id for a user.
+/// Get one note by id for a user. The `ETag` header can be sent back in `If-Match`.
 pub async fn get_note(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(id): Path<Uuid>,
-) -> Result<(StatusCode, Json<Note>), (StatusCode, String)> {
+) -> Result<Response, (StatusCode, String)> {
     info!("User {} is fetching note id {}", user_id, id);
-    let opt = sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE id = $1 AND user_id = $2")
-        .bind(id)
-        .bind(user_id)
-        .fetch_optional(&state.pool)
-        .await
-        .map_err(|e| {
-            error!("DB error fetching note {} for user {}: {}", id, user_id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Database error".to_string(),
-            )
-        })?;
-
-    if let Some(n) = opt {
-        Ok((StatusCode::OK, Json(n)))
-    } else {
-        info!("Note {} not found for user {}", id, user_id);
-        Err((StatusCode::NOT_FOUND, "Note does not exist".to_string()))
-    }
+    let note = fetch_note(&state.pool, user_id, id).await?;
+    Ok(note_response(StatusCode::OK, note))
 }
 
 #[derive(Deserialize)]
 pub struct UpdateNotePayload {
     pub title: Option<String>,
+    /// Block document, or a string taken as plain text
     pub content: Option<serde_json::Value>,
     pub is_archived: Option<bool>,
     pub is_pinned: Option<bool>,
-    pub tags: Option<serde_json::Value>,
+    /// Replaces all tags of the note
+    pub tags: Option<Vec<String>>,
 }
 
 /// Update a note for a user.
+/// With `If-Match`, the update only succeeds if the note was not changed since the client
+/// read it; otherwise 412 is returned with the current note so the client can merge.
 pub async fn update_note(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(id): Path<Uuid>,
+    headers: HeaderMap,
     Json(payload): Json<UpdateNotePayload>,
-) -> Result<(StatusCode, Json<Note>), (StatusCode, String)> {
+) -> Result<Response, (StatusCode, String)> {
     info!("User {} is updating note id {}", user_id, id);
-    let result = sqlx::query(
+    let tags = payload
+        .tags
+        .as_deref()
+        .map(validate_tags)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let content = match payload.content {
+        Some(value) => Some(parse_content(&state.pool, user_id, value).await?),
+        None => None,
+    };
+    let db_error = |e: sqlx::Error| {
+        error!("DB error updating note {} for user {}: {}", id, user_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    };
+
+    let mut tx = state.pool.begin().await.map_err(db_error)?;
+    let Some(current) = lock_note(&mut tx, user_id, id).await.map_err(db_error)? else {
+        info!("Note {} not found for user {}", id, user_id);
+        return Err((StatusCode::NOT_FOUND, "Note does not exist".to_string()));
+    };
+    if !if_match(&headers, current.revision) {
+        drop(tx);
+        info!(
+            "Stale update of note {} by user {} (server revision {})",
+            id, user_id, current.revision
+        );
+        let note = fetch_note(&state.pool, user_id, id).await?;
+        return Ok(note_response(StatusCode::PRECONDITION_FAILED, note));
+    }
+    // Keep the previous title and content as a version before they change
+    let changed = payload.title.as_ref().is_some_and(|t| *t != current.title)
+        || content
+            .as_ref()
+            .is_some_and(|c| c.to_value() != current.content);
+    if changed {
+        let limit = state
+            .config
+            .note_version_limit
+            .unwrap_or(DEFAULT_VERSION_LIMIT);
+        record_version(&mut tx, id, &current, limit)
+            .await
+            .map_err(db_error)?;
+    }
+
+    sqlx::query(
         r#"UPDATE notes SET
-            title       = COALESCE($2, title),
-            content     = COALESCE($3, content),
-            is_archived = COALESCE($4, is_archived),
-            is_pinned   = COALESCE($5, is_pinned),
-            tags        = COALESCE($6, tags)
-          WHERE id = $1 AND user_id = $7"#,
+            title        = COALESCE($2, title),
+            content      = COALESCE($3, content),
+            content_text = COALESCE($7, content_text),
+            is_archived  = COALESCE($4, is_archived),
+            is_pinned    = COALESCE($5, is_pinned)
+          WHERE id = $1 AND user_id = $6"#,
     )
     .bind(id)
     .bind(payload.title)
-    .bind(payload.content)
+    .bind(content.as_ref().map(NoteContent::to_value))
     .bind(payload.is_archived)
     .bind(payload.is_pinned)
-    .bind(payload.tags)
     .bind(user_id)
-    .execute(&state.pool)
+    .bind(content.as_ref().map(NoteContent::plain_text))
+    .execute(&mut *tx)
     .await
     .map_err(|e| {
         error!("DB error updating note {} for user {}: {}", id, user_id, e);
         (StatusCode::BAD_REQUEST, "Invalid request".to_string())
     })?;
-
-    if result.rows_affected() == 0 {
-        info!("Note {} not found for user {}", id, user_id);
-        return Err((StatusCode::NOT_FOUND, "Note does not exist".to_string()));
+    if let Some(tags) = &tags {
+        set_note_tags(&mut tx, user_id, id, tags)
+            .await
+            .map_err(db_error)?;
     }
+    tx.commit().await.map_err(db_error)?;
 
     get_note(State(state), AuthUser(user_id), Path(id)).await
 }
 
-/// Delete a note for a user.
+/// Move a note of a user to the trash.
 pub async fn delete_note(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
     Path(id): Path<Uuid>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     info!("User {} is deleting note id {}", user_id, id);
-    let result = sqlx::query("DELETE FROM notes WHERE id = $1 AND user_id = $2")
-        .bind(id)
-        .bind(user_id)
-        .execute(&state.pool)
+    let trashed = trash_notes(&state.pool, user_id, &[id])
         .await
         .map_err(|e| {
             error!("DB error deleting note {} for user {}: {}", id, user_id, e);
@@ -177,7 +552,7 @@ pub async fn delete_note(
             )
         })?;
 
-    if result.rows_affected() == 0 {
+    if trashed == 0 {
         info!("Note {} not found for user {}", id, user_id);
         return Err((StatusCode::NOT_FOUND, "Note does not exist".to_string()));
     }
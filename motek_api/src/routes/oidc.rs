@@ -0,0 +1,255 @@
+use crate::{
+    database::oidc::{
+        create_login_state, create_user_with_identity, get_user_by_identity, link_identity,
+        take_login_state,
+    },
+    database::token::generate_refresh_token,
+    models::{platform::Platform, user::User},
+    routes::auth::{
+        ensure_email_verified, ensure_enabled, ensure_not_locked, get_user_by_email, issue_tokens,
+        mfa_challenge, session_info,
+    },
+    state::AppState,
+    utils::config_loader::OidcProviderConfig,
+    utils::oidc::{
+        IdTokenClaims, authorization_url, discover, exchange_code, pkce_challenge,
+        validate_id_token,
+    },
+    utils::password::hash_password,
+};
+use axum::{
+    Router,
+    extract::{ConnectInfo, Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tracing::{error, info, warn};
+
+/// Returns a router for OpenID Connect login, nested under `/api/auth/oidc`.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/{provider}/authorize", get(authorize))
+        .route("/{provider}/callback", post(callback))
+}
+
+/// Query parameters for starting a login.
+#[derive(Deserialize)]
+pub struct AuthorizeQuery {
+    pub platform: Platform,
+}
+
+/// URL of the provider sign-in page the client should open.
+#[derive(Serialize)]
+pub struct AuthorizeResponse {
+    pub authorization_url: String,
+}
+
+/// Payload with the parameters the provider appended to `redirect_uri`.
+#[derive(Deserialize)]
+pub struct CallbackPayload {
+    pub code: String,
+    pub state: String,
+}
+
+/// Looks up a configured provider by name.
+fn provider_config<'a>(
+    state: &'a AppState,
+    provider: &str,
+) -> Result<&'a OidcProviderConfig, (StatusCode, String)> {
+    state.config.oidc.get(provider).ok_or_else(|| {
+        info!("OIDC login with unknown provider '{}'", provider);
+        (StatusCode::NOT_FOUND, "Unknown provider".to_string())
+    })
+}
+
+/// Maps a failed call to the provider to 502.
+fn provider_error(provider: &str, e: anyhow::Error) -> (StatusCode, String) {
+    error!("OIDC provider '{}' error: {:#}", provider, e);
+    (
+        StatusCode::BAD_GATEWAY,
+        "Identity provider error".to_string(),
+    )
+}
+
+/// Start a login: returns the provider URL to redirect the user to (authorization code + PKCE).
+pub async fn authorize(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    Query(query): Query<AuthorizeQuery>,
+) -> Result<Json<AuthorizeResponse>, (StatusCode, String)> {
+    let config = provider_config(&state, &provider)?;
+    let metadata = discover(&state.http, config)
+        .await
+        .map_err(|e| provider_error(&provider, e))?;
+
+    let login = create_login_state(&state.pool, &provider, query.platform)
+        .await
+        .map_err(|e| {
+            error!("DB error storing OIDC login state: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+    let authorization_url = authorization_url(
+        &metadata,
+        config,
+        &login.state,
+        &login.nonce,
+        &pkce_challenge(&login.code_verifier),
+    )
+    .map_err(|e| provider_error(&provider, e))?;
+
+    info!("OIDC login started with provider '{}'", provider);
+    Ok(Json(AuthorizeResponse { authorization_url }))
+}
+
+/// Finish a login: exchanges the code, validates the ID token and issues Motek tokens.
+/// The identity is matched to a linked user, then to a user with the same verified email;
+/// otherwise a new account is created if the provider allows signup.
+/// The provider replaces only the password: locked and unverified accounts are refused
+/// as in a password login, and accounts with 2FA get the same challenge.
+pub async fn callback(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<CallbackPayload>,
+) -> Result<Response, (StatusCode, String)> {
+    let config = provider_config(&state, &provider)?;
+
+    let login = take_login_state(&state.pool, &payload.state, &provider)
+        .await
+        .map_err(|e| {
+            error!("DB error reading OIDC login state: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?
+        .ok_or_else(|| {
+            info!("OIDC callback with unknown or expired state");
+            (
+                StatusCode::BAD_REQUEST,
+                "Invalid or expired login state".to_string(),
+            )
+        })?;
+    let platform: Platform = login
+        .platform
+        .parse()
+        .map_err(|e: String| (StatusCode::BAD_REQUEST, e))?;
+
+    let metadata = discover(&state.http, config)
+        .await
+        .map_err(|e| provider_error(&provider, e))?;
+    let id_token = exchange_code(
+        &state.http,
+        &metadata,
+        config,
+        &payload.code,
+        &login.code_verifier,
+    )
+    .await
+    .map_err(|e| provider_error(&provider, e))?;
+    let claims = validate_id_token(&state.http, &metadata, config, &id_token, &login.nonce)
+        .await
+        .map_err(|e| {
+            warn!(target: "security", "Rejected ID token from '{}': {:#}", provider, e);
+            (StatusCode::UNAUTHORIZED, "Invalid ID token".to_string())
+        })?;
+
+    let user = resolve_user(&state, &provider, config, &claims).await?;
+    ensure_enabled(&user)?;
+    ensure_not_locked(&user)?;
+    ensure_email_verified(&state, &user)?;
+
+    if let Some(challenge) = mfa_challenge(&state, &user, platform).await? {
+        info!(
+            "User {} signed in with provider '{}', 2FA required",
+            user.id, provider
+        );
+        return Ok(Json(challenge).into_response());
+    }
+
+    let session = session_info(platform, &addr, &headers);
+    let tokens = issue_tokens(&state, user.id, platform, &session).await?;
+    info!(
+        "User {} logged in with provider '{}' (platform: {})",
+        user.id, provider, platform
+    );
+    Ok(Json(tokens).into_response())
+}
+
+/// Finds, links or creates the Motek user for a validated identity.
+async fn resolve_user(
+    state: &AppState,
+    provider: &str,
+    config: &OidcProviderConfig,
+    claims: &IdTokenClaims,
+) -> Result<User, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| {
+        error!("DB error resolving {} identity: {}", provider, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    };
+
+    if let Some(user) = get_user_by_identity(&state.pool, provider, &claims.sub)
+        .await
+        .map_err(db_error)?
+    {
+        return Ok(user);
+    }
+
+    // Only a verified email may be used to link or create an account
+    let email = match (&claims.email, claims.email_verified()) {
+        (Some(email), true) => email,
+        _ => {
+            info!(
+                "OIDC login refused: no verified email for {} identity {}",
+                provider, claims.sub
+            );
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Identity provider did not confirm the email address".to_string(),
+            ));
+        }
+    };
+
+    match get_user_by_email(&state.pool, email).await {
+        Ok(mut user) => {
+            link_identity(&state.pool, user.id, provider, &claims.sub, email)
+                .await
+                .map_err(db_error)?;
+            user.email_verified_at.get_or_insert_with(Utc::now);
+            Ok(user)
+        }
+        Err(sqlx::Error::RowNotFound) => {
+            if !config.allow_signup.unwrap_or(true) {
+                info!("OIDC signup disabled for provider '{}'", provider);
+                return Err((
+                    StatusCode::FORBIDDEN,
+                    "No account for this identity".to_string(),
+                ));
+            }
+            // Random password: the account signs in with the provider (or after a reset)
+            let password_hash = hash_password(&generate_refresh_token(), &state.config.argon2)
+                .map_err(|e| {
+                    error!("Hashing error: {}", e);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Database error".to_string(),
+                    )
+                })?;
+            create_user_with_identity(&state.pool, provider, &claims.sub, email, &password_hash)
+                .await
+                .map_err(db_error)
+        }
+        Err(e) => Err(db_error(e)),
+    }
+}
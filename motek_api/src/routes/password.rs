@@ -0,0 +1,234 @@
+use crate::{
+    database::password_reset::{create_password_reset_token, reset_password_with_token},
+    database::token::revoke_all_refresh_tokens_for_user,
+    mailer::Email,
+    models::user::User,
+    routes::auth::{LoginResponse, get_user_by_email, issue_tokens, session_info},
+    state::AppState,
+    utils::jwt::AuthClaims,
+    utils::password::{hash_password, verify_password},
+    utils::validators::validate_password,
+};
+use axum::{
+    Router,
+    extract::{ConnectInfo, Json, State},
+    http::{HeaderMap, StatusCode},
+    routing::post,
+};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Returns a router for the public forgot/reset password endpoints.
+pub fn public_router() -> Router<AppState> {
+    Router::new()
+        .route("/forgot", post(forgot_password))
+        .route("/reset", post(reset_password))
+}
+
+/// Returns a router for changing the password of the authenticated user.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", post(change_password))
+}
+
+/// Payload for changing the password.
+#[derive(Deserialize)]
+pub struct ChangePasswordPayload {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+/// Payload for requesting a password reset email.
+#[derive(Deserialize)]
+pub struct ForgotPasswordPayload {
+    pub email: String,
+}
+
+/// Payload for setting a new password with a reset token.
+#[derive(Deserialize)]
+pub struct ResetPasswordPayload {
+    pub token: String,
+    pub new_password: String,
+}
+
+/// Change the password of the authenticated user.
+/// All sessions are revoked and a fresh token pair is returned for the current device.
+pub async fn change_password(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<AppState>,
+    AuthClaims(claims): AuthClaims,
+    headers: HeaderMap,
+    Json(payload): Json<ChangePasswordPayload>,
+) -> Result<Json<LoginResponse>, (StatusCode, String)> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token".to_string()))?;
+    info!("User {} is changing password", user_id);
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.pool)
+        .await
+        .map_err(|e| {
+            error!("DB error fetching user {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+    if !verify_password(&payload.old_password, &user.password) {
+        info!("Password change failed: wrong old password for {}", user_id);
+        return Err((StatusCode::FORBIDDEN, "Invalid password".to_string()));
+    }
+    validate_password(&payload.new_password).map_err(|msg| (StatusCode::BAD_REQUEST, msg))?;
+
+    let password_hash =
+        hash_password(&payload.new_password, &state.config.argon2).map_err(|e| {
+            error!("Hashing error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+    sqlx::query("UPDATE users SET password = $1 WHERE id = $2")
+        .bind(&password_hash)
+        .bind(user_id)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| {
+            error!("DB error updating password for {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+    // Log out every device, then start a new session for this one
+    let revoked = revoke_all_refresh_tokens_for_user(&state.pool, user_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to revoke sessions for {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+    info!(
+        "Password changed for {}, revoked {} refresh tokens",
+        user_id, revoked
+    );
+
+    let session = session_info(claims.platform, &addr, &headers);
+    let tokens = issue_tokens(&state, user_id, claims.platform, &session).await?;
+    Ok(Json(tokens))
+}
+
+/// Request a password reset email.
+/// Always answers 202 so the endpoint cannot be used to probe for accounts.
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ForgotPasswordPayload>,
+) -> StatusCode {
+    info!("Password reset requested for {}", &payload.email);
+    let user = match get_user_by_email(&state.pool, &payload.email).await {
+        Ok(user) => user,
+        Err(sqlx::Error::RowNotFound) => {
+            info!("Password reset for unknown email {}", &payload.email);
+            return StatusCode::ACCEPTED;
+        }
+        Err(e) => {
+            error!(
+                "DB error during password reset for {}: {}",
+                &payload.email, e
+            );
+            return StatusCode::ACCEPTED;
+        }
+    };
+
+    let ttl_minutes = state.config.password_reset_ttl_minutes.unwrap_or(30);
+    let token = match create_password_reset_token(&state.pool, user.id, ttl_minutes).await {
+        Ok(t) => t,
+        Err(e) => {
+            error!(
+                "Failed to create password reset token for {}: {}",
+                user.id, e
+            );
+            return StatusCode::ACCEPTED;
+        }
+    };
+
+    let link = match state.config.app_url.as_deref() {
+        Some(url) => format!(
+            "{}/reset-password?token={}",
+            url.trim_end_matches('/'),
+            token
+        ),
+        None => token,
+    };
+    let email = Email {
+        to: user.email.clone(),
+        subject: "Motek password reset".to_string(),
+        body: format!(
+            "Someone requested a password reset for your Motek account.\n\n\
+             Use this to set a new password (valid for {} minutes):\n{}\n\n\
+             If it was not you, ignore this email.",
+            ttl_minutes, link
+        ),
+    };
+    // Sent in the background so the response time does not reveal whether the account exists
+    let mailer = state.mailer.clone();
+    tokio::spawn(async move {
+        if let Err(e) = mailer.send(email).await {
+            error!(
+                "Failed to send password reset email to {}: {}",
+                user.email, e
+            );
+        }
+    });
+
+    StatusCode::ACCEPTED
+}
+
+/// Set a new password using a reset token.
+/// The token is single-use; all sessions of the user are revoked afterwards.
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordPayload>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    validate_password(&payload.new_password).map_err(|msg| (StatusCode::BAD_REQUEST, msg))?;
+
+    let password_hash =
+        hash_password(&payload.new_password, &state.config.argon2).map_err(|e| {
+            error!("Hashing error: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+    let user_id = match reset_password_with_token(&state.pool, &payload.token, &password_hash).await
+    {
+        Ok(Some(id)) => id,
+        Ok(None) => {
+            warn!("Password reset with invalid or expired token");
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Invalid or expired reset token".to_string(),
+            ));
+        }
+        Err(e) => {
+            error!("DB error during password reset: {}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            ));
+        }
+    };
+
+    if let Err(e) = revoke_all_refresh_tokens_for_user(&state.pool, user_id).await {
+        error!("Failed to revoke sessions for {}: {}", user_id, e);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
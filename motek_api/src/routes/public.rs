@@ -1,11 +1,18 @@
 use crate::state::AppState;
-use axum::{Router, extract::ConnectInfo, routing::get};
+use axum::{
+    Json, Router,
+    extract::{ConnectInfo, State},
+    routing::get,
+};
+use jsonwebtoken::jwk::JwkSet;
 use std::net::SocketAddr;
 use tracing::info;
 
 /// Returns a router for public endpoints.
 pub fn router() -> Router<AppState> {
-    Router::new().route("/ip", get(get_ip))
+    Router::new()
+        .route("/ip", get(get_ip))
+        .route("/jwks.json", get(get_jwks))
 }
 
 /// Returns the IP address of the client.
@@ -13,3 +20,9 @@ pub async fn get_ip(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> String {
     info!("Public IP check from {}", addr);
     format!("Your IP address is: {addr}")
 }
+
+/// Returns the public keys used to sign access tokens (JWKS, RFC 7517).
+/// Other services use it to validate Motek tokens without a shared secret.
+pub async fn get_jwks(State(state): State<AppState>) -> Json<JwkSet> {
+    Json(state.jwt_keys.jwks())
+}
@@ -18,7 +18,7 @@ pub fn router() -> Router<AppState> {
         .route("/{id}", get(get_one).put(update).delete(delete_one))
 }
 
-/// List all reminders for notes owned by the user.
+/// List all reminders for notes owned by the user, except notes in the trash.
 pub async fn list_reminders(
     State(state): State<AppState>,
     AuthUser(user_id): AuthUser,
@@ -27,7 +27,7 @@ pub async fn list_reminders(
     let rows = sqlx::query_as::<_, Reminder>(
         "SELECT r.* FROM reminders r
          JOIN notes n ON r.note_id = n.id
-         WHERE n.user_id = $1",
+         WHERE n.user_id = $1 AND n.deleted_at IS NULL",
     )
     .bind(user_id)
     .fetch_all(&state.pool)
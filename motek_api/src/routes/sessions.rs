@@ -0,0 +1,93 @@
+use crate::{database::token::revoke_session, state::AppState, utils::extractors::AuthUser};
+use axum::{
+    Router,
+    extract::{Json, Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Returns a router for session (logged-in device) endpoints.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_sessions))
+        .route("/{id}", delete(revoke_one))
+}
+
+/// Session – an active refresh token family, i.e. one logged-in device.
+/// The refresh token itself is never returned.
+#[derive(Debug, FromRow, Serialize)]
+pub struct Session {
+    /// Session id (refresh token family id)
+    pub id: Uuid,
+    /// Platform given at login
+    pub platform: Option<String>,
+    /// Client IP address of the last login/refresh
+    pub ip_address: Option<String>,
+    /// User-Agent of the client
+    pub user_agent: Option<String>,
+    /// When the session was started (login time)
+    pub created_at: DateTime<Utc>,
+    /// Last login/refresh time
+    pub last_used_at: DateTime<Utc>,
+    /// Expiry of the current refresh token
+    pub expires_at: DateTime<Utc>,
+}
+
+/// List active sessions of the authenticated user, most recently used first.
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> Result<(StatusCode, Json<Vec<Session>>), (StatusCode, String)> {
+    info!("User {} requested sessions list", user_id);
+    let rows = sqlx::query_as::<_, Session>(
+        "SELECT t.family_id AS id, t.platform, t.ip_address, t.user_agent,
+                (SELECT MIN(f.created_at) FROM refresh_tokens f WHERE f.family_id = t.family_id) AS created_at,
+                t.last_used_at, t.expires_at
+         FROM refresh_tokens t
+         WHERE t.user_id = $1 AND t.revoked = FALSE AND t.expires_at > NOW()
+         ORDER BY t.last_used_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| {
+        error!("DB error fetching sessions for user {}: {}", user_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
+    Ok((StatusCode::OK, Json(rows)))
+}
+
+/// Revoke one session (log out a single device).
+pub async fn revoke_one(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    info!("User {} is revoking session {}", user_id, id);
+    let revoked = revoke_session(&state.pool, user_id, id)
+        .await
+        .map_err(|e| {
+            error!(
+                "DB error revoking session {} for user {}: {}",
+                id, user_id, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+
+    if revoked == 0 {
+        info!("Session {} not found for user {}", id, user_id);
+        return Err((StatusCode::NOT_FOUND, "Session does not exist".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,189 @@
+use crate::{
+    database::tags::{
+        TagWithCount, create_tag, delete_tag, get_tag, list_tags, merge_tags, update_tag,
+    },
+    models::tag::Tag,
+    state::AppState,
+    utils::extractors::AuthUser,
+    utils::validators::{validate_color, validate_tag_name},
+};
+use axum::{
+    Router,
+    extract::{Json, Path, State},
+    http::StatusCode,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Returns a router for tag endpoints, nested under `/api/tags`.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/{id}", get(get_one).put(update).delete(delete_one))
+        .route("/{id}/merge", post(merge))
+}
+
+/// Payload for creating a tag.
+#[derive(Deserialize)]
+pub struct CreateTagPayload {
+    pub name: String,
+    /// `#RRGGBB`
+    pub color: Option<String>,
+}
+
+/// Payload for renaming or recolouring a tag.
+#[derive(Deserialize)]
+pub struct UpdateTagPayload {
+    pub name: Option<String>,
+    /// `#RRGGBB`, or an empty string to remove the colour
+    pub color: Option<String>,
+}
+
+/// Payload for merging a tag into another one.
+#[derive(Deserialize)]
+pub struct MergeTagPayload {
+    /// Tag that remains after the merge
+    pub into: Uuid,
+}
+
+#[derive(Serialize)]
+pub struct MergeTagResponse {
+    pub notes_updated: u64,
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!("Tags database error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Database error".to_string(),
+    )
+}
+
+/// Maps a duplicate tag name to 409, anything else to 500.
+fn write_error(e: sqlx::Error) -> (StatusCode, String) {
+    if e.as_database_error()
+        .is_some_and(|d| d.is_unique_violation())
+    {
+        return (
+            StatusCode::CONFLICT,
+            "A tag with this name already exists".to_string(),
+        );
+    }
+    db_error(e)
+}
+
+fn not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Tag does not exist".to_string())
+}
+
+fn validate_optional_color(color: Option<&str>) -> Result<(), (StatusCode, String)> {
+    match color {
+        Some(c) => validate_color(c).map_err(|e| (StatusCode::BAD_REQUEST, e)),
+        None => Ok(()),
+    }
+}
+
+/// List the user's tags with the number of notes using each.
+pub async fn list(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> Result<(StatusCode, Json<Vec<TagWithCount>>), (StatusCode, String)> {
+    info!("User {} is listing tags", user_id);
+    let tags = list_tags(&state.pool, user_id).await.map_err(db_error)?;
+    Ok((StatusCode::OK, Json(tags)))
+}
+
+/// Get one tag.
+pub async fn get_one(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<Tag>), (StatusCode, String)> {
+    let tag = get_tag(&state.pool, user_id, id)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    Ok((StatusCode::OK, Json(tag)))
+}
+
+/// Create a tag, e.g. to give it a colour before it is used.
+pub async fn create(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Json(payload): Json<CreateTagPayload>,
+) -> Result<(StatusCode, Json<Tag>), (StatusCode, String)> {
+    let name = validate_tag_name(&payload.name).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    validate_optional_color(payload.color.as_deref())?;
+
+    let tag = create_tag(&state.pool, user_id, &name, payload.color.as_deref())
+        .await
+        .map_err(write_error)?;
+    info!("User {} created tag {}", user_id, tag.id);
+    Ok((StatusCode::CREATED, Json(tag)))
+}
+
+/// Rename or recolour a tag. A rename is applied to every note carrying the tag.
+pub async fn update(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateTagPayload>,
+) -> Result<(StatusCode, Json<Tag>), (StatusCode, String)> {
+    let name = payload
+        .name
+        .as_deref()
+        .map(validate_tag_name)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let color = payload.color.as_deref();
+    validate_optional_color(color.filter(|c| !c.is_empty()))?;
+
+    let tag = update_tag(&state.pool, user_id, id, name.as_deref(), color)
+        .await
+        .map_err(write_error)?
+        .ok_or_else(not_found)?;
+    info!("User {} updated tag {}", user_id, id);
+    Ok((StatusCode::OK, Json(tag)))
+}
+
+/// Merge a tag into another one; the merged tag is deleted.
+pub async fn merge(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<MergeTagPayload>,
+) -> Result<(StatusCode, Json<MergeTagResponse>), (StatusCode, String)> {
+    if payload.into == id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Cannot merge a tag into itself".to_string(),
+        ));
+    }
+    let notes_updated = merge_tags(&state.pool, user_id, id, payload.into)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(not_found)?;
+    info!(
+        "User {} merged tag {} into {} ({} notes)",
+        user_id, id, payload.into, notes_updated
+    );
+    Ok((StatusCode::OK, Json(MergeTagResponse { notes_updated })))
+}
+
+/// Delete a tag and remove it from all notes.
+pub async fn delete_one(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !delete_tag(&state.pool, user_id, id)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(not_found());
+    }
+    info!("User {} deleted tag {}", user_id, id);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,126 @@
+use crate::{
+    database::trash::{
+        Trash, delete_trashed_note, delete_trashed_notebook, empty_trash, list_trash,
+        restore_notebook, restore_notes,
+    },
+    state::AppState,
+    utils::extractors::AuthUser,
+};
+use axum::{
+    Router,
+    extract::{Json, Path, State},
+    http::StatusCode,
+    routing::{delete, get, post},
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Returns a router for the trash, nested under `/api/trash`.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).delete(empty))
+        .route("/notes/{id}", delete(delete_note))
+        .route("/notes/{id}/restore", post(restore_note_handler))
+        .route("/notebooks/{id}", delete(delete_notebook))
+        .route("/notebooks/{id}/restore", post(restore_notebook_handler))
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    error!("Trash database error: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Database error".to_string(),
+    )
+}
+
+fn not_in_trash() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Not found in trash".to_string())
+}
+
+/// List the trashed notes and notebooks of the user.
+pub async fn list(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> Result<(StatusCode, Json<Trash>), (StatusCode, String)> {
+    info!("User {} requested the trash", user_id);
+    let trash = list_trash(&state.pool, user_id).await.map_err(db_error)?;
+    Ok((StatusCode::OK, Json(trash)))
+}
+
+/// Permanently delete everything in the trash.
+pub async fn empty(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let (notes, notebooks) = empty_trash(&state.pool, user_id).await.map_err(db_error)?;
+    info!(
+        "User {} emptied the trash ({} notes, {} notebooks)",
+        user_id, notes, notebooks
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Restore a note from the trash.
+pub async fn restore_note_handler(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if restore_notes(&state.pool, user_id, &[id])
+        .await
+        .map_err(db_error)?
+        == 0
+    {
+        return Err(not_in_trash());
+    }
+    info!("User {} restored note {}", user_id, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Restore a notebook, with what was deleted together with it, from the trash.
+pub async fn restore_notebook_handler(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !restore_notebook(&state.pool, user_id, id)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(not_in_trash());
+    }
+    info!("User {} restored notebook {}", user_id, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Permanently delete a trashed note.
+pub async fn delete_note(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !delete_trashed_note(&state.pool, user_id, id)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(not_in_trash());
+    }
+    info!("User {} permanently deleted note {}", user_id, id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Permanently delete a trashed notebook with its trashed contents.
+pub async fn delete_notebook(
+    State(state): State<AppState>,
+    AuthUser(user_id): AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !delete_trashed_notebook(&state.pool, user_id, id)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(not_in_trash());
+    }
+    info!("User {} permanently deleted notebook {}", user_id, id);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -2,20 +2,30 @@
 //! Combines public and protected routes, applies middleware, and starts the HTTP server.
 
 use crate::{
+    mailer,
     routes::public,
     utils::config_loader::Config,
     routes::{api, auth},
     state::AppState,
     utils::auth::auth_middleware,
+    utils::jwt_keys::JwtKeys,
+    utils::rate_limit::rate_limit,
+    utils::revocation::cleanup_expired_revocations,
     database::token::cleanup_expired_refresh_tokens,
+    database::account::purge_deleted_accounts,
+    database::oidc::cleanup_expired_login_states,
+    database::trash::{DEFAULT_TRASH_RETENTION_DAYS, purge_expired_trash},
+    database::imports::fail_stale_imports,
+    routes::imports::remove_orphaned_files,
 };
 use axum::{Router, middleware};
 use axum_server::Server;
 use axum::http::{Method, header, HeaderValue};
 use sqlx::PgPool;
 use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{error, info};
 use tower_http::cors::CorsLayer;
 
 
@@ -31,8 +41,18 @@ pub async fn run() -> anyhow::Result<()> {
     let pool = PgPool::connect(&config.database_url).await?;
     info!("Connected to PostgreSQL");
 
+    // Set up outgoing email.
+    let mailer = mailer::from_config(config.mailer.as_ref())?;
+
+    // Load JWT signing keys.
+    let jwt_keys = JwtKeys::from_config(&config)?;
+
     // Initialize application state.
-    let state = AppState::new(pool, config);
+    let state = AppState::new(pool, config, mailer, jwt_keys);
+
+    // Load revoked access tokens.
+    let revoked = state.revoked_tokens.reload(&state.pool).await?;
+    info!("Loaded {} revoked access tokens", revoked);
 
     let server_address = state.config.server_address.clone();
     let server_port = state.config.port;
@@ -42,18 +62,22 @@ pub async fn run() -> anyhow::Result<()> {
         .allow_origin(origin.parse::<HeaderValue>().unwrap())
         .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
         .allow_credentials(true)
-        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]);
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE, header::IF_MATCH])
+        .expose_headers([header::ETAG]);
 
     // Set up public endpoints.
     let api_public = Router::new()
         .nest("/api/auth", auth::router())
         .nest("/api/public", public::router())
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
         .with_state(state.clone());
 
     // Set up protected endpoints (all /api/* except /api/auth).
     let api_protected = Router::new()
         .nest("/api", api::router())
         .layer(TraceLayer::new_for_http())
+        // Runs after auth_middleware so requests are limited per user
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -78,6 +102,72 @@ pub async fn run() -> anyhow::Result<()> {
         state.config.server_address, state.config.port
     );
 
+    // Periodically remove expired refresh tokens.
+    let cleanup_pool = state.pool.clone();
+    let trash_retention_days = state
+        .config
+        .trash_retention_days
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+    let attachment_dir = state.config.attachment_dir.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            match cleanup_expired_refresh_tokens(&cleanup_pool).await {
+                Ok(n) => info!("Removed {} expired refresh tokens", n),
+                Err(e) => error!("Refresh token cleanup failed: {}", e),
+            }
+            if let Err(e) = cleanup_expired_revocations(&cleanup_pool).await {
+                error!("Revoked access token cleanup failed: {}", e);
+            }
+            if let Err(e) = cleanup_expired_login_states(&cleanup_pool).await {
+                error!("OIDC login state cleanup failed: {}", e);
+            }
+            // Purge accounts whose deletion grace period is over.
+            match purge_deleted_accounts(&cleanup_pool).await {
+                Ok(n) => info!("Purged {} deleted accounts", n),
+                Err(e) => error!("Account purge failed: {}", e),
+            }
+            // Purge notes and notebooks kept in the trash for longer than the retention.
+            match purge_expired_trash(&cleanup_pool, trash_retention_days).await {
+                Ok((notes, notebooks)) => {
+                    info!("Purged {} notes and {} notebooks from the trash", notes, notebooks)
+                }
+                Err(e) => error!("Trash purge failed: {}", e),
+            }
+            // Fail imports interrupted by a restart of the instance running them.
+            match fail_stale_imports(&cleanup_pool).await {
+                Ok(n) if n > 0 => info!("Marked {} interrupted imports as failed", n),
+                Ok(_) => {}
+                Err(e) => error!("Interrupted import cleanup failed: {}", e),
+            }
+            // Remove imported attachment files no longer referred to by any attachment.
+            if let Some(dir) = &attachment_dir {
+                match remove_orphaned_files(&cleanup_pool, std::path::Path::new(dir)).await {
+                    Ok(n) if n > 0 => info!("Removed {} orphaned import files", n),
+                    Ok(_) => {}
+                    Err(e) => error!("Orphaned import file cleanup failed: {}", e),
+                }
+            }
+        }
+    });
+
+    // Pick up access tokens revoked by other instances.
+    let revocation_state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+            if let Err(e) = revocation_state
+                .revoked_tokens
+                .reload(&revocation_state.pool)
+                .await
+            {
+                error!("Reloading revoked access tokens failed: {}", e);
+            }
+        }
+    });
+
     // Start the Axum server.
     Server::from_tcp(listener)
         .serve(app.into_make_service_with_connect_info::<SocketAddr>())
@@ -88,9 +178,5 @@ pub async fn run() -> anyhow::Result<()> {
         state.config.server_address, state.config.port
     );
 
-    tokio::spawn(async move {
-        let _ = cleanup_expired_refresh_tokens(&state.pool).await;
-    });
-
     Ok(())
 }
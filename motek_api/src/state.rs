@@ -1,32 +1,57 @@
 //! Application state container.
-//! Holds database connection pool, configuration, and IP registration limiter.
+//! Holds database connection pool, configuration, rate limiter, mailer, JWT keys, revoked tokens,
+//! the outgoing HTTP client and the import slots.
 
 use crate::{
+    mailer::Mailer,
+    routes::imports::MAX_RUNNING_IMPORTS,
     utils::config_loader::Config,
-    utils::ip_limiter::IpLimiter,
+    utils::jwt_keys::JwtKeys,
+    utils::rate_limit::RateLimiter,
+    utils::revocation::RevokedTokens,
 };
 use sqlx::Pool;
 use sqlx::Postgres;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 
 /// Shared application state, passed to handlers and middleware.
 #[derive(Clone)]
 pub struct AppState {
     pub pool: Pool<Postgres>,
     pub config: Arc<Config>,
-    pub register_limiter: Arc<IpLimiter>,
-    pub login_limiter: Arc<IpLimiter>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub mailer: Arc<dyn Mailer>,
+    pub jwt_keys: Arc<JwtKeys>,
+    /// Deny-list of revoked access tokens
+    pub revoked_tokens: Arc<RevokedTokens>,
+    /// Client for calls to external services (OpenID Connect providers)
+    pub http: reqwest::Client,
+    /// Permits for imports running on this instance
+    pub import_slots: Arc<Semaphore>,
 }
 
 impl AppState {
-    /// Constructs a new AppState with a database pool, configuration, and IP limiter.
-    pub fn new(pool: Pool<Postgres>, config: Config) -> Self {
-        let register_per_hour = config.register_ip_limit_per_hour.unwrap_or(1);
-        let login_per_hour = config.login_ip_limit_per_hour.unwrap_or(1);
+    /// Constructs a new AppState with a database pool, configuration, rate limiter,
+    /// mailer and JWT signing keys.
+    pub fn new(
+        pool: Pool<Postgres>,
+        config: Config,
+        mailer: Arc<dyn Mailer>,
+        jwt_keys: JwtKeys,
+    ) -> Self {
         AppState {
             pool,
-            register_limiter: Arc::new(IpLimiter::new(register_per_hour)),
-            login_limiter: Arc::new(IpLimiter::new(login_per_hour)),
+            rate_limiter: Arc::new(RateLimiter::from_config(&config)),
+            mailer,
+            jwt_keys: Arc::new(jwt_keys),
+            revoked_tokens: Arc::new(RevokedTokens::default()),
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            import_slots: Arc::new(Semaphore::new(MAX_RUNNING_IMPORTS)),
             config: Arc::new(config),
         }
     }
@@ -1,4 +1,14 @@
-use crate::{database::token::verify_jwt, models::user::User, state::AppState};
+use crate::{
+    database::access_token::{ACCESS_TOKEN_PREFIX, authenticate_access_token},
+    database::token::verify_jwt,
+    models::{
+        personal_access_token::Scope,
+        platform::Platform,
+        user::{Role, User},
+    },
+    state::AppState,
+    utils::scope::TokenScopes,
+};
 use axum::{
     body::Body,
     extract::State,
@@ -10,8 +20,17 @@ use sqlx::query_as;
 use tracing::{error, info};
 use uuid::Uuid;
 
+/// Credential the request was authenticated with.
+enum Credential {
+    /// Login session (JWT) with its issued-at timestamp
+    Session(Platform, f64),
+    /// Personal access token with its scopes
+    AccessToken(Vec<Scope>),
+}
+
 /// Authentication middleware for Axum.
-/// Checks for a Bearer JWT token, verifies it, fetches the user, and inserts user_id into request extensions.
+/// Checks for a Bearer JWT or personal access token, verifies it, fetches the user, and inserts user_id and role into request extensions.
+/// Disabled accounts are rejected.
 /// Logs all important steps and errors.
 pub async fn auth_middleware(
     State(state): State<AppState>,
@@ -31,27 +50,42 @@ pub async fn auth_middleware(
         return Err((StatusCode::UNAUTHORIZED, "Missing Bearer token"));
     }
 
-    // 2) Verify JWT token
-    let secret = state
-        .config
-        .jwt_secret
-        .as_deref()
-        .expect("Missing jwt_secret in config");
-
-    // After JWT verification
-    let data = match verify_jwt(token, secret) {
-        Ok(d) => d,
-        Err(e) => {
-            error!("Invalid JWT token: {}", e);
-            return Err((StatusCode::UNAUTHORIZED, "Invalid token"));
+    // 2) Verify personal access token or JWT token
+    let (user_id, credential) = if token.starts_with(ACCESS_TOKEN_PREFIX) {
+        match authenticate_access_token(&state.pool, token).await {
+            Ok(Some(pat)) => {
+                info!("Authenticated with access token {}", pat.id);
+                (pat.user_id, Credential::AccessToken(pat.parsed_scopes()))
+            }
+            Ok(None) => {
+                info!("Unknown, expired or revoked access token");
+                return Err((StatusCode::UNAUTHORIZED, "Invalid token"));
+            }
+            Err(e) => {
+                error!("Database error when checking access token: {}", e);
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, "Database error"));
+            }
+        }
+    } else {
+        let data = match verify_jwt(token, &state.jwt_keys) {
+            Ok(d) => d,
+            Err(e) => {
+                error!("Invalid JWT token: {}", e);
+                return Err((StatusCode::UNAUTHORIZED, "Invalid token"));
+            }
+        };
+        if state.revoked_tokens.is_revoked(&data.jti) {
+            info!("Revoked JWT jti={} for sub={}", data.jti, data.sub);
+            return Err((StatusCode::UNAUTHORIZED, "Token revoked"));
         }
-    };
 
-    // Parse UUID from JWT sub
-    let user_id = uuid::Uuid::parse_str(&data.sub).map_err(|e| {
-        error!("Invalid UUID in JWT sub: {}", e);
-        (StatusCode::UNAUTHORIZED, "Invalid user ID in token")
-    })?;
+        // Parse UUID from JWT sub
+        let user_id = uuid::Uuid::parse_str(&data.sub).map_err(|e| {
+            error!("Invalid UUID in JWT sub: {}", e);
+            (StatusCode::UNAUTHORIZED, "Invalid user ID in token")
+        })?;
+        (user_id, Credential::Session(data.platform, data.iat))
+    };
 
     // Fetch user by id
     let user: User = match query_as::<_, User>("SELECT * FROM users WHERE id=$1")
@@ -70,11 +104,138 @@ pub async fn auth_middleware(
         }
     };
 
+    // Tokens issued before "log out everywhere" are no longer valid
+    if let (Credential::Session(_, iat), Some(after)) = (&credential, user.tokens_valid_after)
+        && *iat < after.timestamp_micros() as f64 / 1_000_000.0
+    {
+        info!("JWT of user {} issued before {}", user_id, after);
+        return Err((StatusCode::UNAUTHORIZED, "Token revoked"));
+    }
+
+    if user.disabled_at.is_some() {
+        info!("User {} is disabled", user_id);
+        return Err((StatusCode::FORBIDDEN, "Account disabled"));
+    }
+
+    // Accounts pending deletion are locked until the user logs in again
+    if user.scheduled_deletion_at.is_some() {
+        info!("User {} is scheduled for deletion", user_id);
+        return Err((StatusCode::UNAUTHORIZED, "Account scheduled for deletion"));
+    }
+
     info!("Authenticated user_id={} email={}", user.id, user.email);
 
-    // Insert user_id into request extensions for downstream extractors
+    // Insert user_id and platform (or token scopes) into request extensions for downstream extractors
     req.extensions_mut().insert::<Uuid>(user.id);
+    req.extensions_mut().insert::<Role>(user.role);
+    match credential {
+        Credential::Session(platform, _) => {
+            req.extensions_mut().insert::<Platform>(platform);
+        }
+        Credential::AccessToken(scopes) => {
+            req.extensions_mut().insert(TokenScopes(scopes));
+        }
+    }
 
     // Continue to next handler/middleware
     Ok(next.run(req).await)
 }
+
+/// Middleware restricting a router to admins. Must run after `auth_middleware`.
+pub async fn require_admin(
+    req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    if req.extensions().get::<Role>() != Some(&Role::Admin) {
+        info!("Non-admin access to {} denied", req.uri().path());
+        return Err((StatusCode::FORBIDDEN, "Admin role required"));
+    }
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::token::{create_jwt, create_mfa_challenge_token},
+        mailer::LogMailer,
+        utils::{config_loader::Config, jwt_keys::JwtKeys},
+    };
+    use axum::{Router, middleware, routing::get};
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    /// State without a reachable database; requests rejected before any query still work.
+    fn test_state() -> AppState {
+        let config: Config = toml::from_str(
+            r#"
+            database_url = "postgres://localhost/motek_test"
+            jwt_secret = "test-secret"
+            server_address = "127.0.0.1"
+            port = 0
+            "#,
+        )
+        .unwrap();
+        let pool = PgPoolOptions::new()
+            .connect_lazy(&config.database_url)
+            .unwrap();
+        let keys = JwtKeys::from_config(&config).unwrap();
+        AppState::new(pool, config, Arc::new(LogMailer), keys)
+    }
+
+    async fn get_protected(state: AppState, token: &str) -> StatusCode {
+        let app = Router::new()
+            .route("/protected", get(|| async { "ok" }))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth_middleware,
+            ))
+            .with_state(state);
+        let request = Request::builder()
+            .uri("/protected")
+            .header("Authorization", format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn mfa_challenge_token_is_not_an_access_token() {
+        let state = test_state();
+        let token =
+            create_mfa_challenge_token(Uuid::new_v4(), Platform::Web, &state.jwt_keys).unwrap();
+        assert!(verify_jwt(&token, &state.jwt_keys).is_err());
+        assert_eq!(get_protected(state, &token).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn token_without_iat_or_jti_is_rejected() {
+        let state = test_state();
+        let exp = (chrono::Utc::now() + chrono::Duration::minutes(5)).timestamp();
+        let token = state
+            .jwt_keys
+            .encode(&serde_json::json!({
+                "sub": Uuid::new_v4().to_string(),
+                "exp": exp,
+                "platform": Platform::Web,
+                "typ": "access",
+            }))
+            .unwrap();
+        assert!(verify_jwt(&token, &state.jwt_keys).is_err());
+    }
+
+    #[tokio::test]
+    async fn access_token_is_verified() {
+        let state = test_state();
+        let user_id = Uuid::new_v4().to_string();
+        let token = create_jwt(
+            &user_id,
+            Platform::Web,
+            chrono::Duration::minutes(5),
+            &state.jwt_keys,
+        )
+        .unwrap();
+        assert_eq!(verify_jwt(&token, &state.jwt_keys).unwrap().sub, user_id);
+    }
+}
@@ -1,22 +1,203 @@
 // utils/config_loader.rs
 
+use crate::models::platform::Platform;
+use crate::utils::rate_limit::RateLimitGroup;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
+use std::net::IpAddr;
 use tracing::{error, info};
 
 /// Application configuration loaded from TOML file.
 #[derive(Clone, Debug, Deserialize)]
 pub struct Config {
     pub database_url: String,
+    /// Legacy HS256 secret; still accepted for verification when `[jwt]` keys are set
     pub jwt_secret: Option<String>,
+    /// Signing keys for access tokens (default: HS256 with `jwt_secret`)
+    pub jwt: Option<JwtConfig>,
     pub server_address: String,
     pub port: u16,
+    /// Default of `[rate_limit.groups.register]` (requests per hour per IP)
     pub register_ip_limit_per_hour: Option<u32>,
+    /// Default of `[rate_limit.groups.login]` (requests per hour per IP)
     pub login_ip_limit_per_hour: Option<u32>,
+    /// Request rate limits per route group
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Public URL of the frontend, used to build links in emails
+    pub app_url: Option<String>,
+    /// Lifetime of password reset tokens in minutes (default: 30)
+    pub password_reset_ttl_minutes: Option<i64>,
+    /// Reject logins of accounts with an unverified email (default: false)
+    pub require_email_verification: Option<bool>,
+    /// Lifetime of email verification links in hours (default: 48)
+    pub email_verification_ttl_hours: Option<i64>,
+    /// Days between an account deletion request and the purge of its data (default: 14)
+    pub account_deletion_grace_days: Option<i64>,
+    /// Versions kept per note; the oldest are deleted beyond this (default: 50)
+    pub note_version_limit: Option<i64>,
+    /// Days deleted notes and notebooks stay in the trash before being purged (default: 30)
+    pub trash_retention_days: Option<i64>,
+    /// Directory of attachment files stored by the server; such files are included in
+    /// notebook exports (default: attachment files are not stored, exports only link them)
+    pub attachment_dir: Option<String>,
+    /// Outgoing email settings (default: emails are only logged)
+    pub mailer: Option<MailerConfig>,
+    /// OpenID Connect providers by name, e.g. `[oidc.company]`
+    #[serde(default)]
+    pub oidc: HashMap<String, OidcProviderConfig>,
+    /// Token lifetimes per platform, e.g. `[platforms.web]` (default: 24 h / 30 days)
+    #[serde(default)]
+    pub platforms: HashMap<Platform, PlatformPolicy>,
+    /// Argon2id cost parameters for password hashing
+    #[serde(default)]
+    pub argon2: Argon2Config,
+    /// Per-account lockout after failed logins
+    #[serde(default)]
+    pub lockout: LockoutConfig,
+}
+
+/// Token lifetimes for one platform.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PlatformPolicy {
+    pub access_token_minutes: Option<i64>,
+    pub refresh_token_days: Option<i64>,
+}
+
+/// One OpenID Connect provider. Endpoints are read from
+/// `<issuer>/.well-known/openid-configuration`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OidcProviderConfig {
+    pub issuer: String,
+    pub client_id: String,
+    /// Not needed for public clients (PKCE only)
+    pub client_secret: Option<String>,
+    /// Frontend URL the provider redirects back to
+    pub redirect_uri: String,
+    /// Requested scopes (default: "openid email profile")
+    pub scopes: Option<Vec<String>>,
+    /// Create an account for unknown users (default: true)
+    pub allow_signup: Option<bool>,
+}
+
+/// JWT signing keys: `active_kid` signs new tokens, the other keys are only
+/// used to verify tokens issued before a rotation.
+#[derive(Clone, Debug, Deserialize)]
+pub struct JwtConfig {
+    pub active_kid: String,
+    pub keys: Vec<JwtKeyConfig>,
+}
+
+/// One JWT key. RS256/EdDSA keys are read from PEM files, HMAC keys use `secret`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    pub algorithm: jsonwebtoken::Algorithm,
+    pub private_key_path: Option<String>,
+    pub public_key_path: Option<String>,
+    pub secret: Option<String>,
+}
+
+/// Argon2id cost parameters. Defaults follow the OWASP recommendation
+/// (19 MiB memory, 2 iterations, 1 lane).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Argon2Config {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Rate limiting: trusted reverse proxies, memory bound and per-group rules.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Proxies whose `X-Forwarded-For` header is trusted
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Maximum number of tracked clients (default: 100000)
+    pub max_entries: Option<usize>,
+    pub groups: HashMap<RateLimitGroup, RateLimitRule>,
+}
+
+/// Token bucket: bursts of up to `requests`, refilled at `requests` per `per_seconds`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateLimitRule {
+    pub requests: u32,
+    pub per_seconds: u64,
+}
+
+/// Per-account lockout: after `free_attempts` failures within `failure_window_minutes`
+/// the account is locked for `base_lock_seconds`, doubling with each further failure
+/// up to `max_lock_seconds`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LockoutConfig {
+    pub free_attempts: i32,
+    pub base_lock_seconds: i64,
+    pub max_lock_seconds: i64,
+    pub failure_window_minutes: i64,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            free_attempts: 5,
+            base_lock_seconds: 30,
+            max_lock_seconds: 60 * 60,
+            failure_window_minutes: 24 * 60,
+        }
+    }
+}
+
+/// Outgoing email transport, selected with `kind = "smtp" | "file" | "log"`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum MailerConfig {
+    Smtp {
+        host: String,
+        port: Option<u16>,
+        username: Option<String>,
+        password: Option<String>,
+        from: String,
+    },
+    /// Writes emails as files into `dir` (development and tests)
+    File {
+        dir: String,
+    },
+    Log,
 }
 
 impl Config {
+    /// Lifetime of access tokens (JWT) issued to a platform.
+    pub fn access_token_ttl(&self, platform: Platform) -> chrono::Duration {
+        let minutes = self
+            .platforms
+            .get(&platform)
+            .and_then(|p| p.access_token_minutes)
+            .unwrap_or(24 * 60);
+        chrono::Duration::minutes(minutes)
+    }
+
+    /// Lifetime of refresh tokens issued to a platform, in days.
+    pub fn refresh_token_days(&self, platform: Platform) -> i64 {
+        self.platforms
+            .get(&platform)
+            .and_then(|p| p.refresh_token_days)
+            .unwrap_or(30)
+    }
+
     /// Loads configuration based on RUN_ENV environment variable (default: "dev").
     /// Panics if file cannot be read or parsed.
     pub fn load() -> Self {
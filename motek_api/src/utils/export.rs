@@ -0,0 +1,431 @@
+//! Rendering of notes to Markdown, HTML and plain text, and ZIP archives of notebooks.
+//!
+//! Markdown files start with a YAML front matter holding the title, tags, timestamps and
+//! flags; HTML files carry the same in `<meta>` elements and text files in a header.
+
+use crate::database::export::NotebookExport;
+use crate::models::{
+    attachment::Attachment,
+    note::Note,
+    note_content::{Block, NoteContent},
+};
+use chrono::{DateTime, Datelike, SecondsFormat, Timelike, Utc};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use tracing::warn;
+use uuid::Uuid;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+/// Folder of a notebook archive holding the attachment files.
+const ATTACHMENTS_DIR: &str = "_attachments";
+/// Maximum length of a file or folder name, in characters.
+const MAX_NAME_LENGTH: usize = 100;
+
+/// File format of exported notes.
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteFormat {
+    #[default]
+    Md,
+    Html,
+    Txt,
+}
+
+impl NoteFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            NoteFormat::Md => "md",
+            NoteFormat::Html => "html",
+            NoteFormat::Txt => "txt",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            NoteFormat::Md => "text/markdown; charset=utf-8",
+            NoteFormat::Html => "text/html; charset=utf-8",
+            NoteFormat::Txt => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+/// Renders a note. `hrefs` maps attachment ids to the links used for image blocks;
+/// images of other attachments are left out.
+pub fn render_note(note: &Note, format: NoteFormat, hrefs: &HashMap<Uuid, String>) -> String {
+    let content = NoteContent::from_legacy(&note.content);
+    match format {
+        NoteFormat::Md => render_markdown(note, &content, hrefs),
+        NoteFormat::Html => render_html(note, &content, hrefs),
+        NoteFormat::Txt => render_text(note, &content),
+    }
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// JSON strings are valid YAML double-quoted scalars.
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn render_markdown(note: &Note, content: &NoteContent, hrefs: &HashMap<Uuid, String>) -> String {
+    let mut out = String::from("---\n");
+    out.push_str(&format!("title: {}\n", yaml_string(&note.title)));
+    let tags: Vec<String> = note.tags.iter().map(|t| yaml_string(t)).collect();
+    out.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+    out.push_str(&format!("created: {}\n", timestamp(&note.created_at)));
+    out.push_str(&format!("updated: {}\n", timestamp(&note.updated_at)));
+    if note.is_pinned {
+        out.push_str("pinned: true\n");
+    }
+    if note.is_archived {
+        out.push_str("archived: true\n");
+    }
+    out.push_str("---\n");
+
+    for block in &content.blocks {
+        let text = match block {
+            Block::Paragraph { text } if text.trim().is_empty() => continue,
+            Block::Paragraph { text } => text.clone(),
+            Block::Heading { level, text } => {
+                format!("{} {}", "#".repeat(*level as usize), text)
+            }
+            Block::List { ordered, items } => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| match ordered {
+                    true => format!("{}. {}", i + 1, item),
+                    false => format!("- {}", item),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Block::Checklist { items } => items
+                .iter()
+                .map(|item| {
+                    let mark = if item.checked { 'x' } else { ' ' };
+                    format!("- [{}] {}", mark, item.text)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Block::Code { language, code } => {
+                // The fence must be longer than any run of backticks in the code
+                let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+                let fence = "`".repeat((longest + 1).max(3));
+                format!(
+                    "{}{}\n{}\n{}",
+                    fence,
+                    language.as_deref().unwrap_or(""),
+                    code,
+                    fence
+                )
+            }
+            Block::Image {
+                attachment_id,
+                caption,
+            } => match hrefs.get(attachment_id) {
+                Some(href) => format!("![{}](<{}>)", caption.as_deref().unwrap_or(""), href),
+                None => continue,
+            },
+            Block::Link { url, text } => match text {
+                Some(text) => format!("[{}](<{}>)", text, url),
+                None => format!("<{}>", url),
+            },
+        };
+        out.push('\n');
+        out.push_str(&text);
+        out.push('\n');
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_html(note: &Note, content: &NoteContent, hrefs: &HashMap<Uuid, String>) -> String {
+    let title = escape_html(&note.title);
+    let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", title));
+    let meta = [
+        ("keywords", escape_html(&note.tags.join(", "))),
+        ("dcterms.created", timestamp(&note.created_at)),
+        ("dcterms.modified", timestamp(&note.updated_at)),
+    ];
+    for (name, value) in meta {
+        out.push_str(&format!("<meta name=\"{}\" content=\"{}\">\n", name, value));
+    }
+    out.push_str("</head>\n<body>\n");
+    out.push_str(&format!("<h1>{}</h1>\n", title));
+
+    for block in &content.blocks {
+        match block {
+            Block::Paragraph { text } if text.trim().is_empty() => {}
+            Block::Paragraph { text } => out.push_str(&format!("<p>{}</p>\n", escape_html(text))),
+            Block::Heading { level, text } => {
+                // The note title is the only <h1>
+                let level = (*level + 1).min(6);
+                out.push_str(&format!("<h{}>{}</h{}>\n", level, escape_html(text), level));
+            }
+            Block::List { ordered, items } => {
+                let tag = if *ordered { "ol" } else { "ul" };
+                out.push_str(&format!("<{}>\n", tag));
+                for item in items {
+                    out.push_str(&format!("<li>{}</li>\n", escape_html(item)));
+                }
+                out.push_str(&format!("</{}>\n", tag));
+            }
+            Block::Checklist { items } => {
+                out.push_str("<ul class=\"checklist\">\n");
+                for item in items {
+                    out.push_str(&format!(
+                        "<li><input type=\"checkbox\" disabled{}> {}</li>\n",
+                        if item.checked { " checked" } else { "" },
+                        escape_html(&item.text)
+                    ));
+                }
+                out.push_str("</ul>\n");
+            }
+            Block::Code { language, code } => {
+                let class = language
+                    .as_deref()
+                    .map(|l| format!(" class=\"language-{}\"", escape_html(l)))
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "<pre><code{}>{}</code></pre>\n",
+                    class,
+                    escape_html(code)
+                ));
+            }
+            Block::Image {
+                attachment_id,
+                caption,
+            } => {
+                let Some(href) = hrefs.get(attachment_id) else {
+                    continue;
+                };
+                let caption = escape_html(caption.as_deref().unwrap_or(""));
+                out.push_str(&format!(
+                    "<figure><img src=\"{}\" alt=\"{}\"><figcaption>{}</figcaption></figure>\n",
+                    escape_html(href),
+                    caption,
+                    caption
+                ));
+            }
+            Block::Link { url, text } => {
+                out.push_str(&format!(
+                    "<p><a href=\"{}\">{}</a></p>\n",
+                    escape_html(url),
+                    escape_html(text.as_deref().unwrap_or(url))
+                ));
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_text(note: &Note, content: &NoteContent) -> String {
+    let mut out = format!("{}\n", note.title);
+    if !note.tags.is_empty() {
+        out.push_str(&format!("Tags: {}\n", note.tags.join(", ")));
+    }
+    out.push_str(&format!("Created: {}\n", timestamp(&note.created_at)));
+    out.push_str(&format!("Updated: {}\n\n", timestamp(&note.updated_at)));
+    out.push_str(&content.plain_text());
+    out.push('\n');
+    out
+}
+
+/// Turns a title into a file or folder name that is valid on common file systems.
+pub fn file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    let name = name.trim().trim_matches('.').trim();
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns `name`, or `name (2)`, `name (3)`, … if it is taken (ignoring case).
+fn unique_name(used: &mut HashSet<String>, name: &str, extension: &str) -> String {
+    let mut candidate = format!("{}{}", name, extension);
+    let mut n = 1;
+    while !used.insert(candidate.to_lowercase()) {
+        n += 1;
+        candidate = format!("{} ({}){}", name, n, extension);
+    }
+    candidate
+}
+
+/// Local file of an attachment stored by the server under `storage_key`, relative to `dir`.
+fn attachment_path(dir: &Path, storage_key: &str) -> Option<PathBuf> {
+    let relative = Path::new(storage_key);
+    relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+        .then(|| dir.join(relative))
+}
+
+fn zip_time(time: &DateTime<Utc>) -> zip::DateTime {
+    zip::DateTime::from_date_and_time(
+        time.year().clamp(1980, 2107) as u16,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .unwrap_or_default()
+}
+
+/// Writes a notebook as a ZIP archive: one folder per notebook, one file per note and the
+/// attachment files the server stored in `attachment_dir` under `_attachments/` of the top
+/// folder. Other attachments keep their URL as the link.
+pub fn write_notebook_zip<W: Write + Seek>(
+    writer: W,
+    export: &NotebookExport,
+    format: NoteFormat,
+    attachment_dir: Option<&Path>,
+) -> anyhow::Result<W> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(writer);
+    let Some(root) = export.notebooks.first() else {
+        anyhow::bail!("nothing to export");
+    };
+
+    // Folder path of every notebook, parents first
+    let mut folders: HashMap<Uuid, Vec<String>> = HashMap::new();
+    let mut used: HashMap<Uuid, HashSet<String>> = HashMap::new();
+    folders.insert(root.id, vec![file_name(&root.name)]);
+    used.entry(root.id)
+        .or_default()
+        .insert(ATTACHMENTS_DIR.to_lowercase());
+    let mut pending: Vec<_> = export.notebooks.iter().skip(1).collect();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|nb| {
+            let Some(parent_id) = nb.parent_id else {
+                return true;
+            };
+            let Some(mut path) = folders.get(&parent_id).cloned() else {
+                return true;
+            };
+            let siblings = used.entry(parent_id).or_default();
+            path.push(unique_name(siblings, &file_name(&nb.name), ""));
+            folders.insert(nb.id, path);
+            false
+        });
+        if pending.len() == before {
+            break;
+        }
+    }
+    let mut paths: Vec<_> = folders.values().collect();
+    paths.sort();
+    for path in paths {
+        zip.add_directory(format!("{}/", path.join("/")), options)?;
+    }
+
+    // Attachment files, each under a unique name
+    let top = &folders[&root.id][0];
+    let mut archived: HashMap<Uuid, String> = HashMap::new();
+    let mut urls: HashMap<Uuid, String> = HashMap::new();
+    let mut attachment_names = HashSet::new();
+    let mut has_attachments = false;
+    for attachment in &export.attachments {
+        match read_attachment(attachment, attachment_dir) {
+            Some(bytes) => {
+                if !has_attachments {
+                    zip.add_directory(format!("{}/{}/", top, ATTACHMENTS_DIR), options)?;
+                    has_attachments = true;
+                }
+                let name = unique_name(&mut attachment_names, &file_name(&attachment.filename), "");
+                zip.start_file(format!("{}/{}/{}", top, ATTACHMENTS_DIR, name), options)?;
+                zip.write_all(&bytes)?;
+                archived.insert(attachment.id, name);
+            }
+            None => {
+                urls.insert(attachment.id, attachment.url.clone());
+            }
+        }
+    }
+
+    let extension = format!(".{}", format.extension());
+    for note in &export.notes {
+        let Some(notebook_id) = note.notebook_id else {
+            continue;
+        };
+        let Some(folder) = folders.get(&notebook_id) else {
+            continue;
+        };
+        // Links to archived attachments are relative to the note's folder
+        let up = "../".repeat(folder.len() - 1);
+        let mut hrefs = urls.clone();
+        hrefs.extend(
+            archived
+                .iter()
+                .map(|(id, name)| (*id, format!("{}{}/{}", up, ATTACHMENTS_DIR, name))),
+        );
+        let siblings = used.entry(notebook_id).or_default();
+        let name = unique_name(siblings, &file_name(&note.title), &extension);
+        zip.start_file(
+            format!("{}/{}", folder.join("/"), name),
+            options.last_modified_time(zip_time(&note.updated_at)),
+        )?;
+        zip.write_all(render_note(note, format, &hrefs).as_bytes())?;
+    }
+
+    Ok(zip.finish()?)
+}
+
+fn read_attachment(attachment: &Attachment, dir: Option<&Path>) -> Option<Vec<u8>> {
+    let path = attachment_path(dir?, attachment.storage_key.as_deref()?)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => Some(bytes),
+        Err(e) => {
+            warn!(
+                "Attachment {} not exported, cannot read {}: {}",
+                attachment.id,
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Temporary file for building an archive. On Unix it is unlinked right away, so its
+/// space is freed as soon as the handle is dropped.
+pub fn temp_file() -> std::io::Result<File> {
+    let path = std::env::temp_dir().join(format!("motek-export-{}.zip", Uuid::new_v4()));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    let _ = std::fs::remove_file(&path);
+    Ok(file)
+}
@@ -0,0 +1,1448 @@
+//! Parsing of notes exported by other applications: Markdown folders in a ZIP archive,
+//! Evernote ENEX and Google Keep Takeout. Produces [`ImportedNote`]s one at a time;
+//! storing them is up to the caller. Archives are read file by file, so only the notes
+//! being passed on and their attachments are held in memory.
+
+use crate::models::{
+    import_job::ImportItemError,
+    note_content::{Block, CONTENT_VERSION, ChecklistItem, NoteContent, validate_link},
+};
+use crate::utils::xml::{self, Element, Node};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use md5::{Digest, Md5};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read};
+use tracing::warn;
+use uuid::Uuid;
+use zip::ZipArchive;
+
+/// Largest file read from an uploaded archive.
+const MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+/// Largest total size of the files read from an uploaded archive.
+const MAX_ARCHIVE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Source format of an import.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// ZIP of Markdown files; folders become notebooks
+    Markdown,
+    /// Evernote `.enex` file, or a ZIP of them (one notebook per file)
+    Enex,
+    /// Google Keep Takeout ZIP, or a single Keep JSON note
+    Keep,
+}
+
+impl ImportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportFormat::Markdown => "markdown",
+            ImportFormat::Enex => "enex",
+            ImportFormat::Keep => "keep",
+        }
+    }
+}
+
+/// File attached to an imported note; image blocks refer to it by `id`.
+#[derive(Debug)]
+pub struct ImportedFile {
+    pub id: Uuid,
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// A note read from an upload, ready to be stored.
+#[derive(Debug)]
+pub struct ImportedNote {
+    /// File name or title, for the error report
+    pub item: String,
+    /// Folders (notebooks) of the note, outermost first
+    pub folders: Vec<String>,
+    pub title: String,
+    pub content: NoteContent,
+    pub tags: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub is_pinned: bool,
+    pub is_archived: bool,
+    pub attachments: Vec<ImportedFile>,
+    /// Problems that did not prevent the import, e.g. missing attachments
+    pub warnings: Vec<String>,
+}
+
+impl ImportedNote {
+    fn new(item: String, folders: Vec<String>, title: String) -> Self {
+        ImportedNote {
+            item,
+            folders,
+            title,
+            content: NoteContent::from_plain_text(""),
+            tags: Vec::new(),
+            created_at: None,
+            updated_at: None,
+            is_pinned: false,
+            is_archived: false,
+            attachments: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// A note of the upload, or why it cannot be imported.
+pub type ImportItem = Result<ImportedNote, ImportItemError>;
+
+/// What a parser reports while reading an upload.
+#[derive(Debug)]
+pub enum ImportEvent {
+    /// This many more items were found; they follow as [`ImportEvent::Item`]s
+    Found(usize),
+    Item(ImportItem),
+}
+
+fn item_error(item: &str, error: impl Into<String>) -> ImportItemError {
+    ImportItemError {
+        item: item.to_string(),
+        error: error.into(),
+    }
+}
+
+/// Receives the events of a parser; returns `false` to stop parsing.
+pub type Emit<'a> = dyn FnMut(ImportEvent) -> bool + 'a;
+
+/// Parses an upload, passing its items to `emit` as they are read. Fails only if the
+/// upload as a whole cannot be read; items emitted before that stay valid.
+pub fn parse_upload(format: ImportFormat, data: &[u8], emit: &mut Emit) -> Result<(), String> {
+    match format {
+        ImportFormat::Markdown => parse_markdown_zip(data, emit),
+        ImportFormat::Enex => parse_enex_upload(data, emit),
+        ImportFormat::Keep => parse_keep_upload(data, emit),
+    }
+}
+
+fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(b"PK\x03\x04")
+}
+
+/// A ZIP archive whose files are read on demand. Directories and hidden files are
+/// left out.
+struct Archive<'a> {
+    zip: ZipArchive<Cursor<&'a [u8]>>,
+    /// Entry index of each file by path, with `/` separators
+    files: BTreeMap<String, usize>,
+    /// Bytes decompressed so far
+    total: u64,
+}
+
+impl<'a> Archive<'a> {
+    fn open(data: &'a [u8]) -> Result<Self, String> {
+        let mut zip =
+            ZipArchive::new(Cursor::new(data)).map_err(|e| format!("Not a ZIP archive: {}", e))?;
+        let mut files = BTreeMap::new();
+        for i in 0..zip.len() {
+            let entry = zip
+                .by_index_raw(i)
+                .map_err(|e| format!("Broken ZIP archive: {}", e))?;
+            let Some(path) = entry.enclosed_name() else {
+                continue;
+            };
+            let parts: Vec<String> = path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            if entry.is_dir() || parts.iter().any(|p| p.starts_with('.') || p == "__MACOSX") {
+                continue;
+            }
+            files.insert(parts.join("/"), i);
+        }
+        Ok(Archive {
+            zip,
+            files,
+            total: 0,
+        })
+    }
+
+    /// Paths of the files whose lowercase path ends with `suffix`.
+    fn paths_ending_with(&self, suffix: &str) -> Vec<String> {
+        self.files
+            .keys()
+            .filter(|path| path.to_lowercase().ends_with(suffix))
+            .cloned()
+            .collect()
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Contents of a file, or `None` if it is missing or too large. Fails if the archive
+    /// is broken or more than `MAX_ARCHIVE_SIZE` bytes were read from it.
+    fn read(&mut self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        let Some(&index) = self.files.get(path) else {
+            return Ok(None);
+        };
+        let mut entry = self
+            .zip
+            .by_index(index)
+            .map_err(|e| format!("Broken ZIP archive: {}", e))?;
+        if entry.size() > MAX_ENTRY_SIZE {
+            warn!("Skipping {} of an import: {} bytes", path, entry.size());
+            return Ok(None);
+        }
+        let mut bytes = Vec::new();
+        entry
+            .by_ref()
+            .take(MAX_ENTRY_SIZE)
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Broken ZIP archive: {}", e))?;
+        self.total += bytes.len() as u64;
+        if self.total > MAX_ARCHIVE_SIZE {
+            return Err("The archive is too large".into());
+        }
+        Ok(Some(bytes))
+    }
+}
+
+/// Splits `a/b/c.md` into (`["a", "b"]`, `c.md`).
+fn split_path(path: &str) -> (Vec<String>, &str) {
+    match path.rsplit_once('/') {
+        Some((dirs, file)) => (dirs.split('/').map(str::to_string).collect(), file),
+        None => (Vec::new(), path),
+    }
+}
+
+fn file_stem(file: &str) -> &str {
+    file.rsplit_once('.').map_or(file, |(stem, _)| stem)
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(time.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+fn language(info: &str) -> Option<String> {
+    let language = info.split_whitespace().next()?;
+    (language.chars().count() <= 32).then(|| language.to_string())
+}
+
+/// Appends a checklist item, continuing the checklist the blocks end with.
+fn push_checklist_item(blocks: &mut Vec<Block>, text: String, checked: bool) {
+    let item = ChecklistItem { text, checked };
+    match blocks.last_mut() {
+        Some(Block::Checklist { items }) => items.push(item),
+        _ => blocks.push(Block::Checklist { items: vec![item] }),
+    }
+}
+
+// --- Markdown ---
+
+/// Metadata from the YAML front matter of a Markdown file.
+#[derive(Default)]
+struct FrontMatter {
+    title: Option<String>,
+    tags: Vec<String>,
+    created: Option<DateTime<Utc>>,
+    updated: Option<DateTime<Utc>>,
+    pinned: bool,
+    archived: bool,
+}
+
+/// Splits a leading `---` … `---` block from a Markdown document.
+fn split_front_matter(text: &str) -> (Option<&str>, &str) {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (None, text);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+/// A YAML scalar, unquoted.
+fn yaml_scalar(value: &str) -> String {
+    let value = value.trim();
+    if value.starts_with('"')
+        && let Ok(s) = serde_json::from_str::<String>(value)
+    {
+        return s;
+    }
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return inner.replace("''", "'");
+    }
+    value.trim_matches('"').to_string()
+}
+
+/// Items of an inline YAML list (`[a, "b, c"]` without the brackets).
+fn yaml_inline_list(inner: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for c in inner.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                current.push(c);
+            }
+            (Some(q), c) if c == q => {
+                quote = None;
+                current.push(c);
+            }
+            (None, ',') => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+    items
+        .iter()
+        .map(|item| yaml_scalar(item))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Reads the flat subset of YAML that note front matter uses: scalars, inline lists
+/// and block lists.
+fn parse_front_matter(yaml: &str) -> FrontMatter {
+    let mut values: HashMap<String, Vec<String>> = HashMap::new();
+    let mut list_key: Option<String> = None;
+    for line in yaml.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let (Some(key), Some(item)) = (&list_key, trimmed.strip_prefix('-')) {
+            values
+                .entry(key.clone())
+                .or_default()
+                .push(yaml_scalar(item));
+            continue;
+        }
+        list_key = None;
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if key.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        let parsed = if value.is_empty() {
+            list_key = Some(key.clone());
+            Vec::new()
+        } else if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            yaml_inline_list(inner)
+        } else {
+            vec![yaml_scalar(value)]
+        };
+        values.insert(key, parsed);
+    }
+
+    let first = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|k| values.get(*k).and_then(|v| v.first()))
+            .cloned()
+    };
+    let flag = |key: &str| first(&[key]).is_some_and(|v| v.eq_ignore_ascii_case("true"));
+    let tags = ["tags", "tag", "keywords"]
+        .iter()
+        .find_map(|k| values.get(*k))
+        .map(|tags| match tags.as_slice() {
+            // A single scalar holds a comma-separated list
+            [single] => single.split(',').map(str::to_string).collect(),
+            tags => tags.to_vec(),
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .map(|t| t.trim().trim_start_matches('#').to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    FrontMatter {
+        title: first(&["title"]).filter(|t| !t.is_empty()),
+        tags,
+        created: first(&["created", "created_at", "date"]).and_then(|v| parse_date(&v)),
+        updated: first(&["updated", "updated_at", "modified"]).and_then(|v| parse_date(&v)),
+        pinned: flag("pinned"),
+        archived: flag("archived"),
+    }
+}
+
+/// `(fence character, length, info string)` of a code fence line.
+fn code_fence(line: &str) -> Option<(char, usize, &str)> {
+    let c = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = line.chars().take_while(|&x| x == c).count();
+    (len >= 3).then(|| (c, len, line[len..].trim()))
+}
+
+fn heading(line: &str) -> Option<Block> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some(Block::Heading {
+        level: level as u8,
+        text: rest.trim().trim_end_matches('#').trim_end().to_string(),
+    })
+}
+
+/// `(ordered, text)` of a list item line.
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    if let Some(text) = ["- ", "* ", "+ "].iter().find_map(|m| line.strip_prefix(m)) {
+        return Some((false, text.trim()));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let rest = &line[digits..];
+    let text = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") "));
+    (digits > 0)
+        .then_some(text)
+        .flatten()
+        .map(|t| (true, t.trim()))
+}
+
+/// `(checked, text)` of a task list item like `- [x] done`.
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let (false, text) = list_item(line)? else {
+        return None;
+    };
+    if let Some(text) = text.strip_prefix("[ ]") {
+        return Some((false, text.trim()));
+    }
+    text.strip_prefix("[x]")
+        .or_else(|| text.strip_prefix("[X]"))
+        .map(|text| (true, text.trim()))
+}
+
+/// `(text, destination)` of a line consisting of one `[text](destination)`.
+fn whole_link(line: &str) -> Option<(&str, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(')')?;
+    let (text, destination) = inner.split_once("](")?;
+    if text.contains(']') {
+        return None;
+    }
+    // Drop an optional title: [text](url "title")
+    let destination = match destination.strip_prefix('<') {
+        Some(d) => d.split_once('>').map_or(d, |(d, _)| d),
+        None => destination.split_whitespace().next().unwrap_or(""),
+    };
+    Some((text, percent_decode(destination)))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Resolves `target` relative to the folder `dir` of an archive.
+fn resolve_path(dir: &[String], target: &str) -> Option<String> {
+    let mut parts: Vec<&str> = dir.iter().map(String::as_str).collect();
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Converts Markdown to blocks. Inline formatting is kept as written. `image` turns
+/// an image line (`alt`, `source`) into a block.
+fn markdown_blocks(body: &str, image: &mut dyn FnMut(&str, &str) -> Option<Block>) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    // Whether the last block is a list that the next item continues
+    let mut in_list = false;
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph {
+                text: paragraph.join("\n"),
+            });
+            paragraph.clear();
+        }
+    };
+
+    let mut lines = body.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+            continue;
+        }
+        if let Some((fence, len, info)) = code_fence(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if code_fence(line.trim())
+                    .is_some_and(|(c, l, i)| c == fence && l >= len && i.is_empty())
+                {
+                    break;
+                }
+                code.push(line);
+            }
+            blocks.push(Block::Code {
+                language: language(info),
+                code: code.join("\n"),
+            });
+            in_list = false;
+            continue;
+        }
+        if let Some(block) = heading(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(block);
+            in_list = false;
+            continue;
+        }
+        if let Some((checked, text)) = checklist_item(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            if !in_list {
+                blocks.push(Block::Checklist { items: Vec::new() });
+            }
+            push_checklist_item(&mut blocks, text.to_string(), checked);
+            in_list = true;
+            continue;
+        }
+        if let Some((ordered, text)) = list_item(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            match blocks.last_mut() {
+                Some(Block::List {
+                    ordered: o, items, ..
+                }) if in_list && *o == ordered => items.push(text.to_string()),
+                _ => blocks.push(Block::List {
+                    ordered,
+                    items: vec![text.to_string()],
+                }),
+            }
+            in_list = true;
+            continue;
+        }
+        if let Some((alt, source)) = trimmed.strip_prefix('!').and_then(whole_link) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.extend(image(alt, &source));
+            in_list = false;
+            continue;
+        }
+        let link = whole_link(trimmed)
+            .map(|(text, url)| (Some(text.to_string()), url))
+            .or_else(|| {
+                let url = trimmed.strip_prefix('<')?.strip_suffix('>')?;
+                Some((None, url.to_string()))
+            })
+            .filter(|(_, url)| validate_link(url).is_ok());
+        if let Some((text, url)) = link {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Link {
+                url,
+                text: text.filter(|t| !t.is_empty()),
+            });
+            in_list = false;
+            continue;
+        }
+        paragraph.push(line.trim_end());
+        in_list = false;
+    }
+    flush(&mut paragraph, &mut blocks);
+    blocks
+}
+
+fn parse_markdown_zip(data: &[u8], emit: &mut Emit) -> Result<(), String> {
+    let mut archive = Archive::open(data)?;
+    let mut paths = archive.paths_ending_with(".md");
+    paths.extend(archive.paths_ending_with(".markdown"));
+    paths.sort();
+    if !emit(ImportEvent::Found(paths.len())) {
+        return Ok(());
+    }
+    for path in paths {
+        let item = match archive.read(&path)?.map(String::from_utf8) {
+            None => Err(item_error(&path, "File is too large")),
+            Some(Err(_)) => Err(item_error(&path, "File is not valid UTF-8")),
+            Some(Ok(text)) => Ok(markdown_note(&mut archive, &path, &text)?),
+        };
+        if !emit(ImportEvent::Item(item)) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn markdown_note(archive: &mut Archive, path: &str, text: &str) -> Result<ImportedNote, String> {
+    let (folders, file) = split_path(path);
+    let (front_matter, body) = split_front_matter(text);
+    let meta = front_matter.map(parse_front_matter).unwrap_or_default();
+    let title = meta.title.unwrap_or_else(|| file_stem(file).to_string());
+    let mut note = ImportedNote::new(path.to_string(), folders.clone(), title);
+
+    // Images found in the archive become attachments, each file once per note
+    let mut attached: HashMap<String, Uuid> = HashMap::new();
+    let mut attachments = Vec::new();
+    let mut warnings = Vec::new();
+    let mut failure = None;
+    let blocks = markdown_blocks(body, &mut |alt, source| {
+        let caption = (!alt.is_empty()).then(|| alt.to_string());
+        if validate_link(source).is_ok() {
+            return Some(Block::Link {
+                url: source.to_string(),
+                text: caption,
+            });
+        }
+        let Some(file) = resolve_path(&folders, source).filter(|p| archive.contains(p)) else {
+            warnings.push(format!("Image {} not found in the archive", source));
+            return caption.map(|text| Block::Paragraph { text });
+        };
+        let id = match attached.get(&file) {
+            Some(id) => *id,
+            None => match archive.read(&file) {
+                Ok(Some(data)) => {
+                    let id = Uuid::new_v4();
+                    attachments.push(ImportedFile {
+                        id,
+                        name: split_path(&file).1.to_string(),
+                        data,
+                    });
+                    attached.insert(file, id);
+                    id
+                }
+                Ok(None) => {
+                    warnings.push(format!("Image {} is too large", source));
+                    return caption.map(|text| Block::Paragraph { text });
+                }
+                Err(e) => {
+                    failure.get_or_insert(e);
+                    return None;
+                }
+            },
+        };
+        Some(Block::Image {
+            attachment_id: id,
+            caption,
+        })
+    });
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    note.content = NoteContent {
+        version: CONTENT_VERSION,
+        blocks,
+    };
+    note.tags = meta.tags;
+    note.created_at = meta.created;
+    note.updated_at = meta.updated;
+    note.is_pinned = meta.pinned;
+    note.is_archived = meta.archived;
+    note.attachments = attachments;
+    note.warnings = warnings;
+    Ok(note)
+}
+
+// --- Evernote ---
+
+fn parse_enex_upload(data: &[u8], emit: &mut Emit) -> Result<(), String> {
+    if !is_zip(data) {
+        let text = std::str::from_utf8(data).map_err(|_| "The file is not valid UTF-8")?;
+        parse_enex(text, Vec::new(), emit)?;
+        return Ok(());
+    }
+    let mut archive = Archive::open(data)?;
+    for path in archive.paths_ending_with(".enex") {
+        // Each export file is one Evernote notebook
+        let (mut folders, file) = split_path(&path);
+        folders.push(file_stem(file).to_string());
+        let result = match archive.read(&path)? {
+            None => Err("File is too large".to_string()),
+            Some(bytes) => String::from_utf8(bytes)
+                .map_err(|_| "File is not valid UTF-8".to_string())
+                .and_then(|text| parse_enex(&text, folders, emit)),
+        };
+        let more = match result {
+            Ok(more) => more,
+            Err(e) => {
+                emit(ImportEvent::Found(1)) && emit(ImportEvent::Item(Err(item_error(&path, e))))
+            }
+        };
+        if !more {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Emits the notes of one ENEX file; returns whether parsing should go on.
+fn parse_enex(text: &str, folders: Vec<String>, emit: &mut Emit) -> Result<bool, String> {
+    let root = xml::parse(text).map_err(|e| format!("Invalid ENEX file: {}", e))?;
+    let export = root
+        .element("en-export")
+        .ok_or("Not an Evernote export (en-export is missing)")?;
+    if !emit(ImportEvent::Found(export.elements("note").count())) {
+        return Ok(false);
+    }
+    for (i, note) in export.elements("note").enumerate() {
+        if !emit(ImportEvent::Item(enex_note(note, i, &folders))) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn parse_enex_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value.trim(), "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+fn extension_for_mime(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "text/plain" => "txt",
+        _ => "bin",
+    }
+}
+
+fn enex_note(note: &Element, index: usize, folders: &[String]) -> ImportItem {
+    let title = note.element("title").map(Element::text).unwrap_or_default();
+    let mut item = if title.trim().is_empty() {
+        format!("Note {}", index + 1)
+    } else {
+        title.clone()
+    };
+    if let Some(folder) = folders.last() {
+        item = format!("{}: {}", folder, item);
+    }
+    let mut imported = ImportedNote::new(item.clone(), folders.to_vec(), title);
+    imported.created_at = note
+        .element("created")
+        .and_then(|e| parse_enex_date(&e.text()));
+    imported.updated_at = note
+        .element("updated")
+        .and_then(|e| parse_enex_date(&e.text()));
+    imported.tags = note
+        .elements("tag")
+        .map(|t| t.text().trim().to_string())
+        .collect();
+
+    // Resources are referenced from the content by the MD5 hash of their data
+    let mut media: HashMap<String, Uuid> = HashMap::new();
+    for (i, resource) in note.elements("resource").enumerate() {
+        let encoded: String = resource
+            .element("data")
+            .map(Element::text)
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect();
+        let data = match STANDARD.decode(encoded) {
+            Ok(data) => data,
+            Err(e) => {
+                imported
+                    .warnings
+                    .push(format!("Attachment {} is not valid base64: {}", i + 1, e));
+                continue;
+            }
+        };
+        let mime = resource
+            .element("mime")
+            .map(Element::text)
+            .unwrap_or_default();
+        let name = resource
+            .element("resource-attributes")
+            .and_then(|a| a.element("file-name"))
+            .map(Element::text)
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or_else(|| format!("attachment-{}.{}", i + 1, extension_for_mime(mime.trim())));
+        let id = Uuid::new_v4();
+        media.insert(hex::encode(Md5::digest(&data)), id);
+        imported.attachments.push(ImportedFile { id, name, data });
+    }
+
+    let content = note
+        .element("content")
+        .map(Element::text)
+        .unwrap_or_default();
+    let enml = xml::parse(&content)
+        .map_err(|e| item_error(&item, format!("Invalid note content: {}", e)))?;
+    let body = enml.element("en-note").unwrap_or(&enml);
+    let mut writer = EnmlWriter {
+        blocks: Vec::new(),
+        text: String::new(),
+        media: &media,
+    };
+    writer.block(body);
+    writer.flush();
+    imported.content = NoteContent {
+        version: CONTENT_VERSION,
+        blocks: writer.blocks,
+    };
+    Ok(imported)
+}
+
+/// Converts ENML (Evernote's XHTML) to blocks. Walks the tree recursively, so relies on
+/// [`xml::MAX_DEPTH`] to bound the depth.
+struct EnmlWriter<'a> {
+    blocks: Vec<Block>,
+    /// Inline text of the current paragraph
+    text: String,
+    /// Attachment ids by MD5 hash
+    media: &'a HashMap<String, Uuid>,
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Elements that are not content: non-empty text and child elements.
+fn content_nodes(element: &Element) -> impl Iterator<Item = &Node> {
+    element.children.iter().filter(|node| match node {
+        Node::Text(text) => !text.trim().is_empty(),
+        Node::Element(_) => true,
+    })
+}
+
+impl EnmlWriter<'_> {
+    fn flush(&mut self) {
+        let text = collapse_whitespace(&self.text);
+        if !text.is_empty() {
+            self.blocks.push(Block::Paragraph { text });
+        }
+        self.text.clear();
+    }
+
+    fn block(&mut self, element: &Element) {
+        match element.name.as_str() {
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                self.flush();
+                let text = collapse_whitespace(&element.text());
+                if !text.is_empty() {
+                    self.blocks.push(Block::Heading {
+                        level: name[1..].parse().unwrap_or(1),
+                        text,
+                    });
+                }
+            }
+            name @ ("ul" | "ol") => {
+                self.flush();
+                let mut items = Vec::new();
+                for li in element.elements("li") {
+                    let text = collapse_whitespace(&li.text());
+                    match li
+                        .element("en-todo")
+                        .or_else(|| li.elements("div").find_map(|div| div.element("en-todo")))
+                    {
+                        Some(todo) => push_checklist_item(
+                            &mut self.blocks,
+                            text,
+                            todo.attribute("checked") == Some("true"),
+                        ),
+                        None => items.push(text),
+                    }
+                }
+                if !items.is_empty() {
+                    self.blocks.push(Block::List {
+                        ordered: name == "ol",
+                        items,
+                    });
+                }
+            }
+            "pre" => {
+                self.flush();
+                self.blocks.push(Block::Code {
+                    language: None,
+                    code: element.text(),
+                });
+            }
+            "en-media" => {
+                self.flush();
+                if let Some(id) = element.attribute("hash").and_then(|h| self.media.get(h)) {
+                    self.blocks.push(Block::Image {
+                        attachment_id: *id,
+                        caption: None,
+                    });
+                }
+            }
+            "br" | "hr" => self.flush(),
+            "en-todo" => {}
+            "td" | "th" => {
+                self.inline(element);
+                self.text.push(' ');
+            }
+            "en-note" | "div" | "p" | "blockquote" | "section" | "article" | "center" | "li"
+            | "table" | "thead" | "tbody" | "tfoot" | "tr" | "dl" | "dt" | "dd" => {
+                self.flush();
+                let mut nodes = content_nodes(element);
+                match (nodes.next(), nodes.next()) {
+                    // A line starting with a checkbox is a checklist item
+                    (Some(Node::Element(first)), _) if first.name == "en-todo" => {
+                        let checked = first.attribute("checked") == Some("true");
+                        let text = collapse_whitespace(&element.text());
+                        push_checklist_item(&mut self.blocks, text, checked);
+                        return;
+                    }
+                    // A line holding only a link is a link block
+                    (Some(Node::Element(a)), None) if a.name == "a" => {
+                        if let Some(url) = a.attribute("href").filter(|u| validate_link(u).is_ok())
+                        {
+                            let text = collapse_whitespace(&a.text());
+                            self.blocks.push(Block::Link {
+                                url: url.to_string(),
+                                text: (!text.is_empty() && text != url).then_some(text),
+                            });
+                            return;
+                        }
+                    }
+                    _ => {}
+                }
+                self.inline(element);
+                self.flush();
+            }
+            _ => self.inline(element),
+        }
+    }
+
+    /// Adds the text of an element to the current paragraph; nested blocks end it.
+    fn inline(&mut self, element: &Element) {
+        for node in &element.children {
+            match node {
+                Node::Text(text) => self.text.push_str(text),
+                Node::Element(child) => self.block(child),
+            }
+        }
+    }
+}
+
+// --- Google Keep ---
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepNote {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    text_content: String,
+    #[serde(default)]
+    list_content: Vec<KeepListItem>,
+    #[serde(default)]
+    labels: Vec<KeepLabel>,
+    #[serde(default)]
+    is_trashed: bool,
+    #[serde(default)]
+    is_archived: bool,
+    #[serde(default)]
+    is_pinned: bool,
+    created_timestamp_usec: Option<i64>,
+    user_edited_timestamp_usec: Option<i64>,
+    #[serde(default)]
+    attachments: Vec<KeepAttachment>,
+    #[serde(default)]
+    annotations: Vec<KeepAnnotation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepListItem {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    is_checked: bool,
+}
+
+#[derive(Deserialize)]
+struct KeepLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepAttachment {
+    file_path: String,
+}
+
+#[derive(Deserialize)]
+struct KeepAnnotation {
+    url: Option<String>,
+    title: Option<String>,
+}
+
+fn parse_keep_upload(data: &[u8], emit: &mut Emit) -> Result<(), String> {
+    if !is_zip(data) {
+        let note = serde_json::from_slice::<KeepNote>(data)
+            .map_err(|e| format!("Not a Google Keep note: {}", e))?;
+        if let Some(item) = keep_note("note.json", note, None)?
+            && emit(ImportEvent::Found(1))
+        {
+            emit(ImportEvent::Item(item));
+        }
+        return Ok(());
+    }
+    let mut archive = Archive::open(data)?;
+    for path in archive.paths_ending_with(".json") {
+        // Takeout archives hold other JSON files too; only notes have text or list content
+        let item = match archive
+            .read(&path)?
+            .map(|bytes| serde_json::from_slice(&bytes))
+        {
+            None => Some(Err(item_error(&path, "File is too large"))),
+            Some(Err(e)) => Some(Err(item_error(&path, format!("Invalid JSON: {}", e)))),
+            Some(Ok(value)) => {
+                let value: serde_json::Value = value;
+                if value.get("textContent").is_none() && value.get("listContent").is_none() {
+                    continue;
+                }
+                match serde_json::from_value::<KeepNote>(value) {
+                    Ok(note) => keep_note(&path, note, Some(&mut archive))?,
+                    Err(e) => Some(Err(item_error(
+                        &path,
+                        format!("Not a Google Keep note: {}", e),
+                    ))),
+                }
+            }
+        };
+        if let Some(item) = item
+            && !(emit(ImportEvent::Found(1)) && emit(ImportEvent::Item(item)))
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Converts a Keep note, reading its attachments from `archive`; notes in Keep's trash
+/// are skipped.
+fn keep_note(
+    path: &str,
+    keep: KeepNote,
+    mut archive: Option<&mut Archive>,
+) -> Result<Option<ImportItem>, String> {
+    if keep.is_trashed {
+        return Ok(None);
+    }
+    let (folders, _) = split_path(path);
+    let mut note = ImportedNote::new(path.to_string(), Vec::new(), keep.title);
+
+    let mut blocks = NoteContent::from_plain_text(&keep.text_content).blocks;
+    for item in keep.list_content {
+        push_checklist_item(&mut blocks, item.text, item.is_checked);
+    }
+    for annotation in keep.annotations {
+        if let Some(url) = annotation.url.filter(|u| validate_link(u).is_ok()) {
+            blocks.push(Block::Link {
+                url,
+                text: annotation.title.filter(|t| !t.is_empty()),
+            });
+        }
+    }
+    for attachment in keep.attachments {
+        // Takeout sometimes names .jpg files .jpeg in the note
+        let candidates = [
+            attachment.file_path.clone(),
+            attachment.file_path.replace(".jpeg", ".jpg"),
+            attachment.file_path.replace(".jpg", ".jpeg"),
+        ];
+        let Some((archive, file)) = archive.as_deref_mut().and_then(|archive| {
+            let file = candidates
+                .iter()
+                .filter_map(|name| resolve_path(&folders, name))
+                .find(|file| archive.contains(file))?;
+            Some((archive, file))
+        }) else {
+            note.warnings.push(format!(
+                "Attachment {} not found in the archive",
+                attachment.file_path
+            ));
+            continue;
+        };
+        let Some(data) = archive.read(&file)? else {
+            note.warnings
+                .push(format!("Attachment {} is too large", attachment.file_path));
+            continue;
+        };
+        let id = Uuid::new_v4();
+        note.attachments.push(ImportedFile {
+            id,
+            name: split_path(&file).1.to_string(),
+            data,
+        });
+        blocks.push(Block::Image {
+            attachment_id: id,
+            caption: None,
+        });
+    }
+
+    note.content = NoteContent {
+        version: CONTENT_VERSION,
+        blocks,
+    };
+    note.tags = keep.labels.into_iter().map(|l| l.name).collect();
+    note.created_at = keep
+        .created_timestamp_usec
+        .and_then(DateTime::from_timestamp_micros);
+    note.updated_at = keep
+        .user_edited_timestamp_usec
+        .and_then(DateTime::from_timestamp_micros);
+    note.is_pinned = keep.is_pinned;
+    note.is_archived = keep.is_archived;
+    Ok(Some(Ok(note)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// All items of an upload; checks that they match the number of items found.
+    fn parse(format: ImportFormat, data: &[u8]) -> Result<Vec<ImportItem>, String> {
+        let mut found = 0;
+        let mut items = Vec::new();
+        parse_upload(format, data, &mut |event| {
+            match event {
+                ImportEvent::Found(count) => found += count,
+                ImportEvent::Item(item) => items.push(item),
+            }
+            true
+        })?;
+        assert_eq!(found, items.len());
+        Ok(items)
+    }
+
+    fn notes(items: Vec<ImportItem>) -> Vec<ImportedNote> {
+        items.into_iter().map(Result::unwrap).collect()
+    }
+
+    fn checklist(items: &[(&str, bool)]) -> Block {
+        Block::Checklist {
+            items: items
+                .iter()
+                .map(|(text, checked)| ChecklistItem {
+                    text: text.to_string(),
+                    checked: *checked,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn front_matter_with_quoted_values_and_lists() {
+        let (yaml, body) = split_front_matter(
+            "---\ntitle: \"Plan: \\\"A\\\"\"\ntags: [work, \"a, b\", '#home']\npinned: true\n---\nBody\n",
+        );
+        assert_eq!(body, "Body\n");
+        let meta = parse_front_matter(yaml.unwrap());
+        assert_eq!(meta.title.as_deref(), Some("Plan: \"A\""));
+        assert_eq!(meta.tags, ["work", "a, b", "home"]);
+        assert!(meta.pinned);
+        assert!(!meta.archived);
+
+        let meta = parse_front_matter("tags:\n  - 'it''s'\n  - two\ncreated: 2024-03-01\n");
+        assert_eq!(meta.tags, ["it's", "two"]);
+        assert_eq!(
+            meta.created.map(|d| d.to_rfc3339()).as_deref(),
+            Some("2024-03-01T00:00:00+00:00")
+        );
+        assert_eq!(parse_front_matter("tags: a, b").tags, ["a", "b"]);
+        // Without a closing line there is no front matter
+        assert_eq!(split_front_matter("---\ntitle: x\n").0, None);
+    }
+
+    #[test]
+    fn longer_code_fences_contain_shorter_ones() {
+        let blocks = markdown_blocks(
+            "````md\n```rust\nfn main() {}\n```\n````\nafter",
+            &mut |_, _| None,
+        );
+        assert_eq!(
+            blocks,
+            [
+                Block::Code {
+                    language: Some("md".into()),
+                    code: "```rust\nfn main() {}\n```".into(),
+                },
+                Block::Paragraph {
+                    text: "after".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn task_lists_become_checklists() {
+        let blocks = markdown_blocks(
+            "- [ ] buy milk\n- [x] call mom\n* [X] pay\n- plain\n\n1. first\n2) second",
+            &mut |_, _| None,
+        );
+        assert_eq!(
+            blocks,
+            [
+                checklist(&[("buy milk", false), ("call mom", true), ("pay", true)]),
+                Block::List {
+                    ordered: false,
+                    items: vec!["plain".into()],
+                },
+                Block::List {
+                    ordered: true,
+                    items: vec!["first".into(), "second".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn paths_resolve_within_the_archive() {
+        let dir = ["a".to_string(), "b".to_string()];
+        assert_eq!(
+            resolve_path(&dir, "../img/p.png").as_deref(),
+            Some("a/img/p.png")
+        );
+        assert_eq!(
+            resolve_path(&dir, "./c/../d.png").as_deref(),
+            Some("a/b/d.png")
+        );
+        assert_eq!(resolve_path(&dir, "../../x.png").as_deref(), Some("x.png"));
+        assert_eq!(resolve_path(&dir, "../../../x.png"), None);
+    }
+
+    #[test]
+    fn markdown_zip_skips_hidden_entries_and_attaches_images() {
+        let data = zip(&[
+            (
+                "Notes/Trip.md",
+                b"# Trip\n\n![Beach](../img/beach%20day.png)\n![](missing.png)",
+            ),
+            ("img/beach day.png", b"png"),
+            ("__MACOSX/Notes/._Trip.md", b"junk"),
+            ("Notes/.hidden.md", b"secret"),
+            (".obsidian/app.md", b"config"),
+        ]);
+        let notes = notes(parse(ImportFormat::Markdown, &data).unwrap());
+        assert_eq!(notes.len(), 1);
+        let note = &notes[0];
+        assert_eq!(note.item, "Notes/Trip.md");
+        assert_eq!(note.folders, ["Notes"]);
+        assert_eq!(note.title, "Trip");
+        assert_eq!(note.attachments.len(), 1);
+        assert_eq!(note.attachments[0].name, "beach day.png");
+        assert_eq!(note.attachments[0].data, b"png");
+        assert_eq!(
+            note.warnings,
+            ["Image missing.png not found in the archive"]
+        );
+        assert_eq!(
+            note.content.blocks,
+            [
+                Block::Heading {
+                    level: 1,
+                    text: "Trip".into(),
+                },
+                Block::Image {
+                    attachment_id: note.attachments[0].id,
+                    caption: Some("Beach".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parsing_stops_when_the_items_are_no_longer_wanted() {
+        let data = zip(&[("a.md", b"a"), ("b.md", b"b"), ("c.md", b"c")]);
+        let mut items = Vec::new();
+        parse_upload(ImportFormat::Markdown, &data, &mut |event| match event {
+            ImportEvent::Found(count) => {
+                assert_eq!(count, 3);
+                true
+            }
+            ImportEvent::Item(item) => {
+                items.push(item.unwrap().title);
+                false
+            }
+        })
+        .unwrap();
+        assert_eq!(items, ["a"]);
+    }
+
+    #[test]
+    fn enex_note_with_entities_cdata_and_media() {
+        let image = b"image bytes";
+        let enex = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-export SYSTEM "http://xml.evernote.com/pub/evernote-export4.dtd">
+<en-export>
+<note>
+  <title>Fish &amp; chips</title>
+  <created>20240301T101500Z</created>
+  <tag>food</tag>
+  <content><![CDATA[<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd">
+<en-note><h2>Shopping</h2><div>Salt &amp; vinegar&nbsp;please</div>
+<div><en-todo checked="true"/>fish</div><div><en-todo/>chips</div>
+<en-media hash="{}" type="image/png"/></en-note>]]></content>
+  <resource><data encoding="base64">{}</data><mime>image/png</mime></resource>
+</note>
+</en-export>"#,
+            hex::encode(Md5::digest(image)),
+            STANDARD.encode(image)
+        );
+        let notes = notes(parse(ImportFormat::Enex, enex.as_bytes()).unwrap());
+        assert_eq!(notes.len(), 1);
+        let note = &notes[0];
+        assert_eq!(note.title, "Fish & chips");
+        assert_eq!(note.tags, ["food"]);
+        assert_eq!(
+            note.created_at.map(|d| d.to_rfc3339()).as_deref(),
+            Some("2024-03-01T10:15:00+00:00")
+        );
+        assert_eq!(note.attachments.len(), 1);
+        assert_eq!(note.attachments[0].name, "attachment-1.png");
+        assert_eq!(note.attachments[0].data, image);
+        assert_eq!(
+            note.content.blocks,
+            [
+                Block::Heading {
+                    level: 2,
+                    text: "Shopping".into(),
+                },
+                Block::Paragraph {
+                    text: "Salt & vinegar please".into(),
+                },
+                checklist(&[("fish", true), ("chips", false)]),
+                Block::Image {
+                    attachment_id: note.attachments[0].id,
+                    caption: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn deeply_nested_enex_content_is_rejected() {
+        let enex = |depth: usize| {
+            format!(
+                "<en-export><note><title>Deep</title><content><![CDATA[<en-note>{}x{}</en-note>]]></content></note></en-export>",
+                "<div>".repeat(depth),
+                "</div>".repeat(depth)
+            )
+        };
+        let items = parse(ImportFormat::Enex, enex(xml::MAX_DEPTH - 1).as_bytes()).unwrap();
+        assert_eq!(
+            items[0].as_ref().unwrap().content.blocks,
+            [Block::Paragraph { text: "x".into() }]
+        );
+
+        let items = parse(ImportFormat::Enex, enex(200_000).as_bytes()).unwrap();
+        assert_eq!(
+            items[0].as_ref().unwrap_err().error,
+            "Invalid note content: Document is nested too deeply"
+        );
+        let nested = format!("{}<en-export>", "<a>".repeat(200_000));
+        assert_eq!(
+            parse(ImportFormat::Enex, nested.as_bytes()).unwrap_err(),
+            "Invalid ENEX file: Document is nested too deeply"
+        );
+    }
+
+    #[test]
+    fn keep_note_with_list_labels_and_attachment() {
+        let json = br#"{
+            "title": "Groceries",
+            "textContent": "Weekly",
+            "listContent": [
+                {"text": "eggs", "isChecked": true},
+                {"text": "bread", "isChecked": false}
+            ],
+            "labels": [{"name": "home"}],
+            "isPinned": true,
+            "isTrashed": false,
+            "createdTimestampUsec": 1709288100000000,
+            "attachments": [{"filePath": "photo.jpeg", "mimetype": "image/jpeg"}],
+            "annotations": [{"url": "https://example.com", "title": "Recipe", "source": "WEBLINK"}]
+        }"#;
+        let trashed = br#"{"title": "Old", "textContent": "x", "isTrashed": true}"#;
+        let data = zip(&[
+            ("Takeout/Keep/Groceries.json", json),
+            ("Takeout/Keep/Old.json", trashed),
+            ("Takeout/Keep/Labels.json", br#"{"labels": []}"#),
+            ("Takeout/Keep/photo.jpg", b"jpg"),
+        ]);
+        let notes = notes(parse(ImportFormat::Keep, &data).unwrap());
+        assert_eq!(notes.len(), 1);
+        let note = &notes[0];
+        assert_eq!(note.title, "Groceries");
+        assert_eq!(note.tags, ["home"]);
+        assert!(note.is_pinned);
+        assert_eq!(
+            note.created_at.map(|d| d.to_rfc3339()).as_deref(),
+            Some("2024-03-01T10:15:00+00:00")
+        );
+        assert_eq!(note.attachments.len(), 1);
+        assert_eq!(note.attachments[0].name, "photo.jpg");
+        assert_eq!(
+            note.content.blocks,
+            [
+                Block::Paragraph {
+                    text: "Weekly".into(),
+                },
+                checklist(&[("eggs", true), ("bread", false)]),
+                Block::Link {
+                    url: "https://example.com".into(),
+                    text: Some("Recipe".into()),
+                },
+                Block::Image {
+                    attachment_id: note.attachments[0].id,
+                    caption: None,
+                },
+            ]
+        );
+    }
+}
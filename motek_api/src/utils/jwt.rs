@@ -15,39 +15,31 @@ impl FromRequestParts<AppState> for AuthClaims {
 
     /// Extracts and verifies JWT claims from the Authorization header.
     /// Logs extraction and validation results.
-    fn from_request_parts(
+    async fn from_request_parts(
         parts: &mut Parts,
         state: &AppState,
-    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
-        async move {
-            let jwt_secret = state
-                .config
-                .jwt_secret
-                .as_deref()
-                .ok_or((StatusCode::UNAUTHORIZED, "Missing JWT secret"))?;
+    ) -> Result<Self, Self::Rejection> {
+        let auth_header = parts
+            .headers
+            .get("authorization")
+            .and_then(|h| h.to_str().ok())
+            .ok_or((StatusCode::UNAUTHORIZED, "Missing Authorization header"))?;
 
-            let auth_header = parts
-                .headers
-                .get("authorization")
-                .and_then(|h| h.to_str().ok())
-                .ok_or((StatusCode::UNAUTHORIZED, "Missing Authorization header"))?;
+        let token = auth_header
+            .strip_prefix("Bearer ")
+            .ok_or((StatusCode::UNAUTHORIZED, "Wrong token format"))?;
 
-            let token = auth_header
-                .strip_prefix("Bearer ")
-                .ok_or((StatusCode::UNAUTHORIZED, "Wrong token format"))?;
+        let claims = match verify_jwt(token, &state.jwt_keys) {
+            Ok(c) => {
+                info!("JWT successfully verified for subject={}", c.sub);
+                c
+            }
+            Err(e) => {
+                error!("JWT verification failed: {}", e);
+                return Err((StatusCode::UNAUTHORIZED, "Invalid token"));
+            }
+        };
 
-            let claims = match verify_jwt(token, jwt_secret) {
-                Ok(c) => {
-                    info!("JWT successfully verified for subject={}", c.sub);
-                    c
-                }
-                Err(e) => {
-                    error!("JWT verification failed: {}", e);
-                    return Err((StatusCode::UNAUTHORIZED, "Invalid token"));
-                }
-            };
-
-            Ok(AuthClaims(claims))
-        }
+        Ok(AuthClaims(claims))
     }
 }
@@ -0,0 +1,229 @@
+//! JWT signing keys.
+//! Supports HS256 secrets as well as RS256/EdDSA key pairs identified by `kid`,
+//! with one active signing key and any number of retired, verify-only keys.
+
+use crate::utils::config_loader::{Config, JwtKeyConfig};
+use anyhow::{Context, anyhow, bail};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+    errors::{Error, ErrorKind},
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+};
+use rsa::{
+    RsaPrivateKey, RsaPublicKey,
+    pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey},
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+    traits::PublicKeyParts,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use std::fs;
+use tracing::{info, warn};
+
+/// A single key of the key ring.
+struct JwtKey {
+    /// Key id written to / matched against the token header (None = legacy secret)
+    kid: Option<String>,
+    algorithm: Algorithm,
+    /// Present only for keys we can sign with
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    /// Public JWK, published for asymmetric keys only
+    jwk: Option<Jwk>,
+}
+
+/// All keys used to sign and verify tokens issued by this service.
+pub struct JwtKeys {
+    keys: Vec<JwtKey>,
+    active: usize,
+}
+
+impl JwtKeys {
+    /// Builds the key ring from config.
+    /// Without a `[jwt]` section the legacy `jwt_secret` (HS256, no `kid`) signs tokens.
+    /// With it, `jwt_secret` is still accepted for verification so tokens issued
+    /// before the switch stay valid until they expire.
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let mut keys = Vec::new();
+
+        let legacy_secret = match (&config.jwt_secret, &config.jwt) {
+            (Some(secret), _) => Some(secret.clone()),
+            (None, None) => {
+                warn!("No jwt_secret or [jwt] keys configured, using the development secret");
+                Some("sekret_dev".to_string())
+            }
+            (None, Some(_)) => None,
+        };
+        if let Some(secret) = legacy_secret {
+            keys.push(JwtKey {
+                kid: None,
+                algorithm: Algorithm::HS256,
+                encoding: Some(EncodingKey::from_secret(secret.as_bytes())),
+                decoding: DecodingKey::from_secret(secret.as_bytes()),
+                jwk: None,
+            });
+        }
+
+        let Some(jwt) = &config.jwt else {
+            return Ok(Self { keys, active: 0 });
+        };
+
+        for key in &jwt.keys {
+            keys.push(load_key(key).with_context(|| format!("Loading JWT key '{}'", key.kid))?);
+        }
+        let active = keys
+            .iter()
+            .position(|k| k.kid.as_deref() == Some(jwt.active_kid.as_str()))
+            .ok_or_else(|| anyhow!("Active JWT key '{}' is not configured", jwt.active_kid))?;
+        if keys[active].encoding.is_none() {
+            bail!("Active JWT key '{}' has no private key", jwt.active_kid);
+        }
+
+        info!(
+            "Loaded {} JWT keys, signing with kid={}",
+            keys.len(),
+            jwt.active_kid
+        );
+        Ok(Self { keys, active })
+    }
+
+    /// Signs claims with the active key.
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, Error> {
+        let key = &self.keys[self.active];
+        let mut header = Header::new(key.algorithm);
+        header.kid = key.kid.clone();
+        let encoding = key
+            .encoding
+            .as_ref()
+            .ok_or_else(|| Error::from(ErrorKind::InvalidKeyFormat))?;
+        encode(&header, claims, encoding)
+    }
+
+    /// Verifies a token with the key named in its header and returns the claims.
+    /// Tokens without `kid` are checked against the legacy secret only.
+    pub fn decode<T: DeserializeOwned>(&self, token: &str) -> Result<T, Error> {
+        let header = decode_header(token)?;
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.kid == header.kid)
+            .ok_or_else(|| Error::from(ErrorKind::InvalidToken))?;
+        decode::<T>(token, &key.decoding, &Validation::new(key.algorithm)).map(|d| d.claims)
+    }
+
+    /// Public keys (active and retired) as a JWK set.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|k| k.jwk.clone()).collect(),
+        }
+    }
+}
+
+/// Loads one configured key.
+/// The public half is derived from the private key when it is available,
+/// so retired keys only need `public_key_path`.
+fn load_key(config: &JwtKeyConfig) -> anyhow::Result<JwtKey> {
+    let private_pem = config
+        .private_key_path
+        .as_deref()
+        .map(fs::read_to_string)
+        .transpose()?;
+    let public_pem = config
+        .public_key_path
+        .as_deref()
+        .map(fs::read_to_string)
+        .transpose()?;
+
+    match config.algorithm {
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+            let secret = config
+                .secret
+                .as_deref()
+                .ok_or_else(|| anyhow!("HMAC keys need a secret"))?;
+            Ok(JwtKey {
+                kid: Some(config.kid.clone()),
+                algorithm: config.algorithm,
+                encoding: Some(EncodingKey::from_secret(secret.as_bytes())),
+                decoding: DecodingKey::from_secret(secret.as_bytes()),
+                jwk: None,
+            })
+        }
+        Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+            let public = match (&private_pem, &public_pem) {
+                (Some(pem), _) => RsaPrivateKey::from_pkcs8_pem(pem)
+                    .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))?
+                    .to_public_key(),
+                (None, Some(pem)) => RsaPublicKey::from_public_key_pem(pem)
+                    .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))?,
+                (None, None) => bail!("RSA keys need private_key_path or public_key_path"),
+            };
+            let n = URL_SAFE_NO_PAD.encode(public.n().to_bytes_be());
+            let e = URL_SAFE_NO_PAD.encode(public.e().to_bytes_be());
+            let encoding = private_pem
+                .as_deref()
+                .map(|pem| EncodingKey::from_rsa_pem(pem.as_bytes()))
+                .transpose()?;
+            Ok(JwtKey {
+                kid: Some(config.kid.clone()),
+                algorithm: config.algorithm,
+                encoding,
+                decoding: DecodingKey::from_rsa_components(&n, &e)?,
+                jwk: Some(Jwk {
+                    common: public_jwk_common(config),
+                    algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
+                        key_type: RSAKeyType::RSA,
+                        n,
+                        e,
+                    }),
+                }),
+            })
+        }
+        Algorithm::EdDSA => {
+            let public = match (&private_pem, &public_pem) {
+                (Some(pem), _) => SigningKey::from_pkcs8_pem(pem)?.verifying_key(),
+                (None, Some(pem)) => VerifyingKey::from_public_key_pem(pem)?,
+                (None, None) => bail!("EdDSA keys need private_key_path or public_key_path"),
+            };
+            let x = URL_SAFE_NO_PAD.encode(public.to_bytes());
+            let encoding = private_pem
+                .as_deref()
+                .map(|pem| EncodingKey::from_ed_pem(pem.as_bytes()))
+                .transpose()?;
+            Ok(JwtKey {
+                kid: Some(config.kid.clone()),
+                algorithm: config.algorithm,
+                encoding,
+                decoding: DecodingKey::from_ed_components(&x)?,
+                jwk: Some(Jwk {
+                    common: public_jwk_common(config),
+                    algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x,
+                    }),
+                }),
+            })
+        }
+        other => bail!("Unsupported JWT algorithm {:?}", other),
+    }
+}
+
+/// JWK fields shared by all published keys.
+fn public_jwk_common(config: &JwtKeyConfig) -> CommonParameters {
+    CommonParameters {
+        public_key_use: Some(PublicKeyUse::Signature),
+        key_algorithm: match config.algorithm {
+            Algorithm::RS256 => Some(KeyAlgorithm::RS256),
+            Algorithm::RS384 => Some(KeyAlgorithm::RS384),
+            Algorithm::RS512 => Some(KeyAlgorithm::RS512),
+            Algorithm::EdDSA => Some(KeyAlgorithm::EdDSA),
+            _ => None,
+        },
+        key_id: Some(config.kid.clone()),
+        ..Default::default()
+    }
+}
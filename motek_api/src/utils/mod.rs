@@ -1,7 +1,17 @@
 //utils/mod.rs
 pub mod auth;
 pub mod config_loader;
+pub mod export;
 pub mod extractors;
-pub mod ip_limiter;
+pub mod import;
 pub mod jwt;
+pub mod jwt_keys;
+pub mod oidc;
+pub mod password;
+pub mod platform;
+pub mod rate_limit;
+pub mod revocation;
+pub mod scope;
+pub mod totp;
 pub mod validators;
+pub mod xml;
@@ -0,0 +1,61 @@
+use crate::utils::config_loader::Argon2Config;
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+    password_hash::{SaltString, rand_core::OsRng},
+};
+
+/// Builds an Argon2id hasher with the configured cost parameters.
+fn argon2(config: &Argon2Config) -> Result<Argon2<'static>, argon2::Error> {
+    let params = Params::new(
+        config.memory_kib,
+        config.iterations,
+        config.parallelism,
+        None,
+    )?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+}
+
+/// Hashes a plain-text password for storage in `users.password` (Argon2id, PHC string).
+pub fn hash_password(
+    password: &str,
+    config: &Argon2Config,
+) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = argon2(config)?.hash_password(password.as_bytes(), &salt)?;
+    Ok(hash.to_string())
+}
+
+/// Checks a plain-text password against a stored hash.
+/// Accepts Argon2 PHC strings and legacy bcrypt hashes; malformed hashes are a mismatch.
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    if password_hash.starts_with("$argon2") {
+        PasswordHash::new(password_hash)
+            .map(|parsed| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &parsed)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    } else {
+        bcrypt::verify(password, password_hash).unwrap_or(false)
+    }
+}
+
+/// Returns true if a stored hash is not Argon2id with the configured parameters
+/// (legacy bcrypt, or parameters changed since it was created).
+pub fn needs_rehash(password_hash: &str, config: &Argon2Config) -> bool {
+    let Ok(parsed) = PasswordHash::new(password_hash) else {
+        return true;
+    };
+    if parsed.algorithm != Algorithm::Argon2id.ident() {
+        return true;
+    }
+    match Params::try_from(&parsed) {
+        Ok(params) => {
+            params.m_cost() != config.memory_kib
+                || params.t_cost() != config.iterations
+                || params.p_cost() != config.parallelism
+        }
+        Err(_) => true,
+    }
+}
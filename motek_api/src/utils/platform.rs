@@ -0,0 +1,58 @@
+use crate::models::platform::Platform;
+use axum::{
+    body::Body,
+    extract::{FromRequestParts, State},
+    http::{Request, StatusCode, request::Parts},
+    middleware::Next,
+    response::IntoResponse,
+};
+use tracing::info;
+
+/// Extractor for the platform of the authenticated session.
+/// The platform is put into request extensions by `auth_middleware`.
+pub struct AuthPlatform(pub Platform);
+
+impl<S> FromRequestParts<S> for AuthPlatform
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Platform>()
+            .copied()
+            .map(AuthPlatform)
+            .ok_or((StatusCode::UNAUTHORIZED, "No authenticated platform found"))
+    }
+}
+
+/// Set of platforms allowed on a router, used as state of `require_platform`.
+#[derive(Clone, Copy)]
+pub struct AllowedPlatforms(pub &'static [Platform]);
+
+/// Middleware restricting a router to some platforms. Must run after `auth_middleware`:
+///
+/// ```ignore
+/// router.route_layer(middleware::from_fn_with_state(
+///     AllowedPlatforms(&[Platform::Web]),
+///     require_platform,
+/// ))
+/// ```
+pub async fn require_platform(
+    State(AllowedPlatforms(allowed)): State<AllowedPlatforms>,
+    AuthPlatform(platform): AuthPlatform,
+    req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    if !allowed.contains(&platform) {
+        info!(
+            "Access to {} denied for platform '{}'",
+            req.uri().path(),
+            platform
+        );
+        return Err((StatusCode::FORBIDDEN, "Not available on this platform"));
+    }
+    Ok(next.run(req).await)
+}
@@ -0,0 +1,452 @@
+use crate::state::AppState;
+use crate::utils::config_loader::{Config, RateLimitRule};
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, HeaderName, HeaderValue, Request, StatusCode, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Route group with its own limit, configured under `[rate_limit.groups.<name>]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitGroup {
+    /// POST /api/auth/register, per IP
+    Register,
+    /// /api/auth/login, per IP
+    Login,
+    /// /api/auth/login/mfa (second step of a 2FA login), per IP
+    Mfa,
+    /// Other /api/auth endpoints, per IP
+    Auth,
+    /// /api/public, per IP
+    Public,
+    /// Protected /api endpoints, per authenticated user
+    Api,
+}
+
+impl RateLimitGroup {
+    /// Group of a public request path.
+    fn for_public_path(path: &str) -> Self {
+        if path == "/api/auth/register" {
+            RateLimitGroup::Register
+        } else if path == "/api/auth/login" {
+            RateLimitGroup::Login
+        } else if path == "/api/auth/login/mfa" {
+            RateLimitGroup::Mfa
+        } else if path.starts_with("/api/auth") {
+            RateLimitGroup::Auth
+        } else {
+            RateLimitGroup::Public
+        }
+    }
+}
+
+/// Who a bucket belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum ClientKey {
+    Ip(IpAddr),
+    User(Uuid),
+}
+
+/// Token bucket: `tokens` refill continuously up to the rule's `requests`.
+#[derive(Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Outcome of a rate limit check, used for the `RateLimit-*` headers.
+struct Decision {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    /// Seconds until the bucket is full again
+    reset: u64,
+    /// Seconds until the next request is allowed (when denied)
+    retry_after: u64,
+}
+
+/// Token bucket rate limiter shared by all route groups.
+/// Memory is bounded by `max_entries`; when full, idle buckets are evicted first, in
+/// batches so that most new clients do not pay for a sweep.
+pub struct RateLimiter {
+    rules: HashMap<RateLimitGroup, RateLimitRule>,
+    trusted_proxies: Vec<IpAddr>,
+    max_entries: usize,
+    buckets: Mutex<HashMap<(RateLimitGroup, ClientKey), Bucket>>,
+}
+
+impl RateLimiter {
+    /// Builds the limiter from `[rate_limit]`; groups without a rule get built-in defaults.
+    /// `register_ip_limit_per_hour`/`login_ip_limit_per_hour` are still honoured.
+    pub fn from_config(config: &Config) -> Self {
+        let rl = &config.rate_limit;
+        let defaults = [
+            (
+                RateLimitGroup::Register,
+                RateLimitRule {
+                    requests: config.register_ip_limit_per_hour.unwrap_or(1),
+                    per_seconds: 60 * 60,
+                },
+            ),
+            (
+                RateLimitGroup::Login,
+                RateLimitRule {
+                    requests: config.login_ip_limit_per_hour.unwrap_or(1),
+                    per_seconds: 60 * 60,
+                },
+            ),
+            (
+                RateLimitGroup::Mfa,
+                RateLimitRule {
+                    requests: 10,
+                    per_seconds: 5 * 60,
+                },
+            ),
+            (
+                RateLimitGroup::Auth,
+                RateLimitRule {
+                    requests: 30,
+                    per_seconds: 60,
+                },
+            ),
+            (
+                RateLimitGroup::Public,
+                RateLimitRule {
+                    requests: 60,
+                    per_seconds: 60,
+                },
+            ),
+            (
+                RateLimitGroup::Api,
+                RateLimitRule {
+                    requests: 300,
+                    per_seconds: 60,
+                },
+            ),
+        ];
+        let mut rules: HashMap<_, _> = defaults.into_iter().collect();
+        rules.extend(rl.groups.iter().map(|(g, r)| (*g, r.clone())));
+
+        Self {
+            rules,
+            trusted_proxies: rl.trusted_proxies.clone(),
+            max_entries: rl.max_entries.unwrap_or(100_000),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Client IP address. `X-Forwarded-For` is only trusted when the peer is a trusted
+    /// proxy; the rightmost address that is not a trusted proxy is the client.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.trusted_proxies.contains(&peer) {
+            return peer;
+        }
+        let forwarded: Vec<IpAddr> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|ip| ip.trim().parse().ok())
+            .collect();
+        forwarded
+            .iter()
+            .rev()
+            .find(|ip| !self.trusted_proxies.contains(ip))
+            .or(forwarded.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+
+    /// Takes one token from the client's bucket in a group.
+    async fn check(&self, group: RateLimitGroup, key: ClientKey) -> Decision {
+        let rule = &self.rules[&group];
+        let capacity = rule.requests.max(1) as f64;
+        let rate = capacity / rule.per_seconds.max(1) as f64;
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().await;
+        if buckets.len() >= self.max_entries && !buckets.contains_key(&(group, key)) {
+            self.evict(&mut buckets, now);
+        }
+        let bucket = buckets.entry((group, key)).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        Decision {
+            allowed,
+            limit: capacity as u32,
+            remaining: bucket.tokens.floor() as u32,
+            reset: ((capacity - bucket.tokens) / rate).ceil() as u64,
+            retry_after: ((1.0 - bucket.tokens).max(0.0) / rate).ceil() as u64,
+        }
+    }
+
+    /// Frees room for new buckets: drops buckets that have refilled completely
+    /// (indistinguishable from a new one), then the least recently used ones until at
+    /// most 90% of `max_entries` are left.
+    fn evict(&self, buckets: &mut HashMap<(RateLimitGroup, ClientKey), Bucket>, now: Instant) {
+        let before = buckets.len();
+        buckets.retain(|(group, _), b| {
+            let rule = &self.rules[group];
+            let full_after =
+                rule.per_seconds as f64 * (1.0 - b.tokens / rule.requests.max(1) as f64);
+            now.duration_since(b.updated).as_secs_f64() < full_after
+        });
+        let keep = self
+            .max_entries
+            .saturating_sub((self.max_entries / 10).max(1));
+        if buckets.len() > keep {
+            let mut by_age: Vec<_> = buckets.iter().map(|(k, b)| (b.updated, *k)).collect();
+            let excess = buckets.len() - keep;
+            by_age.select_nth_unstable_by_key(excess - 1, |(updated, _)| *updated);
+            for (_, key) in &by_age[..excess] {
+                buckets.remove(key);
+            }
+        }
+        let evicted = before - buckets.len();
+        if evicted > 0 {
+            info!("Rate limiter evicted {} buckets", evicted);
+        }
+    }
+}
+
+/// Rate limiting middleware for all routes. Public routes are limited per client IP,
+/// protected routes per authenticated user, so it must run after `auth_middleware` there.
+/// Adds `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` headers, and
+/// answers 429 with `Retry-After` when the limit is exceeded.
+pub async fn rate_limit(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let limiter = &state.rate_limiter;
+    let (group, key) = match req.extensions().get::<Uuid>() {
+        Some(user_id) => (RateLimitGroup::Api, ClientKey::User(*user_id)),
+        None => (
+            RateLimitGroup::for_public_path(req.uri().path()),
+            ClientKey::Ip(limiter.client_ip(addr.ip(), req.headers())),
+        ),
+    };
+
+    let decision = limiter.check(group, key).await;
+    let mut response = if decision.allowed {
+        next.run(req).await
+    } else {
+        warn!(
+            "Rate limit exceeded for {:?} in group {:?} on {}",
+            key,
+            group,
+            req.uri().path()
+        );
+        let mut response = (StatusCode::TOO_MANY_REQUESTS, "Too many requests").into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(decision.retry_after.max(1)));
+        response
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static("ratelimit-limit"),
+        HeaderValue::from(decision.limit),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-remaining"),
+        HeaderValue::from(decision.remaining),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-reset"),
+        HeaderValue::from(decision.reset),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mailer::LogMailer,
+        utils::{config_loader::Config, jwt_keys::JwtKeys},
+    };
+    use axum::{Router, middleware, routing::get};
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn config(rate_limit: &str) -> Config {
+        toml::from_str(&format!(
+            r#"
+            database_url = "postgres://localhost/motek_test"
+            jwt_secret = "test-secret"
+            server_address = "127.0.0.1"
+            port = 0
+
+            [rate_limit]
+            {}
+            "#,
+            rate_limit
+        ))
+        .unwrap()
+    }
+
+    fn limiter(rate_limit: &str) -> RateLimiter {
+        RateLimiter::from_config(&config(rate_limit))
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    /// Moves the last update of a bucket `seconds` into the past.
+    async fn age(limiter: &RateLimiter, group: RateLimitGroup, key: ClientKey, seconds: u64) {
+        let mut buckets = limiter.buckets.lock().await;
+        buckets.get_mut(&(group, key)).unwrap().updated -= Duration::from_secs(seconds);
+    }
+
+    #[test]
+    fn public_paths_map_to_groups() {
+        let group = RateLimitGroup::for_public_path;
+        assert_eq!(group("/api/auth/register"), RateLimitGroup::Register);
+        assert_eq!(group("/api/auth/login"), RateLimitGroup::Login);
+        assert_eq!(group("/api/auth/login/mfa"), RateLimitGroup::Mfa);
+        assert_eq!(group("/api/auth/refresh"), RateLimitGroup::Auth);
+        assert_eq!(group("/api/public/notes/x"), RateLimitGroup::Public);
+    }
+
+    #[tokio::test]
+    async fn buckets_are_consumed_and_refill() {
+        let limiter = limiter("[rate_limit.groups.public]\nrequests = 2\nper_seconds = 60");
+        let (group, key) = (RateLimitGroup::Public, ClientKey::Ip(ip("10.0.0.1")));
+
+        let first = limiter.check(group, key).await;
+        assert!(first.allowed);
+        assert_eq!((first.limit, first.remaining, first.reset), (2, 1, 30));
+        let second = limiter.check(group, key).await;
+        assert!(second.allowed);
+        assert_eq!((second.remaining, second.reset), (0, 60));
+        let denied = limiter.check(group, key).await;
+        assert!(!denied.allowed);
+        assert_eq!(denied.retry_after, 30);
+
+        // Other clients and groups have their own buckets
+        assert!(
+            limiter
+                .check(group, ClientKey::Ip(ip("10.0.0.2")))
+                .await
+                .allowed
+        );
+        assert!(limiter.check(RateLimitGroup::Api, key).await.allowed);
+
+        // One token refills every 30 seconds, up to the limit
+        age(&limiter, group, key, 30).await;
+        assert!(limiter.check(group, key).await.allowed);
+        assert!(!limiter.check(group, key).await.allowed);
+        age(&limiter, group, key, 3600).await;
+        let full = limiter.check(group, key).await;
+        assert!(full.allowed);
+        assert_eq!(full.remaining, 1);
+    }
+
+    #[tokio::test]
+    async fn denied_requests_get_retry_after() {
+        let config = config("[rate_limit.groups.public]\nrequests = 1\nper_seconds = 120");
+        let pool = PgPoolOptions::new()
+            .connect_lazy(&config.database_url)
+            .unwrap();
+        let keys = JwtKeys::from_config(&config).unwrap();
+        let state = AppState::new(pool, config, Arc::new(LogMailer), keys);
+        let app = Router::new()
+            .route("/api/public/ping", get(|| async { "pong" }))
+            .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+            .with_state(state);
+        let request = || {
+            let mut request = Request::builder()
+                .uri("/api/public/ping")
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))));
+            request
+        };
+
+        let allowed = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(allowed.status(), StatusCode::OK);
+        assert_eq!(allowed.headers()["ratelimit-limit"], "1");
+        assert_eq!(allowed.headers()["ratelimit-remaining"], "0");
+        assert!(allowed.headers().get(RETRY_AFTER).is_none());
+
+        let denied = app.oneshot(request()).await.unwrap();
+        assert_eq!(denied.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(denied.headers()[RETRY_AFTER], "120");
+        assert_eq!(denied.headers()["ratelimit-reset"], "120");
+    }
+
+    #[test]
+    fn forwarded_for_is_only_trusted_from_proxies() {
+        let limiter = limiter(r#"trusted_proxies = ["10.0.0.1", "10.0.0.2"]"#);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.1.1.1, 2.2.2.2".parse().unwrap());
+        headers.append("x-forwarded-for", "10.0.0.2".parse().unwrap());
+
+        // Spoofed by a client connecting directly
+        assert_eq!(limiter.client_ip(ip("3.3.3.3"), &headers), ip("3.3.3.3"));
+        // The rightmost address not added by a trusted proxy is the client
+        assert_eq!(limiter.client_ip(ip("10.0.0.1"), &headers), ip("2.2.2.2"));
+        // Without the header the proxy is the client
+        assert_eq!(
+            limiter.client_ip(ip("10.0.0.1"), &HeaderMap::new()),
+            ip("10.0.0.1")
+        );
+        // Only proxies in the chain: the first address is the client
+        headers.insert("x-forwarded-for", "10.0.0.2, bogus".parse().unwrap());
+        assert_eq!(limiter.client_ip(ip("10.0.0.1"), &headers), ip("10.0.0.2"));
+    }
+
+    #[tokio::test]
+    async fn full_limiter_evicts_refilled_then_oldest_buckets() {
+        let limiter = limiter(
+            "max_entries = 20\n[rate_limit.groups.public]\nrequests = 10\nper_seconds = 3600",
+        );
+        let group = RateLimitGroup::Public;
+        let key = |i: u8| ClientKey::Ip(IpAddr::from([10, 0, 0, i]));
+        for i in 0..20 {
+            limiter.check(group, key(i)).await;
+        }
+        // Refilled completely, so evicted before any other
+        age(&limiter, group, key(19), 3600).await;
+        for i in 0..18 {
+            age(&limiter, group, key(i), 20 - i as u64).await;
+        }
+
+        limiter.check(group, key(100)).await;
+        let buckets = limiter.buckets.lock().await;
+        // Down to 90% before the new bucket was added
+        assert_eq!(buckets.len(), 19);
+        assert!(!buckets.contains_key(&(group, key(19))));
+        assert!(!buckets.contains_key(&(group, key(0))));
+        assert!(buckets.contains_key(&(group, key(1))));
+        assert!(buckets.contains_key(&(group, key(100))));
+    }
+}
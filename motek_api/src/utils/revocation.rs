@@ -0,0 +1,77 @@
+//! Deny-list of revoked access tokens (JWT `jti`).
+//! Kept in memory so `auth_middleware` can check it without a database round-trip;
+//! the database copy survives restarts and is re-read periodically to pick up
+//! revocations made by other instances.
+
+use chrono::{DateTime, TimeZone, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tracing::info;
+use uuid::Uuid;
+
+/// In-memory deny-list: jti → expiry of the token.
+#[derive(Default)]
+pub struct RevokedTokens {
+    jtis: RwLock<HashMap<Uuid, DateTime<Utc>>>,
+}
+
+impl RevokedTokens {
+    /// Whether an access token has been revoked.
+    pub fn is_revoked(&self, jti: &Uuid) -> bool {
+        self.jtis
+            .read()
+            .map(|jtis| jtis.contains_key(jti))
+            .unwrap_or(false)
+    }
+
+    /// Revokes one access token until it expires (`exp` in seconds since epoch).
+    pub async fn revoke(
+        &self,
+        pool: &PgPool,
+        jti: Uuid,
+        user_id: Uuid,
+        exp: usize,
+    ) -> Result<(), sqlx::Error> {
+        let expires_at = Utc
+            .timestamp_opt(exp as i64, 0)
+            .single()
+            .unwrap_or_else(Utc::now);
+        sqlx::query(
+            "INSERT INTO revoked_access_tokens (jti, user_id, expires_at) VALUES ($1, $2, $3)
+             ON CONFLICT (jti) DO NOTHING",
+        )
+        .bind(jti)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+        if let Ok(mut jtis) = self.jtis.write() {
+            jtis.insert(jti, expires_at);
+        }
+        info!("Revoked access token jti={} of user_id={}", jti, user_id);
+        Ok(())
+    }
+
+    /// Replaces the in-memory list with the unexpired entries from the database.
+    pub async fn reload(&self, pool: &PgPool) -> Result<usize, sqlx::Error> {
+        let rows: Vec<(Uuid, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT jti, expires_at FROM revoked_access_tokens WHERE expires_at > NOW()",
+        )
+        .fetch_all(pool)
+        .await?;
+        let count = rows.len();
+        if let Ok(mut jtis) = self.jtis.write() {
+            *jtis = rows.into_iter().collect();
+        }
+        Ok(count)
+    }
+}
+
+/// Deletes deny-list entries of tokens that have expired anyway.
+pub async fn cleanup_expired_revocations(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query("DELETE FROM revoked_access_tokens WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected())
+}
@@ -0,0 +1,68 @@
+use crate::models::personal_access_token::Scope;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Method, Request, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use tracing::info;
+
+/// Scopes of the personal access token used for the request.
+/// Put into request extensions by `auth_middleware`; absent for JWT sessions,
+/// which have full access.
+#[derive(Clone, Debug)]
+pub struct TokenScopes(pub Vec<Scope>);
+
+/// Scopes required on a router: `read` for GET/HEAD requests, `write` for the rest.
+#[derive(Clone, Copy)]
+pub struct ScopeGuard {
+    pub read: Scope,
+    pub write: Scope,
+}
+
+/// Middleware checking the scopes of personal access tokens. Must run after `auth_middleware`:
+///
+/// ```ignore
+/// router.route_layer(middleware::from_fn_with_state(
+///     ScopeGuard { read: Scope::NotesRead, write: Scope::NotesWrite },
+///     require_scope,
+/// ))
+/// ```
+pub async fn require_scope(
+    State(guard): State<ScopeGuard>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    if let Some(TokenScopes(scopes)) = req.extensions().get::<TokenScopes>() {
+        let needed = match *req.method() {
+            Method::GET | Method::HEAD => guard.read,
+            _ => guard.write,
+        };
+        if !scopes.contains(&needed) {
+            info!(
+                "Access token without scope '{}' denied on {}",
+                needed,
+                req.uri().path()
+            );
+            return Err((StatusCode::FORBIDDEN, "Insufficient token scope"));
+        }
+    }
+    Ok(next.run(req).await)
+}
+
+/// Middleware rejecting personal access tokens, for routes that need a login session
+/// (account, security and token management).
+pub async fn require_session(
+    req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    if req.extensions().get::<TokenScopes>().is_some() {
+        info!(
+            "Access token denied on session-only route {}",
+            req.uri().path()
+        );
+        return Err((StatusCode::FORBIDDEN, "Not available with an access token"));
+    }
+    Ok(next.run(req).await)
+}
@@ -0,0 +1,48 @@
+use totp_rs::{Algorithm, Secret, TOTP};
+
+const ISSUER: &str = "Motek";
+const STEP_SECONDS: u64 = 30;
+
+/// Generates a new random TOTP secret, base32 encoded.
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+/// Builds an RFC 6238 TOTP (SHA-1, 6 digits, 30 s step) for an account.
+fn build(secret: &str, account: &str) -> Result<TOTP, String> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| e.to_string())?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        STEP_SECONDS,
+        bytes,
+        Some(ISSUER.to_string()),
+        account.replace(':', ""),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Returns the `otpauth://` URI for authenticator apps.
+pub fn otpauth_uri(secret: &str, account: &str) -> Result<String, String> {
+    Ok(build(secret, account)?.get_url())
+}
+
+/// Checks a code against the previous, current and next time step.
+/// Returns the matched time step so callers can reject replays.
+pub fn verify_code(
+    secret: &str,
+    account: &str,
+    code: &str,
+    now: u64,
+) -> Result<Option<i64>, String> {
+    let totp = build(secret, account)?;
+    let code = code.trim();
+    let matched = [now.saturating_sub(STEP_SECONDS), now, now + STEP_SECONDS]
+        .into_iter()
+        .find(|&t| totp.generate(t) == code)
+        .map(|t| (t / STEP_SECONDS) as i64);
+    Ok(matched)
+}
@@ -28,4 +28,48 @@ pub fn validate_email(email: &str) -> Result<(), String> {
         return Err("Invalid email address".into());
     }
     Ok(())
-}
\ No newline at end of file
+}
+/// Maximum length of a tag name, in characters.
+pub const MAX_TAG_LENGTH: usize = 64;
+/// Maximum number of tags on one note.
+pub const MAX_TAGS_PER_NOTE: usize = 50;
+
+pub fn validate_tag_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Tag name must not be empty".into());
+    }
+    if name.chars().count() > MAX_TAG_LENGTH {
+        return Err(format!("Tag name must be at most {} characters", MAX_TAG_LENGTH));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("Tag name must not contain control characters".into());
+    }
+    Ok(name.to_string())
+}
+
+/// Validates the tags of a note; returns them trimmed, without case-insensitive duplicates.
+pub fn validate_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut out: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = validate_tag_name(tag)?;
+        if !out.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+            out.push(tag);
+        }
+    }
+    if out.len() > MAX_TAGS_PER_NOTE {
+        return Err(format!("A note can have at most {} tags", MAX_TAGS_PER_NOTE));
+    }
+    Ok(out)
+}
+
+/// Validates a colour in `#RRGGBB` form.
+pub fn validate_color(color: &str) -> Result<(), String> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        return Err("Colour must be in #RRGGBB format".into());
+    }
+    Ok(())
+}
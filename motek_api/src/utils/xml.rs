@@ -0,0 +1,341 @@
+//! Minimal, lenient XML reader for imported documents (Evernote ENEX and ENML).
+//!
+//! Builds a tree of elements and text; CDATA sections become text, comments, processing
+//! instructions and DOCTYPE declarations are skipped. Namespaces are not resolved and
+//! mismatched closing tags close the nearest matching element, so sloppy XHTML still parses.
+//! Nesting is limited to [`MAX_DEPTH`] elements, which bounds the recursion of code
+//! walking the tree (including dropping it).
+
+/// Deepest nesting of elements accepted by [`parse`].
+pub const MAX_DEPTH: usize = 256;
+
+/// Node of a document: an element or a run of text.
+#[derive(Debug, Clone)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Child elements with the given name.
+    pub fn elements<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter_map(move |node| match node {
+            Node::Element(e) if e.name == name => Some(e),
+            _ => None,
+        })
+    }
+
+    /// First child element with the given name.
+    pub fn element(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find_map(|node| match node {
+            Node::Element(e) if e.name == name => Some(e),
+            _ => None,
+        })
+    }
+
+    /// All text inside the element.
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        collect_text(self, &mut out);
+        out
+    }
+}
+
+fn collect_text(element: &Element, out: &mut String) {
+    let mut stack = vec![element.children.iter()];
+    while let Some(nodes) = stack.last_mut() {
+        match nodes.next() {
+            Some(Node::Text(text)) => out.push_str(text),
+            Some(Node::Element(e)) => stack.push(e.children.iter()),
+            None => {
+                stack.pop();
+            }
+        }
+    }
+}
+
+/// Parses a document and returns a synthetic root element holding its top-level nodes.
+pub fn parse(input: &str) -> Result<Element, String> {
+    let mut stack = vec![Element::default()];
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push_text(&mut stack, &decode_entities(rest));
+            break;
+        };
+        if start > 0 {
+            push_text(&mut stack, &decode_entities(&rest[..start]));
+        }
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>").ok_or("Unterminated CDATA section")?;
+            push_text(&mut stack, &after[..end]);
+            rest = &after[end + 3..];
+        } else if let Some(after) = rest.strip_prefix("<!--") {
+            let end = after.find("-->").ok_or("Unterminated comment")?;
+            rest = &after[end + 3..];
+        } else if let Some(after) = rest.strip_prefix("<?") {
+            let end = after
+                .find("?>")
+                .ok_or("Unterminated processing instruction")?;
+            rest = &after[end + 2..];
+        } else if rest.starts_with("<!") {
+            rest = skip_declaration(rest)?;
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').ok_or("Unterminated closing tag")?;
+            let name = after[..end].trim();
+            close_element(&mut stack, name);
+            rest = &after[end + 1..];
+        } else {
+            let end = tag_end(rest).ok_or("Unterminated tag")?;
+            let tag = &rest[1..end];
+            let (tag, self_closing) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let element = parse_tag(tag)?;
+            if self_closing {
+                push_node(&mut stack, Node::Element(element));
+            } else if stack.len() > MAX_DEPTH {
+                return Err("Document is nested too deeply".into());
+            } else {
+                stack.push(element);
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+
+    while stack.len() > 1 {
+        let element = stack.pop().unwrap_or_default();
+        push_node(&mut stack, Node::Element(element));
+    }
+    Ok(stack.pop().unwrap_or_default())
+}
+
+fn push_node(stack: &mut [Element], node: Node) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(node);
+    }
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(parent) = stack.last_mut() {
+        match parent.children.last_mut() {
+            Some(Node::Text(last)) => last.push_str(text),
+            _ => parent.children.push(Node::Text(text.to_string())),
+        }
+    }
+}
+
+/// Closes the innermost open element named `name`; unmatched closing tags are ignored.
+fn close_element(stack: &mut Vec<Element>, name: &str) {
+    let Some(index) = stack.iter().skip(1).rposition(|e| e.name == name) else {
+        return;
+    };
+    while stack.len() > index + 1 {
+        let element = stack.pop().unwrap_or_default();
+        push_node(stack, Node::Element(element));
+    }
+}
+
+/// Skips `<!DOCTYPE …>`, including an internal subset in brackets.
+fn skip_declaration(input: &str) -> Result<&str, String> {
+    let mut depth = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '>' if depth <= 0 => return Ok(&input[i + 1..]),
+            _ => {}
+        }
+    }
+    Err("Unterminated declaration".into())
+}
+
+/// Position of the `>` ending a start tag, ignoring `>` inside quoted attribute values.
+fn tag_end(input: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_tag(tag: &str) -> Result<Element, String> {
+    let tag = tag.trim();
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let name = &tag[..name_end];
+    if name.is_empty() {
+        return Err("Empty tag name".into());
+    }
+
+    let mut attributes = Vec::new();
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+        let Some(after) = rest.strip_prefix('=') else {
+            // Attribute without a value
+            attributes.push((key.to_string(), String::new()));
+            continue;
+        };
+        let after = after.trim_start();
+        let (value, remaining) = match after.chars().next() {
+            Some(q @ ('"' | '\'')) => {
+                let end = after[1..].find(q).ok_or("Unterminated attribute value")?;
+                (&after[1..end + 1], &after[end + 2..])
+            }
+            _ => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        attributes.push((key.to_string(), decode_entities(value)));
+        rest = remaining.trim_start();
+    }
+
+    Ok(Element {
+        name: name.to_string(),
+        attributes,
+        children: Vec::new(),
+    })
+}
+
+/// Replaces XML, common HTML and numeric character references.
+pub fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(
+            decode_entities("a &amp; b &lt;c&gt; &quot;d&quot; &#65;&#x42;&nbsp;"),
+            "a & b <c> \"d\" AB\u{a0}"
+        );
+        // Unknown or unterminated references are kept as written
+        assert_eq!(decode_entities("&unknown; & &#xZZ;"), "&unknown; & &#xZZ;");
+    }
+
+    #[test]
+    fn cdata_becomes_text_and_declarations_are_skipped() {
+        let root = parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE en-export SYSTEM "http://xml.evernote.com/pub/evernote-export4.dtd">
+<en-export><note><title>Fish &amp; chips</title><!-- comment -->
+<content><![CDATA[<en-note><div>a &amp; b</div></en-note>]]></content></note></en-export>"#,
+        )
+        .unwrap();
+        let note = root.element("en-export").unwrap().element("note").unwrap();
+        assert_eq!(note.element("title").unwrap().text(), "Fish & chips");
+        // CDATA is kept verbatim, entities included
+        assert_eq!(
+            note.element("content").unwrap().text(),
+            "<en-note><div>a &amp; b</div></en-note>"
+        );
+    }
+
+    #[test]
+    fn attributes_and_sloppy_markup() {
+        let root = parse(
+            r#"<div><en-todo checked="true"/><a href='x?a=1&amp;b=2' title=t>l</a><p>open</div>"#,
+        )
+        .unwrap();
+        let div = root.element("div").unwrap();
+        assert_eq!(
+            div.element("en-todo").unwrap().attribute("checked"),
+            Some("true")
+        );
+        let a = div.element("a").unwrap();
+        assert_eq!(a.attribute("href"), Some("x?a=1&b=2"));
+        assert_eq!(a.attribute("title"), Some("t"));
+        // The unclosed <p> is closed by </div>
+        assert_eq!(div.element("p").unwrap().text(), "open");
+        assert!(parse("<a><![CDATA[x").is_err());
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = format!(
+            "{}text{}",
+            "<div>".repeat(MAX_DEPTH),
+            "</div>".repeat(MAX_DEPTH)
+        );
+        let mut element = &parse(&nested).unwrap();
+        for _ in 0..MAX_DEPTH {
+            element = element.element("div").unwrap();
+        }
+        assert_eq!(element.text(), "text");
+
+        assert_eq!(
+            parse(&"<div>".repeat(200_000)).unwrap_err(),
+            "Document is nested too deeply"
+        );
+        assert!(parse(&format!("<div>{}", "<br/>".repeat(1000))).is_ok());
+    }
+}
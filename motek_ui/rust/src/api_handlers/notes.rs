@@ -27,7 +27,7 @@ impl<'de> Deserialize<'de> for Note {
             user_id: String,
             notebook_id: Option<String>,  // Handles null
             title: String,
-            content: String,
+            content: serde_json::Value, // Block document; older servers send a string
             is_archived: bool,
             is_pinned: bool,
             tags: serde_json::Value,  // Handles different tag formats
@@ -64,7 +64,7 @@ impl<'de> Deserialize<'de> for Note {
             user_id: helper.user_id,
             notebook_id: helper.notebook_id,
             title: helper.title,
-            content: helper.content,
+            content: content_text(&helper.content),
             is_archived: helper.is_archived,
             is_pinned: helper.is_pinned,
             tags,
@@ -74,9 +74,44 @@ impl<'de> Deserialize<'de> for Note {
     }
 }
 
-/// Fetches the list of all notes for the current user.
+/// Plain text of note content: one line per paragraph, heading, list item or code block.
+fn content_text(content: &serde_json::Value) -> String {
+    let Some(blocks) = content.get("blocks").and_then(|b| b.as_array()) else {
+        return content.as_str().unwrap_or("").to_string();
+    };
+    let mut lines = Vec::new();
+    for block in blocks {
+        match block.get("type").and_then(|t| t.as_str()) {
+            Some("list") => lines.extend(block["items"].as_array().into_iter().flatten().filter_map(|i| i.as_str())),
+            Some("checklist") => lines.extend(block["items"].as_array().into_iter().flatten().filter_map(|i| i["text"].as_str())),
+            Some("code") => lines.extend(block["code"].as_str()),
+            Some("image") => lines.extend(block["caption"].as_str()),
+            Some("link") => lines.extend(block["text"].as_str().or(block["url"].as_str())),
+            _ => lines.extend(block["text"].as_str()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// One page of the notes list returned by the API.
+#[derive(Deserialize, Debug)]
+struct NotesPage {
+    notes: Vec<Note>,
+    next_cursor: Option<String>,
+}
+
+/// Fetches the list of all notes for the current user, following pagination cursors.
 pub async fn list_notes() -> Vec<Note> {
-    authorized_get("/api/notes").await.unwrap_or_default()
+    let mut notes = Vec::new();
+    let mut endpoint = "/api/notes?limit=200".to_string();
+    while let Some(page) = authorized_get::<NotesPage>(&endpoint).await {
+        notes.extend(page.notes);
+        match page.next_cursor {
+            Some(cursor) => endpoint = format!("/api/notes?limit=200&cursor={}", cursor),
+            None => break,
+        }
+    }
+    notes
 }
 
 /// Fetches a single note by its ID.